@@ -0,0 +1,71 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use pg_eviltransform::coord::{self, TransformKind};
+
+fn sample(n: usize) -> (Vec<f64>, Vec<f64>) {
+    let xs = (0..n).map(|i| 100.0 + (i % 400) as f64 * 0.05).collect();
+    let ys = (0..n).map(|i| 20.0 + (i / 400) as f64 * 0.05).collect();
+    (xs, ys)
+}
+
+fn scalar(kind: TransformKind, xs: &mut [f64], ys: &mut [f64]) {
+    for (x, y) in xs.iter_mut().zip(ys.iter_mut()) {
+        let (lat, lng) = coord::apply(kind, *y, *x);
+        *x = lng;
+        *y = lat;
+    }
+}
+
+fn bench_apply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply");
+    for kind in [TransformKind::Wgs2Gcj, TransformKind::Bd2Wgs] {
+        for n in [64, 4096] {
+            let (xs, ys) = sample(n);
+            let id = format!("{kind:?}/{n}");
+            group.bench_with_input(BenchmarkId::new("scalar", &id), &n, |b, _| {
+                let (mut bx, mut by) = (xs.clone(), ys.clone());
+                b.iter(|| {
+                    bx.copy_from_slice(&xs);
+                    by.copy_from_slice(&ys);
+                    scalar(kind, black_box(&mut bx), black_box(&mut by));
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("batch", &id), &n, |b, _| {
+                let (mut bx, mut by) = (xs.clone(), ys.clone());
+                b.iter(|| {
+                    bx.copy_from_slice(&xs);
+                    by.copy_from_slice(&ys);
+                    coord::apply_batch(kind, black_box(&mut bx), black_box(&mut by));
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_ewkb_linestring(c: &mut Criterion) {
+    let (xs, ys) = sample(4096);
+    let mut ewkb = vec![1u8];
+    ewkb.extend_from_slice(&2u32.to_le_bytes());
+    ewkb.extend_from_slice(&(xs.len() as u32).to_le_bytes());
+    for (x, y) in xs.iter().zip(&ys) {
+        ewkb.extend_from_slice(&x.to_le_bytes());
+        ewkb.extend_from_slice(&y.to_le_bytes());
+    }
+
+    c.bench_function("ewkb_linestring/4096", |b| {
+        let mut buf = ewkb.clone();
+        b.iter(|| {
+            buf.copy_from_slice(&ewkb);
+            pg_eviltransform::ewkb::transform_ewkb_in_place(
+                black_box(&mut buf),
+                TransformKind::Wgs2Gcj,
+            )
+            .unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, bench_apply, bench_ewkb_linestring);
+criterion_main!(benches);
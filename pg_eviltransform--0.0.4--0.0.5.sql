@@ -0,0 +1,378 @@
+-- The offset zone is read from eviltransform.offset_zone at call time, so
+-- the overloads that use it are STABLE rather than IMMUTABLE.
+ALTER FUNCTION "st_eviltransform"(anyelement, INT) STABLE;
+ALTER FUNCTION eviltransform_internal."__st_eviltransform_ewkb"(bytea, INT) STABLE;
+-- The text overloads also depend on the eviltransform.*_srid settings.
+ALTER FUNCTION st_eviltransform(geometry, text, text) STABLE;
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"exact" bool /* bool */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_exact_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"exact" bool, /* bool */
+	"tolerance" double precision /* f64 */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_exact_tolerance_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"offset_zone" TEXT /* &str */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_zone_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_isinchinaoffsetzone_internal
+CREATE  FUNCTION eviltransform_internal."__st_isinchinaoffsetzone"(
+	"geom" anyelement, /* AnyElement */
+	"offset_zone" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_isinchinaoffsetzone_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- st_isinchinaoffsetzone_sql
+CREATE FUNCTION ST_IsInChinaOffsetZone(geom geometry, offset_zone text DEFAULT 'border')
+RETURNS boolean
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_isinchinaoffsetzone(geom, offset_zone);
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"z_mode" TEXT, /* &str */
+	"m_mode" TEXT /* &str */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_dims_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"z_mode" TEXT, /* &str */
+	"m_mode" TEXT, /* &str */
+	"curve_mode" TEXT /* &str */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_dims_curve_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform
+CREATE  FUNCTION "st_eviltransform"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"max_segment_error" double precision /* f64 */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_integer_max_error_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_geography_internal
+CREATE  FUNCTION eviltransform_internal."__st_eviltransform_geography"(
+	"geog" anyelement, /* AnyElement */
+	"target" TEXT /* &str */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_geography_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- st_eviltransform_geography_sql
+CREATE FUNCTION st_eviltransform(geog geography, target text)
+RETURNS geography
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_eviltransform_geography(geog, target);
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_raster_internal
+CREATE  FUNCTION eviltransform_internal."__st_eviltransform_raster"(
+	"rast" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"resampling" TEXT /* &str */
+) RETURNS anyelement /* AnyElement */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_raster_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- st_eviltransform_raster_sql
+CREATE FUNCTION ST_EvilCreateRasterOverload()
+RETURNS boolean
+LANGUAGE plpgsql
+VOLATILE
+AS $fn$
+DECLARE
+    ext_schema text;
+    overload regprocedure;
+BEGIN
+    IF to_regtype('raster') IS NULL THEN
+        RETURN false;
+    END IF;
+    SELECT n.nspname INTO ext_schema
+    FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace
+    WHERE e.extname = 'pg_eviltransform';
+    EXECUTE format(
+        $sql$
+        CREATE OR REPLACE FUNCTION %1$I.st_eviltransform(rast %2$s, target_srid integer, resampling text DEFAULT 'nearest')
+        RETURNS %2$s
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_raster(rast, target_srid, resampling);
+        $$
+        $sql$,
+        ext_schema, to_regtype('raster'));
+    overload := format('%I.st_eviltransform(%s, integer, text)', ext_schema, to_regtype('raster'))::regprocedure;
+    IF NOT EXISTS (
+        SELECT FROM pg_depend
+        WHERE classid = 'pg_proc'::regclass AND objid = overload AND deptype = 'e'
+    ) THEN
+        EXECUTE format('ALTER EXTENSION pg_eviltransform ADD FUNCTION %s', overload);
+    END IF;
+    RETURN true;
+END
+$fn$;
+
+SELECT ST_EvilCreateRasterOverload();
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::parse_custom_srid
+CREATE OR REPLACE FUNCTION eviltransform_internal."__parse_custom_srid"(
+	"spec" TEXT /* &str */
+) RETURNS INT /* core::option::Option<i32> */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'parse_custom_srid_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::is_custom_srid
+CREATE  FUNCTION eviltransform_internal."__is_custom_srid"(
+	"srid" INT /* i32 */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'is_custom_srid_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- st_eviltransform_sql (custom SRID aliases)
+CREATE OR REPLACE FUNCTION st_eviltransform(geom geometry, to_proj text)
+RETURNS geometry
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+WITH params AS (
+  SELECT
+    geom,
+    ST_SRID(geom) AS src_srid,
+    eviltransform_internal.__parse_custom_srid(to_proj) AS dst_custom
+)
+SELECT CASE
+  WHEN dst_custom IS NOT NULL THEN st_eviltransform(geom, dst_custom)
+  WHEN eviltransform_internal.__is_custom_srid(src_srid)
+    THEN ST_Transform(st_eviltransform(geom, 4326), to_proj)
+  ELSE ST_Transform(geom, to_proj)
+END
+FROM params;
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- st_eviltransform_sql (custom SRID aliases)
+CREATE OR REPLACE FUNCTION st_eviltransform(geom geometry, from_proj text, to_srid integer)
+RETURNS geometry
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+WITH params AS (
+  SELECT
+    geom,
+    to_srid AS dst_srid,
+    eviltransform_internal.__parse_custom_srid(from_proj) AS src_custom
+)
+SELECT CASE
+  WHEN src_custom IS NULL AND NOT eviltransform_internal.__is_custom_srid(dst_srid)
+    THEN ST_Transform(geom, from_proj, dst_srid)
+  WHEN src_custom IS NOT NULL
+    THEN st_eviltransform(ST_SetSRID(geom, src_custom), dst_srid)
+  ELSE st_eviltransform(ST_Transform(geom, from_proj, 4326), dst_srid)
+END
+FROM params;
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::custom_srid_definitions
+CREATE  FUNCTION eviltransform_internal."__custom_srid_definitions"() RETURNS TABLE (
+	"srid" INT,  /* i32 */
+	"srtext" TEXT,  /* alloc::string::String */
+	"proj4text" TEXT  /* alloc::string::String */
+)
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'custom_srid_definitions_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- spatial_ref_sys_sql
+CREATE FUNCTION ST_EvilRegisterSRIDs()
+RETURNS integer
+LANGUAGE SQL
+VOLATILE
+AS $$
+DELETE FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM';
+WITH inserted AS (
+  INSERT INTO spatial_ref_sys (srid, auth_name, auth_srid, srtext, proj4text)
+  SELECT srid, 'EVILTRANSFORM', srid, srtext, proj4text
+  FROM eviltransform_internal.__custom_srid_definitions()
+  ON CONFLICT (srid) DO NOTHING
+  RETURNING 1
+)
+SELECT count(*)::integer FROM inserted;
+$$;
+
+CREATE FUNCTION ST_EvilUnregisterSRIDs()
+RETURNS integer
+LANGUAGE SQL
+VOLATILE
+AS $$
+WITH deleted AS (
+  DELETE FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM'
+  RETURNING 1
+)
+SELECT count(*)::integer FROM deleted;
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_evilvalidateewkb
+CREATE  FUNCTION "st_evilvalidateewkb"(
+	"ewkb" bytea /* &[u8] */
+) RETURNS TABLE (
+	"valid" bool,  /* bool */
+	"reason" TEXT,  /* core::option::Option<alloc::string::String> */
+	"byte_offset" INT  /* core::option::Option<i32> */
+)
+IMMUTABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_evilvalidateewkb_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_safe_internal
+CREATE  FUNCTION eviltransform_internal."__st_eviltransform_safe"(
+	"geom" anyelement, /* AnyElement */
+	"dst_srid" INT, /* i32 */
+	"on_error" TEXT /* &str */
+) RETURNS anyelement /* core::option::Option<AnyElement> */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_safe_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_safe_ewkb_internal
+CREATE  FUNCTION eviltransform_internal."__st_eviltransform_safe_ewkb"(
+	"ewkb" bytea, /* alloc::vec::Vec<u8> */
+	"dst_srid" INT, /* i32 */
+	"on_error" TEXT /* &str */
+) RETURNS bytea /* core::option::Option<alloc::vec::Vec<u8>> */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_safe_ewkb_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_safe_sql
+CREATE FUNCTION ST_EvilTransformSafe(geom geometry, dst_srid integer, on_error text DEFAULT 'null')
+RETURNS geometry
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_eviltransform_safe(geom, dst_srid, on_error);
+$$;
+
+CREATE FUNCTION ST_EvilTransformSafe(ewkb bytea, dst_srid integer, on_error text DEFAULT 'null')
+RETURNS bytea
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_eviltransform_safe_ewkb(ewkb, dst_srid, on_error);
+$$;
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_geojson_internal
+CREATE  FUNCTION eviltransform_internal."__st_eviltransform_geojson"(
+	"geojson" TEXT, /* &str */
+	"from_proj" TEXT, /* &str */
+	"to_proj" TEXT /* &str */
+) RETURNS TEXT /* alloc::string::String */
+STABLE STRICT PARALLEL SAFE 
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'st_eviltransform_geojson_internal_wrapper';
+/* </end connected objects> */
+
+/* <begin connected objects> */
+-- pg_eviltransform::extension::st_eviltransform_geojson_sql
+CREATE FUNCTION ST_EvilTransformGeoJSON(geojson json, from_proj text, to_proj text)
+RETURNS json
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_eviltransform_geojson(geojson::text, from_proj, to_proj)::json;
+$$;
+
+CREATE FUNCTION ST_EvilTransformGeoJSON(geojson jsonb, from_proj text, to_proj text)
+RETURNS jsonb
+LANGUAGE SQL
+STABLE STRICT PARALLEL SAFE
+AS $$
+SELECT eviltransform_internal.__st_eviltransform_geojson(geojson::text, from_proj, to_proj)::jsonb;
+$$;
+/* </end connected objects> */
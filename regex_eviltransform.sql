@@ -18,7 +18,8 @@ STRICT
 PARALLEL SAFE
 AS $$
 DECLARE
-  a constant double precision := 6378245.0;
+  -- Same constants as coord.rs, so both implementations give the same output.
+  a constant double precision := 6378137.0;
   ee constant double precision := 0.00669342162296594323;
   d_lat double precision;
   d_lng double precision;
@@ -97,6 +98,12 @@ DECLARE
   theta double precision;
   x_pi constant double precision := 3.14159265358979324 * 3000.0 / 180.0;
 BEGIN
+  IF (gcj_lng < 72.004 OR gcj_lng > 137.8347 OR gcj_lat < 0.8293 OR gcj_lat > 55.8271) THEN
+    lng := gcj_lng;
+    lat := gcj_lat;
+    RETURN;
+  END IF;
+
   z := sqrt(power(gcj_lng, 2) + power(gcj_lat, 2)) + 0.00002 * sin(gcj_lat * x_pi);
   theta := atan2(gcj_lat, gcj_lng) + 0.000003 * cos(gcj_lng * x_pi);
   lng := z * cos(theta) + 0.0065;
@@ -123,6 +130,12 @@ DECLARE
   theta double precision;
   x_pi constant double precision := 3.14159265358979324 * 3000.0 / 180.0;
 BEGIN
+  IF (bd_lng < 72.004 OR bd_lng > 137.8347 OR bd_lat < 0.8293 OR bd_lat > 55.8271) THEN
+    lng := bd_lng;
+    lat := bd_lat;
+    RETURN;
+  END IF;
+
   x := bd_lng - 0.0065;
   y := bd_lat - 0.006;
   z := sqrt(power(x, 2) + power(y, 2)) - 0.00002 * sin(y * x_pi);
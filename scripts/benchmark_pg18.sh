@@ -70,7 +70,7 @@ FROM (
   FROM public.bench_points
 ) t;
 
-\\echo benchmark: 990002 -> 3857 (via 4326)
+\\echo benchmark: 4326 -> 990002 -> 3857
 EXPLAIN (ANALYZE, BUFFERS)
 SELECT sum(ST_X(g) + ST_Y(g))
 FROM (
@@ -84,8 +84,89 @@ FROM (
   SELECT Regex_EvilTransform(Regex_EvilTransform(geom, 990002), 3857) AS g
   FROM public.bench_points
 ) t;
+
+DROP TABLE IF EXISTS public.bench_points_bd09;
+CREATE TABLE public.bench_points_bd09 AS
+SELECT id, ST_EvilTransform(geom, 990002) AS geom
+FROM public.bench_points;
+
+ANALYZE public.bench_points_bd09;
+
+\\echo benchmark: 990002 -> 3857 (fused)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_EvilTransform(geom, 3857) AS g
+  FROM public.bench_points_bd09
+) t;
+
+\\echo benchmark: 990002 -> 3857 (via 4326 and ST_Transform)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_Transform(ST_EvilTransform(geom, 4326), 3857) AS g
+  FROM public.bench_points_bd09
+) t;
+
+\\echo benchmark: 990002 -> 4490 (fused)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_EvilTransform(geom, 4490) AS g
+  FROM public.bench_points_bd09
+) t;
+
+\\echo benchmark: 990002 -> 4490 (via 4326 and ST_Transform)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_Transform(ST_EvilTransform(geom, 4326), 4490) AS g
+  FROM public.bench_points_bd09
+) t;
+
+\\echo benchmark: 990002 -> 32650 (fused)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_EvilTransform(geom, 32650) AS g
+  FROM public.bench_points_bd09
+) t;
+
+\\echo benchmark: 990002 -> 32650 (via 4326 and ST_Transform)
+EXPLAIN (ANALYZE, BUFFERS)
+SELECT sum(ST_X(g) + ST_Y(g))
+FROM (
+  SELECT ST_Transform(ST_EvilTransform(geom, 4326), 32650) AS g
+  FROM public.bench_points_bd09
+) t;
 SQL
 
 psql -v ON_ERROR_STOP=1 -f "/tmp/benchmark_pg${PG_MAJOR}.sql" 2>&1 | tee "$REPORT_PATH"
 
+# Pairs each fused scenario with its ST_Transform chain as README table rows.
+awk '
+  /^benchmark: 990002 -> / {
+    target = $4
+    path = ($5 == "(fused)") ? "fused" : "chain"
+    next
+  }
+  /Execution Time:/ && target != "" {
+    time[target, path] = $(NF - 1)
+    if (!(target in seen)) {
+      seen[target] = 1
+      order[++n] = target
+    }
+    target = ""
+  }
+  END {
+    print ""
+    print "| Scenario | fused `ST_EvilTransform` | `ST_Transform(ST_EvilTransform(geom, 4326), ...)` | Speedup (chain / fused) |"
+    print "|---|---:|---:|---:|"
+    for (i = 1; i <= n; i++) {
+      t = order[i]
+      printf "| `990002 -> %s` | `%s ms` | `%s ms` | `%.1fx` |\n", t, time[t, "fused"], time[t, "chain"], time[t, "chain"] / time[t, "fused"]
+    }
+  }
+' "$REPORT_PATH" | tee -a "$REPORT_PATH"
+
 echo "Benchmark report written to $REPORT_PATH"
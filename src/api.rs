@@ -0,0 +1,227 @@
+use crate::coord::{Transform, TransformKind};
+use crate::ewkb::{self, EwkbError};
+use crate::plan::{Planner, Step};
+use crate::srid::{self, CustomSrids, Datum};
+use crate::zone::OffsetZone;
+
+/// Errors returned by the library entry points.
+///
+/// New variants may be added in minor releases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// No single transform between the two datums.
+    UnsupportedDatums { from: Datum, to: Datum },
+    /// The SRIDs are not both custom SRIDs, `4326`, `3857`, `4490` or WGS 84
+    /// UTM, or their datums share no transform.
+    UnsupportedSrids { src_srid: i32, dst_srid: i32 },
+    /// `transform_ewkb` input without an SRID in its header.
+    MissingSrid,
+    /// The input is not valid EWKB or WKB.
+    Ewkb(EwkbError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnsupportedDatums { from, to } => {
+                write!(f, "no transform from {from:?} to {to:?}")
+            }
+            Error::UnsupportedSrids { src_srid, dst_srid } => {
+                write!(f, "no transform from SRID {src_srid} to {dst_srid}")
+            }
+            Error::MissingSrid => write!(f, "EWKB geometry has no SRID"),
+            Error::Ewkb(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Ewkb(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<EwkbError> for Error {
+    fn from(err: EwkbError) -> Self {
+        Error::Ewkb(err)
+    }
+}
+
+/// Settings shared by the library entry points.
+///
+/// The default applies the offsets inside China's bounding rectangle, uses
+/// the fast approximate inverses and the default custom SRIDs
+/// (`990001..=990005`). Start from `Transformer::default()` and change
+/// fields; new fields may be added in minor releases.
+///
+/// ```
+/// use pg_eviltransform::{Datum, OffsetZone, Transformer};
+///
+/// let mut transformer = Transformer::default();
+/// transformer.exact = true;
+/// transformer.zone = OffsetZone::Border;
+/// let (lat, lng) = transformer.transform_point(Datum::Gcj02, Datum::Wgs84, 39.9, 116.4)?;
+/// let (back_lat, back_lng) = transformer.transform_point(Datum::Wgs84, Datum::Gcj02, lat, lng)?;
+/// assert!((back_lat - 39.9).abs() < 1e-9 && (back_lng - 116.4).abs() < 1e-9);
+/// # Ok::<(), pg_eviltransform::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Transformer {
+    /// Invert the GCJ02/BD09 offsets iteratively instead of approximately.
+    pub exact: bool,
+    /// Where the offsets apply; points outside are passed through.
+    pub zone: OffsetZone,
+    /// SRID numbers used by `transform_ewkb`; see `CustomSrids::set`.
+    pub srids: CustomSrids,
+}
+
+impl Transformer {
+    pub(crate) fn transform(&self, from: Datum, to: Datum) -> Result<Transform, Error> {
+        let kind: TransformKind =
+            srid::datum_kind(from, to, self.exact).ok_or(Error::UnsupportedDatums { from, to })?;
+        Ok(Transform::new(kind, self.zone))
+    }
+
+    /// Transforms one coordinate given as `(lat, lng)`, or `(y, x)` metres
+    /// for `Datum::Bd09Mc`, and returns it in the same order.
+    pub fn transform_point(
+        &self,
+        from: Datum,
+        to: Datum,
+        lat: f64,
+        lng: f64,
+    ) -> Result<(f64, f64), Error> {
+        Ok(self.transform(from, to)?.apply(lat, lng))
+    }
+
+    /// Transforms every coordinate of a WKB or EWKB geometry. Z and M values,
+    /// byte order and any SRID are kept.
+    pub fn transform_wkb(&self, wkb: &[u8], from: Datum, to: Datum) -> Result<Vec<u8>, Error> {
+        let transform = self.transform(from, to)?;
+        let mut out = wkb.to_vec();
+        ewkb::transform_ewkb_in_place(&mut out, transform)?;
+        Ok(out)
+    }
+
+    /// Transforms an EWKB geometry from the SRID in its header to `dst_srid`
+    /// in a single pass, as `ST_EvilTransform` does, and sets the new SRID.
+    pub fn transform_ewkb(&self, ewkb: &[u8], dst_srid: i32) -> Result<Vec<u8>, Error> {
+        let src_srid = ewkb::get_srid(ewkb)?.ok_or(Error::MissingSrid)?;
+        let Some(Step::Custom { kind, from, to, .. }) =
+            Planner::new(self.srids).direct(src_srid, dst_srid, self.exact)
+        else {
+            return Err(Error::UnsupportedSrids { src_srid, dst_srid });
+        };
+        let transform = Transform::new(kind, self.zone).with_projections(from, to);
+        let mut out = ewkb.to_vec();
+        ewkb::transform_ewkb_in_place(&mut out, transform)?;
+        ewkb::set_srid(&mut out, dst_srid)?;
+        Ok(out)
+    }
+}
+
+/// Transforms one `(lat, lng)` coordinate with the default settings.
+///
+/// ```
+/// use pg_eviltransform::{Datum, transform_point};
+///
+/// let (lat, lng) = transform_point(Datum::Wgs84, Datum::Gcj02, 30.0, 120.0)?;
+/// assert!((lat - 29.997_534_331_696_1).abs() < 1e-6);
+/// assert!((lng - 120.004_660_445_597).abs() < 1e-6);
+/// # Ok::<(), pg_eviltransform::Error>(())
+/// ```
+pub fn transform_point(from: Datum, to: Datum, lat: f64, lng: f64) -> Result<(f64, f64), Error> {
+    Transformer::default().transform_point(from, to, lat, lng)
+}
+
+/// Transforms a WKB or EWKB geometry between two datums with the default
+/// settings.
+///
+/// ```
+/// use pg_eviltransform::{Datum, transform_wkb};
+///
+/// // POINT(120 30)
+/// let wkb = [
+///     0x01, 0x01, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0x5e, 0x40, 0, 0, 0, 0, 0, 0, 0x3e, 0x40,
+/// ];
+/// let gcj = transform_wkb(&wkb, Datum::Wgs84, Datum::Gcj02)?;
+/// let lng = f64::from_le_bytes(gcj[5..13].try_into().unwrap());
+/// assert!((lng - 120.004_660_445_597).abs() < 1e-6);
+/// assert!(transform_wkb(&wkb[..9], Datum::Wgs84, Datum::Gcj02).is_err());
+/// # Ok::<(), pg_eviltransform::Error>(())
+/// ```
+pub fn transform_wkb(wkb: &[u8], from: Datum, to: Datum) -> Result<Vec<u8>, Error> {
+    Transformer::default().transform_wkb(wkb, from, to)
+}
+
+/// Transforms an EWKB geometry to `dst_srid` with the default settings.
+///
+/// ```
+/// use pg_eviltransform::{Error, transform_ewkb};
+///
+/// // SRID=4326;POINT(120 30)
+/// let ewkb = [
+///     0x01, 0x01, 0x00, 0x00, 0x20, 0xe6, 0x10, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0x5e, 0x40, 0, 0,
+///     0, 0, 0, 0, 0x3e, 0x40,
+/// ];
+/// let gcj = transform_ewkb(&ewkb, 990001)?;
+/// assert_eq!(i32::from_le_bytes(gcj[5..9].try_into().unwrap()), 990001);
+/// assert_eq!(
+///     transform_ewkb(&ewkb, 4214),
+///     Err(Error::UnsupportedSrids { src_srid: 4326, dst_srid: 4214 })
+/// );
+/// # Ok::<(), Error>(())
+/// ```
+pub fn transform_ewkb(ewkb: &[u8], dst_srid: i32) -> Result<Vec<u8>, Error> {
+    Transformer::default().transform_ewkb(ewkb, dst_srid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ewkb_matches_wkb_for_custom_srids() {
+        let point = [
+            0x01, 0x01, 0x00, 0x00, 0x20, 0x31, 0x1b, 0x0f, 0x00, 0, 0, 0, 0, 0, 0, 0x5e, 0x40, 0,
+            0, 0, 0, 0, 0, 0x3e, 0x40,
+        ];
+        let by_srid = transform_ewkb(&point, 990002).unwrap();
+        let by_datum = transform_wkb(&point, Datum::Gcj02, Datum::Bd09).unwrap();
+        assert_eq!(by_srid[9..], by_datum[9..]);
+        assert_eq!(ewkb::get_srid(&by_srid), Ok(Some(990002)));
+        assert_eq!(ewkb::get_srid(&by_datum), Ok(Some(990001)));
+
+        let mut wkb = point.to_vec();
+        wkb[4] = 0;
+        wkb.drain(5..9);
+        assert_eq!(transform_ewkb(&wkb, 990002), Err(Error::MissingSrid));
+    }
+
+    #[test]
+    fn test_projected_srids_use_their_frames() {
+        let mut transformer = Transformer::default();
+        transformer
+            .srids
+            .set("eviltransform.gcj02_srid", 910001)
+            .unwrap();
+        let point = [
+            0x01, 0x01, 0x00, 0x00, 0x20, 0xb1, 0xe2, 0x0d, 0x00, 0, 0, 0, 0, 0, 0, 0x5e, 0x40, 0,
+            0, 0, 0, 0, 0, 0x3e, 0x40,
+        ];
+        let mercator = transformer.transform_ewkb(&point, 3857).unwrap();
+        let x = f64::from_le_bytes(mercator[9..17].try_into().unwrap());
+        let (lat, lng) = transform_point(Datum::Gcj02, Datum::Wgs84, 30.0, 120.0).unwrap();
+        let (_, expected) = crate::coord::Projection::WebMercator.project(lat, lng);
+        assert!((x - expected).abs() < 1e-6, "{x} {expected}");
+        assert!(matches!(
+            transform_ewkb(&point, 3857),
+            Err(Error::UnsupportedSrids { .. })
+        ));
+    }
+}
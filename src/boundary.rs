@@ -0,0 +1,245 @@
+// Simplified outlines of the areas where Chinese map vendors apply the GCJ02
+// offset, as (lng, lat) rings. Land borders follow the line of actual control
+// to roughly 0.1 degree; coastlines are pushed offshore to keep near-shore
+// islands inside. Hong Kong and Macau overlap the mainland ring and are only
+// used to carve them back out.
+
+pub(crate) const MAINLAND: &[(f64, f64)] = &[
+    (122.30, 53.56),
+    (123.50, 53.55),
+    (125.50, 53.10),
+    (126.60, 52.10),
+    (127.50, 50.20),
+    (129.50, 49.40),
+    (130.60, 48.90),
+    (132.50, 47.70),
+    (134.20, 48.40),
+    (134.77, 48.27),
+    (134.20, 47.30),
+    (133.90, 46.20),
+    (133.10, 45.10),
+    (131.90, 45.30),
+    (131.00, 44.85),
+    (131.20, 44.00),
+    (131.30, 43.40),
+    (131.00, 42.90),
+    (130.60, 42.42),
+    (130.20, 42.90),
+    (129.80, 42.95),
+    (129.30, 42.40),
+    (128.90, 42.10),
+    (128.05, 42.00),
+    (128.20, 41.40),
+    (127.30, 41.45),
+    (126.60, 41.70),
+    (126.00, 40.90),
+    (125.00, 40.45),
+    (124.35, 40.05),
+    (124.10, 39.75),
+    (123.30, 39.00),
+    (121.30, 38.50),
+    (122.90, 37.50),
+    (122.70, 36.80),
+    (120.60, 35.50),
+    (120.00, 34.70),
+    (121.00, 33.30),
+    (122.20, 31.80),
+    (123.00, 30.70),
+    (122.80, 29.50),
+    (122.20, 28.50),
+    (121.50, 27.60),
+    (120.70, 26.70),
+    (119.90, 25.40),
+    (119.20, 24.60),
+    (118.90, 24.20),
+    (117.50, 23.30),
+    (116.50, 22.60),
+    (115.30, 22.40),
+    (114.40, 22.00),
+    (113.60, 21.70),
+    (112.60, 21.35),
+    (111.00, 20.80),
+    (110.70, 20.30),
+    (110.00, 20.15),
+    (109.50, 20.60),
+    (108.90, 21.00),
+    (108.00, 21.45),
+    (107.35, 21.60),
+    (107.00, 21.90),
+    (106.72, 22.00),
+    (106.65, 22.35),
+    (106.80, 22.80),
+    (106.20, 22.95),
+    (105.85, 22.95),
+    (105.35, 23.30),
+    (104.80, 23.15),
+    (104.35, 22.75),
+    (103.95, 22.50),
+    (103.50, 22.60),
+    (103.00, 22.45),
+    (102.50, 22.75),
+    (102.14, 22.40),
+    (101.75, 22.50),
+    (101.57, 22.20),
+    (101.78, 21.83),
+    (101.75, 21.15),
+    (101.25, 21.17),
+    (101.15, 21.55),
+    (100.60, 21.45),
+    (100.10, 21.70),
+    (99.95, 22.05),
+    (99.20, 22.10),
+    (99.55, 22.90),
+    (98.90, 23.20),
+    (98.70, 23.95),
+    (97.70, 23.85),
+    (97.55, 24.75),
+    (97.70, 25.05),
+    (98.20, 25.40),
+    (98.70, 25.85),
+    (98.70, 27.50),
+    (98.30, 27.60),
+    (97.85, 28.30),
+    (97.35, 28.20),
+    (96.10, 29.40),
+    (95.40, 29.05),
+    (94.60, 29.20),
+    (93.70, 28.70),
+    (92.60, 27.85),
+    (91.60, 27.95),
+    (90.40, 28.25),
+    (89.60, 28.20),
+    (89.00, 27.30),
+    (88.75, 27.45),
+    (88.80, 28.00),
+    (88.15, 27.90),
+    (86.90, 28.00),
+    (85.90, 28.20),
+    (85.10, 28.70),
+    (84.20, 28.90),
+    (83.50, 29.20),
+    (82.10, 30.30),
+    (81.20, 30.00),
+    (80.20, 30.70),
+    (79.40, 31.00),
+    (78.80, 31.50),
+    (78.75, 32.50),
+    (78.40, 32.60),
+    (79.20, 33.20),
+    (78.20, 34.40),
+    (78.00, 35.30),
+    (77.80, 35.50),
+    (76.80, 35.70),
+    (76.10, 36.00),
+    (75.50, 36.70),
+    (74.90, 37.23),
+    (74.50, 37.40),
+    (74.80, 38.40),
+    (73.80, 38.60),
+    (73.60, 39.40),
+    (73.50, 39.50),
+    (74.00, 40.00),
+    (74.90, 40.50),
+    (76.30, 40.40),
+    (76.90, 41.05),
+    (78.40, 41.40),
+    (79.80, 42.10),
+    (80.20, 42.20),
+    (80.30, 42.90),
+    (80.60, 43.20),
+    (80.40, 44.10),
+    (80.40, 45.00),
+    (81.90, 45.30),
+    (82.60, 45.20),
+    (82.30, 45.50),
+    (83.00, 47.20),
+    (84.90, 47.00),
+    (85.60, 47.10),
+    (85.60, 48.00),
+    (86.60, 48.50),
+    (86.90, 49.10),
+    (87.80, 49.17),
+    (88.10, 48.70),
+    (89.00, 48.00),
+    (90.10, 47.80),
+    (91.00, 46.60),
+    (90.90, 45.90),
+    (91.50, 45.10),
+    (93.50, 44.90),
+    (95.30, 44.20),
+    (96.40, 42.80),
+    (97.20, 42.80),
+    (100.80, 42.65),
+    (101.80, 42.50),
+    (105.00, 41.60),
+    (107.30, 42.40),
+    (110.40, 42.70),
+    (111.90, 43.70),
+    (113.60, 44.70),
+    (115.70, 45.50),
+    (117.40, 46.60),
+    (119.00, 46.70),
+    (119.90, 46.70),
+    (119.70, 47.20),
+    (118.50, 47.90),
+    (117.30, 47.70),
+    (115.90, 47.70),
+    (116.70, 49.85),
+    (117.80, 49.50),
+    (119.20, 50.00),
+    (119.30, 50.40),
+    (120.10, 51.60),
+    (120.80, 52.60),
+    (120.70, 53.20),
+];
+
+pub(crate) const HAINAN: &[(f64, f64)] = &[
+    (108.90, 19.60),
+    (108.55, 19.10),
+    (108.55, 18.40),
+    (109.50, 17.95),
+    (110.20, 18.35),
+    (111.15, 19.60),
+    (111.10, 20.10),
+    (110.60, 20.25),
+    (110.00, 20.25),
+    (109.50, 20.05),
+];
+
+pub(crate) const TAIWAN: &[(f64, f64)] = &[
+    (121.60, 25.45),
+    (122.10, 25.00),
+    (121.90, 24.00),
+    (121.40, 22.70),
+    (120.90, 21.80),
+    (120.60, 21.85),
+    (120.00, 23.00),
+    (119.30, 23.25),
+    (119.30, 23.80),
+    (120.10, 24.50),
+    (121.00, 25.20),
+];
+
+pub(crate) const HONG_KONG: &[(f64, f64)] = &[
+    (113.83, 22.18),
+    (113.84, 22.30),
+    (113.90, 22.39),
+    (113.96, 22.47),
+    (114.03, 22.51),
+    (114.08, 22.53),
+    (114.15, 22.55),
+    (114.23, 22.56),
+    (114.44, 22.56),
+    (114.50, 22.45),
+    (114.45, 22.14),
+    (114.20, 22.12),
+];
+
+pub(crate) const MACAU: &[(f64, f64)] = &[
+    (113.528, 22.215),
+    (113.560, 22.220),
+    (113.600, 22.200),
+    (113.600, 22.110),
+    (113.550, 22.100),
+    (113.530, 22.150),
+];
@@ -1,10 +1,184 @@
 use std::f64::consts::PI;
 
+use crate::zone::OffsetZone;
+
 const EARTH_R: f64 = 6_378_137.0;
 const EE: f64 = 0.006_693_421_622_965_943_23;
 const X_PI: f64 = PI * 3000.0 / 180.0;
 
-#[derive(Clone, Copy, Debug)]
+pub const DEFAULT_EXACT_TOLERANCE: f64 = 1e-12;
+const EXACT_MAX_ITERATIONS: usize = 32;
+
+// Baidu Mercator (BD09MC) is not a true Mercator: Baidu's map API converts
+// BD09 degrees to metres with a separate polynomial per latitude band, and
+// uses another set of fitted polynomials (banded by northing) for the way
+// back. Each row is [x0, x1, y0..y6, scale]; see `mc_polynomial`.
+const MC_BANDS: [f64; 6] = [
+    12_890_594.86,
+    8_362_377.87,
+    5_591_021.0,
+    3_481_989.83,
+    1_678_043.12,
+    0.0,
+];
+const LL_BANDS: [f64; 6] = [75.0, 60.0, 45.0, 30.0, 15.0, 0.0];
+
+const MC2LL: [[f64; 10]; 6] = [
+    [
+        1.410526172116255e-8,
+        0.00000898305509648872,
+        -1.9939833816331,
+        200.9824383106796,
+        -187.2403703815547,
+        91.6087516669843,
+        -23.38765649603339,
+        2.57121317296198,
+        -0.03801003308653,
+        17337981.2,
+    ],
+    [
+        -7.435856389565537e-9,
+        0.000008983055097726239,
+        -0.78625201886289,
+        96.32687599759846,
+        -1.85204757529826,
+        -59.36935905485877,
+        47.40033549296737,
+        -16.50741931063887,
+        2.28786674699375,
+        10260144.86,
+    ],
+    [
+        -3.030883460898826e-8,
+        0.00000898305509983578,
+        0.30071316287616,
+        59.74293618442277,
+        7.357984074871,
+        -25.38371002664745,
+        13.45380521110908,
+        -3.29883767235584,
+        0.32710905363475,
+        6856817.37,
+    ],
+    [
+        -1.981981304930552e-8,
+        0.000008983055099779535,
+        0.03278182852591,
+        40.31678527705744,
+        0.65659298677277,
+        -4.44255534477492,
+        0.85341911805263,
+        0.12923347998204,
+        -0.04625736007561,
+        4482777.06,
+    ],
+    [
+        3.09191371068437e-9,
+        0.000008983055096812155,
+        0.00006995724062,
+        23.10934304144901,
+        -0.00023663490511,
+        -0.6321817810242,
+        -0.00663494467273,
+        0.03430082397953,
+        -0.00466043876332,
+        2555164.4,
+    ],
+    [
+        2.890871144776878e-9,
+        0.000008983055095805407,
+        -3.068298e-8,
+        7.47137025468032,
+        -0.00000353937994,
+        -0.02145144861037,
+        -0.00001234426596,
+        0.00010322952773,
+        -0.00000323890364,
+        826088.5,
+    ],
+];
+
+const LL2MC: [[f64; 10]; 6] = [
+    [
+        -0.0015702102444,
+        111320.7020616939,
+        1704480524535203.0,
+        -10338987376042340.0,
+        26112667856603880.0,
+        -35149669176653700.0,
+        26595700718403920.0,
+        -10725012454188240.0,
+        1800819912950474.0,
+        82.5,
+    ],
+    [
+        0.0008277824516172526,
+        111320.7020463578,
+        647795574.6671607,
+        -4082003173.641316,
+        10774905663.51142,
+        -15171875531.51559,
+        12053065338.62167,
+        -5124939663.577472,
+        913311935.9512032,
+        67.5,
+    ],
+    [
+        0.00337398766765,
+        111320.7020202162,
+        4481351.045890365,
+        -23393751.19931662,
+        79682215.47186455,
+        -115964993.2797253,
+        97236711.15602145,
+        -43661946.33752821,
+        8477230.501135234,
+        52.5,
+    ],
+    [
+        0.00220636496208,
+        111320.7020209128,
+        51751.86112841131,
+        3796837.749470245,
+        992013.7397791013,
+        -1221952.21711287,
+        1340652.697009075,
+        -620943.6990984312,
+        144416.9293806241,
+        37.5,
+    ],
+    [
+        -0.0003441963504368392,
+        111320.7020576856,
+        278.2353980772752,
+        2485758.690035394,
+        6070.750963243378,
+        54821.18345352118,
+        9540.606633304236,
+        -2710.55326746645,
+        1405.483844121726,
+        22.5,
+    ],
+    [
+        -0.0003218135878613132,
+        111320.7020701615,
+        0.00369383431289,
+        823725.6402795718,
+        0.46104986909093,
+        2351.343141331292,
+        1.58060784298199,
+        8.77738589078284,
+        0.37238884252424,
+        7.45,
+    ],
+];
+
+/// A single-pass conversion between two datums.
+///
+/// The `*Exact` kinds invert the offsets iteratively. New kinds may be added
+/// in minor releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum TransformKind {
     Wgs2Gcj,
     Gcj2Wgs,
@@ -12,11 +186,275 @@ pub enum TransformKind {
     Bd2Gcj,
     Wgs2Bd,
     Bd2Wgs,
+    Gcj2WgsExact,
+    Bd2GcjExact,
+    Bd2WgsExact,
+    Bd2Bdmc,
+    Bdmc2Bd,
+    Wgs2Bdmc,
+    Bdmc2Wgs,
+    Gcj2Bdmc,
+    Bdmc2Gcj,
+    Bdmc2WgsExact,
+    Bdmc2GcjExact,
+    // Leaves the datum alone, for transforms that only change projection.
+    Identity,
 }
 
-#[inline(always)]
-fn out_of_china(lat: f64, lng: f64) -> bool {
-    lng < 72.004 || lng > 137.8347 || lat < 0.8293 || lat > 55.8271
+impl TransformKind {
+    // The transform undoing this one; the reverse directions use the
+    // iterative inverses.
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Wgs2Gcj => Self::Gcj2WgsExact,
+            Self::Gcj2Wgs | Self::Gcj2WgsExact => Self::Wgs2Gcj,
+            Self::Gcj2Bd => Self::Bd2GcjExact,
+            Self::Bd2Gcj | Self::Bd2GcjExact => Self::Gcj2Bd,
+            Self::Wgs2Bd => Self::Bd2WgsExact,
+            Self::Bd2Wgs | Self::Bd2WgsExact => Self::Wgs2Bd,
+            Self::Bd2Bdmc => Self::Bdmc2Bd,
+            Self::Bdmc2Bd => Self::Bd2Bdmc,
+            Self::Wgs2Bdmc => Self::Bdmc2WgsExact,
+            Self::Bdmc2Wgs | Self::Bdmc2WgsExact => Self::Wgs2Bdmc,
+            Self::Gcj2Bdmc => Self::Bdmc2GcjExact,
+            Self::Bdmc2Gcj | Self::Bdmc2GcjExact => Self::Gcj2Bdmc,
+            Self::Identity => Self::Identity,
+        }
+    }
+}
+
+// A map projection layered on top of a datum's lng/lat, so that e.g. "GCJ02
+// in Web Mercator" is a datum plus a projection rather than its own kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Projection {
+    #[default]
+    LngLat,
+    WebMercator,
+    // WGS84 UTM, zone 1..=60.
+    Utm {
+        zone: u8,
+        south: bool,
+    },
+}
+
+// Spherical Mercator clips latitude where y would reach +-20037508.34 m.
+// PROJ (and so ST_Transform) does not clamp; the README notes the difference.
+const WEB_MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_59;
+
+impl Projection {
+    // (lat, lng) in degrees to (y, x) in this projection.
+    #[inline(always)]
+    pub fn project(self, lat: f64, lng: f64) -> (f64, f64) {
+        match self {
+            Self::LngLat => (lat, lng),
+            Self::WebMercator => {
+                let lat = lat.clamp(-WEB_MERCATOR_MAX_LAT, WEB_MERCATOR_MAX_LAT);
+                let y = EARTH_R * (PI / 4.0 + lat.to_radians() / 2.0).tan().ln();
+                (y, EARTH_R * lng.to_radians())
+            }
+            Self::Utm { zone, south } => utm_forward(zone, south, lat, lng),
+        }
+    }
+
+    #[inline(always)]
+    pub fn unproject(self, y: f64, x: f64) -> (f64, f64) {
+        match self {
+            Self::LngLat => (y, x),
+            Self::WebMercator => {
+                let lat = 2.0 * (y / EARTH_R).exp().atan() - PI / 2.0;
+                (lat.to_degrees(), (x / EARTH_R).to_degrees())
+            }
+            Self::Utm { zone, south } => utm_inverse(zone, south, y, x),
+        }
+    }
+}
+
+// Transverse Mercator on the WGS84 ellipsoid using Krueger's series to sixth
+// order in n (Karney 2011), the same method as PROJ's default tmerc, so
+// results agree with ST_Transform to well under a millimetre within a zone.
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+const UTM_K0: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+const UTM_FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+
+struct Krueger {
+    e: f64,
+    // Rectifying radius times the UTM scale factor.
+    k0a: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+}
+
+fn krueger() -> &'static Krueger {
+    static COEFFS: std::sync::OnceLock<Krueger> = std::sync::OnceLock::new();
+    COEFFS.get_or_init(|| {
+        let n = WGS84_F / (2.0 - WGS84_F);
+        let [n2, n3, n4, n5, n6] = [n * n, n.powi(3), n.powi(4), n.powi(5), n.powi(6)];
+        let a = EARTH_R / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0);
+        Krueger {
+            e: (WGS84_F * (2.0 - WGS84_F)).sqrt(),
+            k0a: UTM_K0 * a,
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0
+                    + 7891.0 * n6 / 37800.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0
+                    - 1_983_433.0 * n6 / 1_935_360.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0
+                    + 15061.0 * n5 / 26880.0
+                    + 167_603.0 * n6 / 181_440.0,
+                49561.0 * n4 / 161_280.0 - 179.0 * n5 / 168.0 + 6_601_661.0 * n6 / 7_257_600.0,
+                34729.0 * n5 / 80640.0 - 3_418_889.0 * n6 / 1_995_840.0,
+                212_378_941.0 * n6 / 319_334_400.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0
+                    + 96199.0 * n6 / 604_800.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0
+                    - 1_118_711.0 * n6 / 3_870_720.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+                4397.0 * n4 / 161_280.0 - 11.0 * n5 / 504.0 - 830_251.0 * n6 / 7_257_600.0,
+                4583.0 * n5 / 161_280.0 - 108_847.0 * n6 / 3_991_680.0,
+                20_648_693.0 * n6 / 638_668_800.0,
+            ],
+        }
+    })
+}
+
+fn utm_central_meridian(zone: u8) -> f64 {
+    f64::from(zone) * 6.0 - 183.0
+}
+
+fn utm_forward(zone: u8, south: bool, lat: f64, lng: f64) -> (f64, f64) {
+    let k = krueger();
+    let phi = lat.to_radians();
+    let lambda = (lng - utm_central_meridian(zone)).to_radians();
+    // Conformal latitude as tan(chi).
+    let t = (phi.sin().atanh() - k.e * (k.e * phi.sin()).atanh()).sinh();
+    let xi_p = t.atan2(lambda.cos());
+    let eta_p = (lambda.sin() / (1.0 + t * t).sqrt()).atanh();
+    let (mut xi, mut eta) = (xi_p, eta_p);
+    for (j, a) in k.alpha.iter().enumerate() {
+        let m = 2.0 * (j + 1) as f64;
+        xi += a * (m * xi_p).sin() * (m * eta_p).cosh();
+        eta += a * (m * xi_p).cos() * (m * eta_p).sinh();
+    }
+    let false_northing = if south { UTM_FALSE_NORTHING_SOUTH } else { 0.0 };
+    (false_northing + k.k0a * xi, UTM_FALSE_EASTING + k.k0a * eta)
+}
+
+fn utm_inverse(zone: u8, south: bool, northing: f64, easting: f64) -> (f64, f64) {
+    let k = krueger();
+    let false_northing = if south { UTM_FALSE_NORTHING_SOUTH } else { 0.0 };
+    let xi = (northing - false_northing) / k.k0a;
+    let eta = (easting - UTM_FALSE_EASTING) / k.k0a;
+    let (mut xi_p, mut eta_p) = (xi, eta);
+    for (j, b) in k.beta.iter().enumerate() {
+        let m = 2.0 * (j + 1) as f64;
+        xi_p -= b * (m * xi).sin() * (m * eta).cosh();
+        eta_p -= b * (m * xi).cos() * (m * eta).sinh();
+    }
+    let lambda = eta_p.sinh().atan2(xi_p.cos());
+    // tan of the conformal latitude, then Newton's method for tan(phi).
+    let tau_p = xi_p.sin() / (eta_p.sinh().powi(2) + xi_p.cos().powi(2)).sqrt();
+    let one_minus_e2 = 1.0 - k.e * k.e;
+    let mut tau = tau_p;
+    for _ in 0..EXACT_MAX_ITERATIONS {
+        let sigma = (k.e * (k.e * tau / (1.0 + tau * tau).sqrt()).atanh()).sinh();
+        let tau_i = tau * (1.0 + sigma * sigma).sqrt() - sigma * (1.0 + tau * tau).sqrt();
+        let delta = (tau_p - tau_i) / (1.0 + tau_i * tau_i).sqrt()
+            * (1.0 + one_minus_e2 * tau * tau)
+            / (one_minus_e2 * (1.0 + tau * tau).sqrt());
+        tau += delta;
+        if delta.abs() < DEFAULT_EXACT_TOLERANCE {
+            break;
+        }
+    }
+    (
+        tau.atan().to_degrees(),
+        utm_central_meridian(zone) + lambda.to_degrees(),
+    )
+}
+
+/// A `TransformKind` together with the zone it applies in.
+///
+/// Build one with `Transform::new` or from a `TransformKind`, which uses the
+/// default zone, and adjust it with the `with_*` methods.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    pub(crate) kind: TransformKind,
+    pub(crate) zone: OffsetZone,
+    // Input coordinates are unprojected from `from` before `kind` runs, and
+    // the result is projected to `to`.
+    pub(crate) from: Projection,
+    pub(crate) to: Projection,
+    // Convergence tolerance, in degrees, for the exact inverse kinds.
+    pub(crate) tolerance: f64,
+}
+
+impl Transform {
+    /// A lng/lat to lng/lat transform of `kind`, applied inside `zone`.
+    pub fn new(kind: TransformKind, zone: OffsetZone) -> Self {
+        Self {
+            kind,
+            zone,
+            from: Projection::LngLat,
+            to: Projection::LngLat,
+            tolerance: DEFAULT_EXACT_TOLERANCE,
+        }
+    }
+
+    pub(crate) fn with_projections(mut self, from: Projection, to: Projection) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    /// Sets the convergence tolerance, in degrees, of the `*Exact` kinds.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// The transform undoing this one.
+    pub fn inverse(self) -> Self {
+        Self::new(self.kind.inverse(), self.zone)
+            .with_projections(self.to, self.from)
+            .with_tolerance(self.tolerance)
+    }
+
+    pub fn kind(&self) -> TransformKind {
+        self.kind
+    }
+
+    pub fn zone(&self) -> OffsetZone {
+        self.zone
+    }
+
+    /// Transforms one `(lat, lng)` coordinate.
+    #[inline(always)]
+    pub fn apply(self, lat: f64, lng: f64) -> (f64, f64) {
+        let (lat, lng) = self.from.unproject(lat, lng);
+        let (lat, lng) = apply_with(self.kind, self.zone, self.tolerance, lat, lng);
+        self.to.project(lat, lng)
+    }
+
+    /// Transforms `xs[i]`, `ys[i]` (lng, lat) pairs in place.
+    #[inline]
+    pub fn apply_batch(self, xs: &mut [f64], ys: &mut [f64]) {
+        if self.from != Projection::LngLat {
+            map_batch(xs, ys, |y, x| self.from.unproject(y, x));
+        }
+        apply_batch_with(self.kind, self.zone, self.tolerance, xs, ys);
+        if self.to != Projection::LngLat {
+            map_batch(xs, ys, |lat, lng| self.to.project(lat, lng));
+        }
+    }
+}
+
+impl From<TransformKind> for Transform {
+    fn from(kind: TransformKind) -> Self {
+        Self::new(kind, OffsetZone::default())
+    }
 }
 
 #[inline(always)]
@@ -62,7 +500,12 @@ fn delta(lat: f64, lng: f64) -> (f64, f64) {
 
 #[inline(always)]
 pub fn wgs2gcj(lat: f64, lng: f64) -> (f64, f64) {
-    if out_of_china(lat, lng) {
+    wgs2gcj_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn wgs2gcj_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
         return (lat, lng);
     }
     let (d_lat, d_lng) = delta(lat, lng);
@@ -71,7 +514,12 @@ pub fn wgs2gcj(lat: f64, lng: f64) -> (f64, f64) {
 
 #[inline(always)]
 pub fn gcj2wgs(lat: f64, lng: f64) -> (f64, f64) {
-    if out_of_china(lat, lng) {
+    gcj2wgs_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn gcj2wgs_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
         return (lat, lng);
     }
     let (d_lat, d_lng) = delta(lat, lng);
@@ -80,7 +528,12 @@ pub fn gcj2wgs(lat: f64, lng: f64) -> (f64, f64) {
 
 #[inline(always)]
 pub fn gcj2bd(lat: f64, lng: f64) -> (f64, f64) {
-    if out_of_china(lat, lng) {
+    gcj2bd_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn gcj2bd_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
         return (lat, lng);
     }
 
@@ -91,7 +544,12 @@ pub fn gcj2bd(lat: f64, lng: f64) -> (f64, f64) {
 
 #[inline(always)]
 pub fn bd2gcj(lat: f64, lng: f64) -> (f64, f64) {
-    if out_of_china(lat, lng) {
+    bd2gcj_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn bd2gcj_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
         return (lat, lng);
     }
 
@@ -104,25 +562,238 @@ pub fn bd2gcj(lat: f64, lng: f64) -> (f64, f64) {
 
 #[inline(always)]
 pub fn wgs2bd(lat: f64, lng: f64) -> (f64, f64) {
-    let (gcj_lat, gcj_lng) = wgs2gcj(lat, lng);
-    gcj2bd(gcj_lat, gcj_lng)
+    wgs2bd_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn wgs2bd_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    let (gcj_lat, gcj_lng) = wgs2gcj_in(zone, lat, lng);
+    gcj2bd_in(zone, gcj_lat, gcj_lng)
 }
 
 #[inline(always)]
 pub fn bd2wgs(lat: f64, lng: f64) -> (f64, f64) {
-    let (gcj_lat, gcj_lng) = bd2gcj(lat, lng);
-    gcj2wgs(gcj_lat, gcj_lng)
+    bd2wgs_in(OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+fn bd2wgs_in(zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    let (gcj_lat, gcj_lng) = bd2gcj_in(zone, lat, lng);
+    gcj2wgs_in(zone, gcj_lat, gcj_lng)
+}
+
+// The one-step inverses above evaluate the offset at the shifted point, which
+// leaves metre-level residuals. Fixed-point iteration on the forward transform
+// converges quickly because the offset field is smooth.
+#[inline]
+fn invert<F>(lat: f64, lng: f64, tolerance: f64, start: (f64, f64), forward: F) -> (f64, f64)
+where
+    F: Fn(f64, f64) -> (f64, f64),
+{
+    let (mut out_lat, mut out_lng) = start;
+    for _ in 0..EXACT_MAX_ITERATIONS {
+        let (fwd_lat, fwd_lng) = forward(out_lat, out_lng);
+        let d_lat = fwd_lat - lat;
+        let d_lng = fwd_lng - lng;
+        if d_lat.abs() <= tolerance && d_lng.abs() <= tolerance {
+            break;
+        }
+        out_lat -= d_lat;
+        out_lng -= d_lng;
+    }
+    (out_lat, out_lng)
+}
+
+#[inline]
+pub fn gcj2wgs_exact(lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    gcj2wgs_exact_in(OffsetZone::Rectangle, lat, lng, tolerance)
+}
+
+#[inline]
+fn gcj2wgs_exact_in(zone: OffsetZone, lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
+        return (lat, lng);
+    }
+    let start = gcj2wgs_in(zone, lat, lng);
+    invert(lat, lng, tolerance, start, |a, b| wgs2gcj_in(zone, a, b))
+}
+
+#[inline]
+pub fn bd2gcj_exact(lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    bd2gcj_exact_in(OffsetZone::Rectangle, lat, lng, tolerance)
+}
+
+#[inline]
+fn bd2gcj_exact_in(zone: OffsetZone, lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
+        return (lat, lng);
+    }
+    let start = bd2gcj_in(zone, lat, lng);
+    invert(lat, lng, tolerance, start, |a, b| gcj2bd_in(zone, a, b))
+}
+
+#[inline]
+pub fn bd2wgs_exact(lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    bd2wgs_exact_in(OffsetZone::Rectangle, lat, lng, tolerance)
+}
+
+#[inline]
+fn bd2wgs_exact_in(zone: OffsetZone, lat: f64, lng: f64, tolerance: f64) -> (f64, f64) {
+    if !zone.contains(lat, lng) {
+        return (lat, lng);
+    }
+    let start = bd2wgs_in(zone, lat, lng);
+    invert(lat, lng, tolerance, start, |a, b| wgs2bd_in(zone, a, b))
+}
+
+// Returns (y, x) like the degree transforms return (lat, lng).
+#[inline(always)]
+fn mc_polynomial(c: &[f64; 10], y: f64, x: f64) -> (f64, f64) {
+    let t = y.abs() / c[9];
+    let out_y = c[2] + t * (c[3] + t * (c[4] + t * (c[5] + t * (c[6] + t * (c[7] + t * c[8])))));
+    let out_x = c[0] + c[1] * x.abs();
+    (out_y.copysign(y), out_x.copysign(x))
+}
+
+// Baidu picks the band from the signed latitude, so every southern point
+// falls into the equatorial band; bands are chosen by |lat| here instead,
+// which matches Baidu everywhere north of the equator.
+#[inline]
+pub fn bd2bdmc(lat: f64, lng: f64) -> (f64, f64) {
+    let lat = lat.clamp(-74.0, 74.0);
+    let lng = (lng + 180.0).rem_euclid(360.0) - 180.0;
+    let band = LL_BANDS
+        .iter()
+        .position(|&b| lat.abs() >= b)
+        .unwrap_or(LL_BANDS.len() - 1);
+    mc_polynomial(&LL2MC[band], lat, lng)
+}
+
+#[inline]
+pub fn bdmc2bd(y: f64, x: f64) -> (f64, f64) {
+    let band = MC_BANDS
+        .iter()
+        .position(|&b| y.abs() >= b)
+        .unwrap_or(MC_BANDS.len() - 1);
+    mc_polynomial(&MC2LL[band], y, x)
 }
 
 #[inline(always)]
 pub fn apply(kind: TransformKind, lat: f64, lng: f64) -> (f64, f64) {
+    apply_in(kind, OffsetZone::Rectangle, lat, lng)
+}
+
+#[inline(always)]
+pub fn apply_in(kind: TransformKind, zone: OffsetZone, lat: f64, lng: f64) -> (f64, f64) {
+    apply_with(kind, zone, DEFAULT_EXACT_TOLERANCE, lat, lng)
+}
+
+#[inline(always)]
+fn apply_with(
+    kind: TransformKind,
+    zone: OffsetZone,
+    tolerance: f64,
+    lat: f64,
+    lng: f64,
+) -> (f64, f64) {
     match kind {
-        TransformKind::Wgs2Gcj => wgs2gcj(lat, lng),
-        TransformKind::Gcj2Wgs => gcj2wgs(lat, lng),
-        TransformKind::Gcj2Bd => gcj2bd(lat, lng),
-        TransformKind::Bd2Gcj => bd2gcj(lat, lng),
-        TransformKind::Wgs2Bd => wgs2bd(lat, lng),
-        TransformKind::Bd2Wgs => bd2wgs(lat, lng),
+        TransformKind::Wgs2Gcj => wgs2gcj_in(zone, lat, lng),
+        TransformKind::Gcj2Wgs => gcj2wgs_in(zone, lat, lng),
+        TransformKind::Gcj2Bd => gcj2bd_in(zone, lat, lng),
+        TransformKind::Bd2Gcj => bd2gcj_in(zone, lat, lng),
+        TransformKind::Wgs2Bd => wgs2bd_in(zone, lat, lng),
+        TransformKind::Bd2Wgs => bd2wgs_in(zone, lat, lng),
+        TransformKind::Gcj2WgsExact => gcj2wgs_exact_in(zone, lat, lng, tolerance),
+        TransformKind::Bd2GcjExact => bd2gcj_exact_in(zone, lat, lng, tolerance),
+        TransformKind::Bd2WgsExact => bd2wgs_exact_in(zone, lat, lng, tolerance),
+        TransformKind::Bd2Bdmc => bd2bdmc(lat, lng),
+        TransformKind::Bdmc2Bd => bdmc2bd(lat, lng),
+        TransformKind::Wgs2Bdmc => {
+            let (bd_lat, bd_lng) = wgs2bd_in(zone, lat, lng);
+            bd2bdmc(bd_lat, bd_lng)
+        }
+        TransformKind::Bdmc2Wgs => {
+            let (bd_lat, bd_lng) = bdmc2bd(lat, lng);
+            bd2wgs_in(zone, bd_lat, bd_lng)
+        }
+        TransformKind::Gcj2Bdmc => {
+            let (bd_lat, bd_lng) = gcj2bd_in(zone, lat, lng);
+            bd2bdmc(bd_lat, bd_lng)
+        }
+        TransformKind::Bdmc2Gcj => {
+            let (bd_lat, bd_lng) = bdmc2bd(lat, lng);
+            bd2gcj_in(zone, bd_lat, bd_lng)
+        }
+        TransformKind::Bdmc2WgsExact => {
+            let (bd_lat, bd_lng) = bdmc2bd(lat, lng);
+            bd2wgs_exact_in(zone, bd_lat, bd_lng, tolerance)
+        }
+        TransformKind::Bdmc2GcjExact => {
+            let (bd_lat, bd_lng) = bdmc2bd(lat, lng);
+            bd2gcj_exact_in(zone, bd_lat, bd_lng, tolerance)
+        }
+        TransformKind::Identity => (lat, lng),
+    }
+}
+
+// Batch entry points take x (lng) and y (lat) as separate slices so the inner
+// loops see contiguous data. The kind/zone dispatch is hoisted out of the loop
+// and each arm is monomorphised, so the compiler is free to vectorise the
+// arithmetic; the trig calls themselves stay scalar libm calls.
+#[inline(always)]
+fn map_batch<F>(xs: &mut [f64], ys: &mut [f64], f: F)
+where
+    F: Fn(f64, f64) -> (f64, f64),
+{
+    assert_eq!(
+        xs.len(),
+        ys.len(),
+        "x and y slices must have the same length"
+    );
+    for (x, y) in xs.iter_mut().zip(ys.iter_mut()) {
+        let (lat, lng) = f(*y, *x);
+        *x = lng;
+        *y = lat;
+    }
+}
+
+pub fn apply_batch(kind: TransformKind, xs: &mut [f64], ys: &mut [f64]) {
+    apply_batch_in(kind, OffsetZone::Rectangle, xs, ys)
+}
+
+pub fn apply_batch_in(kind: TransformKind, zone: OffsetZone, xs: &mut [f64], ys: &mut [f64]) {
+    apply_batch_with(kind, zone, DEFAULT_EXACT_TOLERANCE, xs, ys)
+}
+
+fn apply_batch_with(
+    kind: TransformKind,
+    zone: OffsetZone,
+    tolerance: f64,
+    xs: &mut [f64],
+    ys: &mut [f64],
+) {
+    match kind {
+        TransformKind::Wgs2Gcj => map_batch(xs, ys, |lat, lng| wgs2gcj_in(zone, lat, lng)),
+        TransformKind::Gcj2Wgs => map_batch(xs, ys, |lat, lng| gcj2wgs_in(zone, lat, lng)),
+        TransformKind::Gcj2Bd => map_batch(xs, ys, |lat, lng| gcj2bd_in(zone, lat, lng)),
+        TransformKind::Bd2Gcj => map_batch(xs, ys, |lat, lng| bd2gcj_in(zone, lat, lng)),
+        TransformKind::Wgs2Bd => map_batch(xs, ys, |lat, lng| wgs2bd_in(zone, lat, lng)),
+        TransformKind::Bd2Wgs => map_batch(xs, ys, |lat, lng| bd2wgs_in(zone, lat, lng)),
+        TransformKind::Gcj2WgsExact => map_batch(xs, ys, |lat, lng| {
+            gcj2wgs_exact_in(zone, lat, lng, tolerance)
+        }),
+        TransformKind::Bd2GcjExact => map_batch(xs, ys, |lat, lng| {
+            bd2gcj_exact_in(zone, lat, lng, tolerance)
+        }),
+        TransformKind::Bd2WgsExact => map_batch(xs, ys, |lat, lng| {
+            bd2wgs_exact_in(zone, lat, lng, tolerance)
+        }),
+        TransformKind::Identity => {}
+        // The Mercator steps branch per point on the band tables, so these
+        // gain nothing from a dedicated loop.
+        _ => map_batch(xs, ys, |lat, lng| {
+            apply_with(kind, zone, tolerance, lat, lng)
+        }),
     }
 }
 
@@ -148,10 +819,216 @@ mod tests {
         assert_nearly(lng, 116.410_369_493_710_29);
     }
 
+    fn sample_points() -> impl Iterator<Item = (f64, f64)> {
+        (0..12).flat_map(|i| {
+            (0..12).map(move |j| (18.0 + f64::from(i) * 3.0, 75.0 + f64::from(j) * 5.0))
+        })
+    }
+
+    fn max_error(a: (f64, f64), b: (f64, f64)) -> f64 {
+        (a.0 - b.0).abs().max((a.1 - b.1).abs())
+    }
+
+    #[test]
+    fn test_gcj2wgs_exact_round_trip() {
+        for (lat, lng) in sample_points() {
+            let (gcj_lat, gcj_lng) = wgs2gcj(lat, lng);
+            let back = gcj2wgs_exact(gcj_lat, gcj_lng, DEFAULT_EXACT_TOLERANCE);
+            assert!(
+                max_error(back, (lat, lng)) < 1e-9,
+                "({lat}, {lng}) -> {back:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bd2wgs_exact_round_trip() {
+        for (lat, lng) in sample_points() {
+            let (bd_lat, bd_lng) = wgs2bd(lat, lng);
+            let back = bd2wgs_exact(bd_lat, bd_lng, DEFAULT_EXACT_TOLERANCE);
+            assert!(
+                max_error(back, (lat, lng)) < 1e-9,
+                "({lat}, {lng}) -> {back:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bd2gcj_exact_round_trip() {
+        for (lat, lng) in sample_points() {
+            let (bd_lat, bd_lng) = gcj2bd(lat, lng);
+            let back = apply(TransformKind::Bd2GcjExact, bd_lat, bd_lng);
+            assert!(
+                max_error(back, (lat, lng)) < 1e-9,
+                "({lat}, {lng}) -> {back:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_exact_inverse_improves_on_single_step() {
+        let (gcj_lat, gcj_lng) = wgs2gcj(39.915, 116.404);
+        let approx = gcj2wgs(gcj_lat, gcj_lng);
+        let exact = apply(TransformKind::Gcj2WgsExact, gcj_lat, gcj_lng);
+        assert!(max_error(approx, (39.915, 116.404)) > 1e-6);
+        assert!(max_error(exact, (39.915, 116.404)) < 1e-9);
+    }
+
+    #[test]
+    fn test_transform_uses_its_tolerance() {
+        let (gcj_lat, gcj_lng) = wgs2gcj(39.915, 116.404);
+        let transform = Transform::new(TransformKind::Gcj2WgsExact, OffsetZone::Rectangle);
+        assert_eq!(
+            transform.apply(gcj_lat, gcj_lng),
+            apply(TransformKind::Gcj2WgsExact, gcj_lat, gcj_lng)
+        );
+        let (mut xs, mut ys) = ([gcj_lng], [gcj_lat]);
+        let loose = transform.with_tolerance(1e-3);
+        loose.apply_batch(&mut xs, &mut ys);
+        assert_eq!(loose.apply(gcj_lat, gcj_lng), (ys[0], xs[0]));
+        assert_eq!(loose.inverse().inverse().tolerance, 1e-3);
+        // The one-step start is already within 1e-3, so no iteration runs.
+        assert_eq!(loose.apply(gcj_lat, gcj_lng), gcj2wgs(gcj_lat, gcj_lng));
+    }
+
+    #[test]
+    fn test_bd2bdmc_reference_point() {
+        let (y, x) = bd2bdmc(39.915, 116.404);
+        assert!((x - 12_958_175.0).abs() < 0.01, "{x}");
+        assert!((y - 4_825_923.77).abs() < 0.01, "{y}");
+    }
+
+    #[test]
+    fn test_bdmc2bd_round_trip() {
+        // Baidu's two polynomial sets are fitted separately and their band
+        // edges disagree slightly, so near 45N the round trip is off by
+        // about 0.3 m.
+        for (lat, lng) in sample_points().chain([(-33.0, 151.0)]) {
+            let (y, x) = bd2bdmc(lat, lng);
+            let back = bdmc2bd(y, x);
+            assert!(
+                max_error(back, (lat, lng)) < 1e-5,
+                "({lat}, {lng}) -> {back:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wgs2bdmc_composes_offset_and_projection() {
+        let (bd_lat, bd_lng) = wgs2bd(39.915, 116.404);
+        let direct = apply(TransformKind::Wgs2Bdmc, 39.915, 116.404);
+        assert_eq!(direct, bd2bdmc(bd_lat, bd_lng));
+        let back = apply(TransformKind::Bdmc2WgsExact, direct.0, direct.1);
+        assert!(max_error(back, (39.915, 116.404)) < 1e-6);
+    }
+
+    #[test]
+    fn test_web_mercator_reference_point() {
+        let (y, x) = Projection::WebMercator.project(30.0, 120.0);
+        assert!((x - 13_358_338.895_192_828).abs() < 1e-6, "{x}");
+        assert!((y - 3_503_549.843_504_374).abs() < 1e-6, "{y}");
+        let (lat, lng) = Projection::WebMercator.unproject(y, x);
+        assert!(max_error((lat, lng), (30.0, 120.0)) < 1e-12);
+    }
+
+    #[test]
+    fn test_utm_reference_points() {
+        for (lat, lng, zone, south, northing, easting) in [
+            (30.0, 120.0, 50, false, 3_322_575.904, 789_409.653),
+            (-33.8688, 151.2093, 56, true, 6_250_948.345, 334_368.634),
+        ] {
+            let utm = Projection::Utm { zone, south };
+            let (y, x) = utm.project(lat, lng);
+            assert!((y - northing).abs() < 1e-3, "{y}");
+            assert!((x - easting).abs() < 1e-3, "{x}");
+            let back = utm.unproject(y, x);
+            assert!(max_error(back, (lat, lng)) < 1e-12, "{back:?}");
+        }
+    }
+
+    #[test]
+    fn test_transform_with_projections_matches_steps() {
+        let merc = Projection::WebMercator;
+        let transform = Transform::from(TransformKind::Wgs2Gcj).with_projections(merc, merc);
+        let (y, x) = merc.project(39.915, 116.404);
+        let (gcj_lat, gcj_lng) = wgs2gcj(39.915, 116.404);
+        let expected = merc.project(gcj_lat, gcj_lng);
+        let got = transform.apply(y, x);
+        assert!(max_error(got, expected) < 1e-6, "{got:?} {expected:?}");
+
+        let (mut xs, mut ys) = (vec![x], vec![y]);
+        transform.apply_batch(&mut xs, &mut ys);
+        assert_eq!((ys[0], xs[0]), got);
+
+        let back = transform.inverse().apply(got.0, got.1);
+        assert!(max_error(back, (y, x)) < 1e-6, "{back:?}");
+    }
+
+    #[test]
+    fn test_inverse_kind_round_trip() {
+        for kind in [
+            TransformKind::Wgs2Gcj,
+            TransformKind::Gcj2Bd,
+            TransformKind::Wgs2Bd,
+        ] {
+            for (lat, lng) in sample_points() {
+                let (y, x) = apply(kind, lat, lng);
+                let back = apply(kind.inverse(), y, x);
+                assert!(max_error(back, (lat, lng)) < 1e-9, "{kind:?}");
+            }
+        }
+    }
+
     #[test]
     fn test_out_of_china_not_changed() {
         let (lat, lng) = wgs2gcj(30.0, -120.0);
         assert_eq!(lat, 30.0);
         assert_eq!(lng, -120.0);
     }
+
+    #[test]
+    fn test_zone_controls_offset() {
+        let ulaanbaatar = (47.92, 106.92);
+        let rect = apply_in(
+            TransformKind::Wgs2Gcj,
+            OffsetZone::Rectangle,
+            ulaanbaatar.0,
+            ulaanbaatar.1,
+        );
+        let border = apply_in(
+            TransformKind::Wgs2Gcj,
+            OffsetZone::Border,
+            ulaanbaatar.0,
+            ulaanbaatar.1,
+        );
+        assert!(max_error(rect, ulaanbaatar) > 1e-4);
+        assert_eq!(border, ulaanbaatar);
+
+        let la = apply_in(TransformKind::Wgs2Bd, OffsetZone::NeverSkip, 34.05, -118.24);
+        assert!(max_error(la, (34.05, -118.24)) > 1e-4);
+    }
+
+    #[test]
+    fn test_apply_batch_matches_scalar() {
+        let kinds = [
+            TransformKind::Wgs2Gcj,
+            TransformKind::Gcj2Wgs,
+            TransformKind::Gcj2Bd,
+            TransformKind::Bd2Gcj,
+            TransformKind::Wgs2Bd,
+            TransformKind::Bd2Wgs,
+            TransformKind::Gcj2WgsExact,
+            TransformKind::Bd2GcjExact,
+            TransformKind::Bd2WgsExact,
+        ];
+        let points: Vec<(f64, f64)> = sample_points().chain([(30.0, -120.0)]).collect();
+        for kind in kinds {
+            let mut xs: Vec<f64> = points.iter().map(|p| p.1).collect();
+            let mut ys: Vec<f64> = points.iter().map(|p| p.0).collect();
+            apply_batch(kind, &mut xs, &mut ys);
+            for (i, &(lat, lng)) in points.iter().enumerate() {
+                assert_eq!((ys[i], xs[i]), apply(kind, lat, lng), "{kind:?}");
+            }
+        }
+    }
 }
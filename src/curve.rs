@@ -0,0 +1,233 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::options::Vertex;
+
+const EPSILON_SQLMM: f64 = 1e-8;
+
+// Upper bound on the pieces one arc is cut into, whatever the tolerance.
+const MAX_ARC_SEGMENTS: usize = 1 << 16;
+
+#[inline]
+pub(crate) fn segment_side(p1: (f64, f64), p2: (f64, f64), q: (f64, f64)) -> i32 {
+    let side = (p2.0 - p1.0) * (q.1 - p1.1) - (q.0 - p1.0) * (p2.1 - p1.1);
+    if side == 0.0 {
+        0
+    } else if side < 0.0 {
+        -1
+    } else {
+        1
+    }
+}
+
+pub(crate) fn arc_center(
+    p1: (f64, f64),
+    p2: (f64, f64),
+    p3: (f64, f64),
+) -> Option<((f64, f64), f64)> {
+    if (p1.0 - p3.0).abs() < EPSILON_SQLMM && (p1.1 - p3.1).abs() < EPSILON_SQLMM {
+        let c = (p1.0 + (p2.0 - p1.0) / 2.0, p1.1 + (p2.1 - p1.1) / 2.0);
+        return Some((c, (c.0 - p1.0).hypot(c.1 - p1.1)));
+    }
+
+    let dx21 = p2.0 - p1.0;
+    let dy21 = p2.1 - p1.1;
+    let dx31 = p3.0 - p1.0;
+    let dy31 = p3.1 - p1.1;
+    let h21 = dx21 * dx21 + dy21 * dy21;
+    let h31 = dx31 * dx31 + dy31 * dy31;
+    let d = 2.0 * (dx21 * dy31 - dx31 * dy21);
+    if d.abs() < EPSILON_SQLMM {
+        return None;
+    }
+
+    let c = (
+        p1.0 + (h21 * dy31 - h31 * dy21) / d,
+        p1.1 - (h21 * dx31 - h31 * dx21) / d,
+    );
+    Some((c, (c.0 - p1.0).hypot(c.1 - p1.1)))
+}
+
+#[inline]
+fn is_closed(p1: (f64, f64), p3: (f64, f64)) -> bool {
+    (p1.0 - p3.0).abs() < EPSILON_SQLMM && (p1.1 - p3.1).abs() < EPSILON_SQLMM
+}
+
+// One circular arc of a CIRCULARSTRING, as an angle range around its centre.
+// `sweep` is signed: positive runs counter-clockwise.
+struct Arc {
+    center: (f64, f64),
+    radius: f64,
+    start: f64,
+    sweep: f64,
+}
+
+impl Arc {
+    // None when the three points are collinear, which SQL/MM treats as two
+    // straight segments.
+    fn new(a1: Vertex, a2: Vertex, a3: Vertex) -> Option<Self> {
+        let p1 = (a1[0], a1[1]);
+        let p2 = (a2[0], a2[1]);
+        let p3 = (a3[0], a3[1]);
+        let (center, radius) = arc_center(p1, p2, p3)?;
+        let angle = |p: (f64, f64)| (p.1 - center.1).atan2(p.0 - center.0);
+        let start = angle(p1);
+        let sweep = if is_closed(p1, p3) {
+            TAU
+        } else if segment_side(p1, p2, p3) > 0 {
+            (angle(p3) - start).rem_euclid(TAU)
+        } else {
+            -(start - angle(p3)).rem_euclid(TAU)
+        };
+        Some(Self {
+            center,
+            radius,
+            start,
+            sweep,
+        })
+    }
+
+    #[inline]
+    fn point_at(&self, t: f64) -> (f64, f64) {
+        let (sin, cos) = (self.start + self.sweep * t).sin_cos();
+        (
+            self.center.0 + self.radius * cos,
+            self.center.1 + self.radius * sin,
+        )
+    }
+
+    // Number of chords keeping every chord within `tolerance` of the arc.
+    fn segments(&self, tolerance: f64) -> usize {
+        let ratio = (1.0 - tolerance / self.radius).max(-1.0);
+        let max_angle = (2.0 * ratio.acos()).min(2.0 * PI / 3.0);
+        if max_angle.is_nan() || max_angle <= 0.0 {
+            return MAX_ARC_SEGMENTS;
+        }
+        ((self.sweep.abs() / max_angle).ceil() as usize).clamp(1, MAX_ARC_SEGMENTS)
+    }
+}
+
+// Replaces the arcs of a CIRCULARSTRING point array by chords, each within
+// `tolerance` of its arc. Z and M are interpolated linearly between the arc
+// end points; collinear triples are kept as two straight segments.
+pub(crate) fn linearize_arcs(points: &[Vertex], tolerance: f64) -> Vec<Vertex> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut out = Vec::with_capacity(points.len());
+    out.push(points[0]);
+    for arc in points.windows(3).step_by(2) {
+        let (a1, a2, a3) = (arc[0], arc[1], arc[2]);
+        match Arc::new(a1, a2, a3) {
+            Some(geom) => {
+                let n = geom.segments(tolerance);
+                for i in 1..n {
+                    let t = i as f64 / n as f64;
+                    let (x, y) = geom.point_at(t);
+                    out.push([
+                        x,
+                        y,
+                        a1[2] + (a3[2] - a1[2]) * t,
+                        a1[3] + (a3[3] - a1[3]) * t,
+                    ]);
+                }
+            }
+            None => out.push(a2),
+        }
+        out.push(a3);
+    }
+    out
+}
+
+// Moves the middle control point of every arc to the arc's true midpoint, so
+// that transforming the three points and fitting a circle through them again
+// follows the transformed arc instead of wherever the control point happened
+// to sit. Z and M of the control points are kept.
+pub(crate) fn center_arc_controls(points: &mut [Vertex]) {
+    let mut i = 0;
+    while i + 2 < points.len() {
+        if let Some(geom) = Arc::new(points[i], points[i + 1], points[i + 2]) {
+            let (x, y) = geom.point_at(0.5);
+            points[i + 1][0] = x;
+            points[i + 1][1] = y;
+        }
+        i += 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xy(x: f64, y: f64) -> Vertex {
+        [x, y, 0.0, 0.0]
+    }
+
+    #[test]
+    fn test_linearize_stays_on_circle() {
+        let arc = [xy(1.0, 0.0), xy(0.0, 1.0), xy(-1.0, 0.0)];
+        let points = linearize_arcs(&arc, 1e-3);
+        assert_eq!(points.first(), Some(&arc[0]));
+        assert_eq!(points.last(), Some(&arc[2]));
+        assert!(points.len() > 10);
+        for p in &points {
+            assert!((p[0].hypot(p[1]) - 1.0).abs() < 1e-12);
+            assert!(p[1] >= 0.0);
+        }
+        for pair in points.windows(2) {
+            let mid = (
+                (pair[0][0] + pair[1][0]) / 2.0,
+                (pair[0][1] + pair[1][1]) / 2.0,
+            );
+            assert!(1.0 - mid.0.hypot(mid.1) <= 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_linearize_follows_direction() {
+        let clockwise = [xy(-1.0, 0.0), xy(0.0, 1.0), xy(1.0, 0.0)];
+        let points = linearize_arcs(&clockwise, 1e-2);
+        assert!(points[1..points.len() - 1].iter().all(|p| p[1] > 0.0));
+
+        let counter_clockwise = [xy(-1.0, 0.0), xy(0.0, -1.0), xy(1.0, 0.0)];
+        let points = linearize_arcs(&counter_clockwise, 1e-2);
+        assert!(points[1..points.len() - 1].iter().all(|p| p[1] < 0.0));
+    }
+
+    #[test]
+    fn test_linearize_full_circle_and_collinear() {
+        let circle = [xy(1.0, 0.0), xy(-1.0, 0.0), xy(1.0, 0.0)];
+        let points = linearize_arcs(&circle, 1e-2);
+        assert!(points.iter().any(|p| p[1] > 0.99));
+        assert!(points.iter().any(|p| p[1] < -0.99));
+
+        let straight = [xy(0.0, 0.0), xy(1.0, 1.0), xy(2.0, 2.0)];
+        assert_eq!(linearize_arcs(&straight, 1e-2), straight.to_vec());
+    }
+
+    #[test]
+    fn test_linearize_interpolates_z_and_m() {
+        let arc = [
+            [1.0, 0.0, 10.0, 0.0],
+            [0.0, 1.0, 99.0, 99.0],
+            [-1.0, 0.0, 20.0, 4.0],
+        ];
+        let points = linearize_arcs(&arc, 1e-1);
+        for pair in points.windows(2) {
+            assert!(pair[1][2] > pair[0][2] && pair[1][3] > pair[0][3]);
+        }
+    }
+
+    #[test]
+    fn test_center_arc_controls() {
+        let mut arc = [
+            xy(1.0, 0.0),
+            xy(0.6, 0.8),
+            xy(-1.0, 0.0),
+            xy(-2.0, 0.0),
+            xy(-3.0, 0.0),
+        ];
+        center_arc_controls(&mut arc);
+        assert!((arc[1][0]).abs() < 1e-12 && (arc[1][1] - 1.0).abs() < 1e-12);
+        assert_eq!(arc[3], xy(-2.0, 0.0));
+    }
+}
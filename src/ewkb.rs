@@ -1,4 +1,9 @@
-use crate::coord::{TransformKind, apply};
+use crate::coord::Transform;
+use crate::curve::{center_arc_controls, linearize_arcs};
+use crate::options::{
+    CurveMode, GeometryOptions, TooManyPoints, Vertex, transform_vertices,
+    transform_vertices_segmentized,
+};
 
 const EWKB_Z: u32 = 0x8000_0000;
 const EWKB_M: u32 = 0x4000_0000;
@@ -6,20 +11,99 @@ const EWKB_SRID: u32 = 0x2000_0000;
 const EWKB_TYPE_MASK: u32 = 0x0000_FFFF;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+// Every error records the byte offset it was found at: the read that ran
+// past the end, the endian marker, the start of the sub-geometry or ring, or
+// the first trailing byte.
 pub enum EwkbError {
-    UnexpectedEof,
-    InvalidEndian(u8),
-    UnsupportedType(u32),
-    TrailingData(usize),
+    UnexpectedEof {
+        offset: usize,
+    },
+    InvalidEndian {
+        offset: usize,
+        marker: u8,
+    },
+    UnsupportedType {
+        offset: usize,
+        gtype: u32,
+    },
+    TrailingData {
+        offset: usize,
+        len: usize,
+    },
+    TooManyPoints {
+        offset: usize,
+    },
+    // Reported by `validate_ewkb` only.
+    DimensionMismatch {
+        offset: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+    NestedSrid {
+        offset: usize,
+    },
+    RingTooShort {
+        offset: usize,
+        points: usize,
+    },
+    RingNotClosed {
+        offset: usize,
+    },
+}
+
+impl EwkbError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            EwkbError::UnexpectedEof { offset }
+            | EwkbError::InvalidEndian { offset, .. }
+            | EwkbError::UnsupportedType { offset, .. }
+            | EwkbError::TrailingData { offset, .. }
+            | EwkbError::TooManyPoints { offset }
+            | EwkbError::DimensionMismatch { offset, .. }
+            | EwkbError::NestedSrid { offset }
+            | EwkbError::RingTooShort { offset, .. }
+            | EwkbError::RingNotClosed { offset } => offset,
+        }
+    }
 }
 
 impl std::fmt::Display for EwkbError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            EwkbError::UnexpectedEof => write!(f, "unexpected end of EWKB"),
-            EwkbError::InvalidEndian(v) => write!(f, "invalid EWKB endian marker: {v}"),
-            EwkbError::UnsupportedType(v) => write!(f, "unsupported EWKB geometry type: {v}"),
-            EwkbError::TrailingData(n) => write!(f, "EWKB has {n} trailing bytes"),
+            EwkbError::UnexpectedEof { offset } => {
+                write!(f, "unexpected end of EWKB at byte {offset}")
+            }
+            EwkbError::InvalidEndian { offset, marker } => {
+                write!(f, "invalid EWKB endian marker {marker} at byte {offset}")
+            }
+            EwkbError::UnsupportedType { offset, gtype } => {
+                write!(f, "unsupported EWKB geometry type {gtype} at byte {offset}")
+            }
+            EwkbError::TrailingData { offset, len } => {
+                write!(f, "EWKB has {len} trailing bytes at byte {offset}")
+            }
+            EwkbError::TooManyPoints { offset } => {
+                write!(f, "EWKB sub-geometry at byte {offset}: {TooManyPoints}")
+            }
+            EwkbError::DimensionMismatch {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "EWKB sub-geometry at byte {offset} is {found}, expected {expected}"
+            ),
+            EwkbError::NestedSrid { offset } => {
+                write!(f, "EWKB sub-geometry at byte {offset} has its own SRID")
+            }
+            EwkbError::RingTooShort { offset, points } => write!(
+                f,
+                "EWKB ring at byte {offset} has {points} points, expected at least 4"
+            ),
+            EwkbError::RingNotClosed { offset } => {
+                write!(f, "EWKB ring at byte {offset} is not closed")
+            }
         }
     }
 }
@@ -34,11 +118,11 @@ enum Endian {
 
 impl Endian {
     #[inline]
-    fn from_marker(marker: u8) -> Result<Self, EwkbError> {
+    fn from_marker(marker: u8, offset: usize) -> Result<Self, EwkbError> {
         match marker {
             0 => Ok(Self::Big),
             1 => Ok(Self::Little),
-            other => Err(EwkbError::InvalidEndian(other)),
+            marker => Err(EwkbError::InvalidEndian { offset, marker }),
         }
     }
 
@@ -58,6 +142,14 @@ impl Endian {
         }
     }
 
+    #[inline]
+    fn write_u32(self, value: u32) -> [u8; 4] {
+        match self {
+            Self::Big => value.to_be_bytes(),
+            Self::Little => value.to_le_bytes(),
+        }
+    }
+
     #[inline]
     fn write_f64(self, value: f64) -> [u8; 8] {
         match self {
@@ -67,10 +159,29 @@ impl Endian {
     }
 }
 
+// The geometry type and Z/M flags of a type word. ISO WKB codes (1001 for
+// POINT Z, 2001 for POINT M, 3001 for POINT ZM, ...) are accepted as well,
+// like PostGIS does.
+#[inline]
+fn decode_type(type_word: u32) -> (u32, bool, bool) {
+    let code = type_word & EWKB_TYPE_MASK;
+    let (gtype, iso_z, iso_m) = match code / 1000 {
+        1 => (code % 1000, true, false),
+        2 => (code % 1000, false, true),
+        3 => (code % 1000, true, true),
+        _ => (code, false, false),
+    };
+    (
+        gtype,
+        iso_z || (type_word & EWKB_Z) != 0,
+        iso_m || (type_word & EWKB_M) != 0,
+    )
+}
+
 #[inline]
 fn ensure_remaining(buf: &[u8], offset: usize, need: usize) -> Result<(), EwkbError> {
     if buf.len().saturating_sub(offset) < need {
-        return Err(EwkbError::UnexpectedEof);
+        return Err(EwkbError::UnexpectedEof { offset });
     }
     Ok(())
 }
@@ -83,6 +194,12 @@ fn read_u8(buf: &[u8], offset: &mut usize) -> Result<u8, EwkbError> {
     Ok(v)
 }
 
+#[inline]
+fn read_endian(buf: &[u8], offset: &mut usize) -> Result<Endian, EwkbError> {
+    let at = *offset;
+    Endian::from_marker(read_u8(buf, offset)?, at)
+}
+
 #[inline]
 fn read_u32(buf: &[u8], offset: &mut usize, endian: Endian) -> Result<u32, EwkbError> {
     ensure_remaining(buf, *offset, 4)?;
@@ -122,14 +239,14 @@ fn transform_coord_tuple(
     endian: Endian,
     has_z: bool,
     has_m: bool,
-    kind: TransformKind,
+    transform: Transform,
 ) -> Result<(), EwkbError> {
     let x_offset = *offset;
     let x = read_f64(buf, offset, endian)?;
     let y_offset = *offset;
     let y = read_f64(buf, offset, endian)?;
 
-    let (lat, lng) = apply(kind, y, x);
+    let (lat, lng) = transform.apply(y, x);
     write_f64(buf, x_offset, endian, lng)?;
     write_f64(buf, y_offset, endian, lat)?;
 
@@ -142,17 +259,38 @@ fn transform_coord_tuple(
     Ok(())
 }
 
+const POINT_BATCH: usize = 64;
+
 fn transform_point_array(
     buf: &mut [u8],
     offset: &mut usize,
     endian: Endian,
     has_z: bool,
     has_m: bool,
-    kind: TransformKind,
+    transform: Transform,
 ) -> Result<(), EwkbError> {
     let npoints = read_u32(buf, offset, endian)? as usize;
-    for _ in 0..npoints {
-        transform_coord_tuple(buf, offset, endian, has_z, has_m, kind)?;
+    let stride = (2 + usize::from(has_z) + usize::from(has_m)) * 8;
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+
+    let mut xs = [0.0; POINT_BATCH];
+    let mut ys = [0.0; POINT_BATCH];
+    let mut remaining = npoints;
+    while remaining > 0 {
+        let n = remaining.min(POINT_BATCH);
+        for i in 0..n {
+            let mut at = *offset + i * stride;
+            xs[i] = read_f64(buf, &mut at, endian)?;
+            ys[i] = read_f64(buf, &mut at, endian)?;
+        }
+        transform.apply_batch(&mut xs[..n], &mut ys[..n]);
+        for i in 0..n {
+            let at = *offset + i * stride;
+            write_f64(buf, at, endian, xs[i])?;
+            write_f64(buf, at + 8, endian, ys[i])?;
+        }
+        *offset += n * stride;
+        remaining -= n;
     }
     Ok(())
 }
@@ -163,11 +301,11 @@ fn transform_polygon(
     endian: Endian,
     has_z: bool,
     has_m: bool,
-    kind: TransformKind,
+    transform: Transform,
 ) -> Result<(), EwkbError> {
     let nrings = read_u32(buf, offset, endian)? as usize;
     for _ in 0..nrings {
-        transform_point_array(buf, offset, endian, has_z, has_m, kind)?;
+        transform_point_array(buf, offset, endian, has_z, has_m, transform)?;
     }
     Ok(())
 }
@@ -176,11 +314,11 @@ fn transform_collection(
     buf: &mut [u8],
     offset: &mut usize,
     endian: Endian,
-    kind: TransformKind,
+    transform: Transform,
 ) -> Result<(), EwkbError> {
     let ngeoms = read_u32(buf, offset, endian)? as usize;
     for _ in 0..ngeoms {
-        transform_geometry(buf, offset, kind)?;
+        transform_geometry(buf, offset, transform)?;
     }
     Ok(())
 }
@@ -188,45 +326,502 @@ fn transform_collection(
 fn transform_geometry(
     buf: &mut [u8],
     offset: &mut usize,
-    kind: TransformKind,
+    transform: Transform,
 ) -> Result<(), EwkbError> {
-    let marker = read_u8(buf, offset)?;
-    let endian = Endian::from_marker(marker)?;
+    let geometry_offset = *offset;
+    let endian = read_endian(buf, offset)?;
 
     let type_word = read_u32(buf, offset, endian)?;
-    let has_z = (type_word & EWKB_Z) != 0;
-    let has_m = (type_word & EWKB_M) != 0;
+    let (gtype, has_z, has_m) = decode_type(type_word);
     let has_srid = (type_word & EWKB_SRID) != 0;
-    let gtype = type_word & EWKB_TYPE_MASK;
 
     if has_srid {
         let _ = read_u32(buf, offset, endian)?;
     }
 
     match gtype {
-        1 => transform_coord_tuple(buf, offset, endian, has_z, has_m, kind),
-        2 | 8 | 13 => transform_point_array(buf, offset, endian, has_z, has_m, kind),
-        3 | 17 => transform_polygon(buf, offset, endian, has_z, has_m, kind),
+        1 => transform_coord_tuple(buf, offset, endian, has_z, has_m, transform),
+        2 | 8 | 13 => transform_point_array(buf, offset, endian, has_z, has_m, transform),
+        3 | 17 => transform_polygon(buf, offset, endian, has_z, has_m, transform),
+        4 | 5 | 6 | 7 | 9 | 10 | 11 | 12 | 14 | 15 | 16 => {
+            transform_collection(buf, offset, endian, transform)
+        }
+        _ => Err(EwkbError::UnsupportedType {
+            offset: geometry_offset,
+            gtype,
+        }),
+    }
+}
+
+pub fn transform_ewkb_in_place(
+    buf: &mut [u8],
+    transform: impl Into<Transform>,
+) -> Result<(), EwkbError> {
+    let transform = transform.into();
+    let mut offset = 0usize;
+    transform_geometry(buf, &mut offset, transform)?;
+    if offset != buf.len() {
+        return Err(EwkbError::TrailingData {
+            offset,
+            len: buf.len() - offset,
+        });
+    }
+    Ok(())
+}
+
+// The SRID of the top-level geometry, if its header carries one.
+pub fn get_srid(buf: &[u8]) -> Result<Option<i32>, EwkbError> {
+    let mut offset = 0usize;
+    let endian = read_endian(buf, &mut offset)?;
+    let type_word = read_u32(buf, &mut offset, endian)?;
+    if type_word & EWKB_SRID == 0 {
+        return Ok(None);
+    }
+    Ok(Some(read_u32(buf, &mut offset, endian)? as i32))
+}
+
+pub fn geometry_type(buf: &[u8]) -> Result<u32, EwkbError> {
+    let mut offset = 0usize;
+    let endian = read_endian(buf, &mut offset)?;
+    Ok(decode_type(read_u32(buf, &mut offset, endian)?).0)
+}
+
+// The name ST_GeometryType reports for a WKB type code, without its "ST_"
+// prefix. These differ from the GSERIALIZED codes from 13 on.
+pub fn type_name(gtype: u32) -> Option<&'static str> {
+    Some(match gtype {
+        1 => "Point",
+        2 => "LineString",
+        3 => "Polygon",
+        4 => "MultiPoint",
+        5 => "MultiLineString",
+        6 => "MultiPolygon",
+        7 => "GeometryCollection",
+        8 => "CircularString",
+        9 => "CompoundCurve",
+        10 => "CurvePolygon",
+        11 => "MultiCurve",
+        12 => "MultiSurface",
+        15 => "PolyhedralSurface",
+        16 => "Tin",
+        17 => "Triangle",
+        _ => return None,
+    })
+}
+
+// Sets the top-level SRID, adding it to the header when it has none.
+pub fn set_srid(buf: &mut Vec<u8>, srid: i32) -> Result<(), EwkbError> {
+    let mut offset = 0usize;
+    let endian = read_endian(buf, &mut offset)?;
+    let type_word = read_u32(buf, &mut offset, endian)?;
+    if type_word & EWKB_SRID == 0 {
+        buf[1..5].copy_from_slice(&endian.write_u32(type_word | EWKB_SRID));
+        buf.splice(5..5, endian.write_u32(srid as u32));
+    } else {
+        ensure_remaining(buf, 5, 4)?;
+        buf[5..9].copy_from_slice(&endian.write_u32(srid as u32));
+    }
+    Ok(())
+}
+
+fn dims_name(has_z: bool, has_m: bool) -> &'static str {
+    match (has_z, has_m) {
+        (false, false) => "XY",
+        (true, false) => "XYZ",
+        (false, true) => "XYM",
+        (true, true) => "XYZM",
+    }
+}
+
+// A ring must have at least four points and end where it starts; Z takes
+// part in the comparison, M does not.
+fn validate_ring(
+    buf: &[u8],
+    offset: &mut usize,
+    endian: Endian,
+    has_z: bool,
+    has_m: bool,
+) -> Result<(), EwkbError> {
+    let ring_offset = *offset;
+    let npoints = read_u32(buf, offset, endian)? as usize;
+    let ordinates = 2 + usize::from(has_z) + usize::from(has_m);
+    let stride = ordinates * 8;
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+    if npoints < 4 {
+        return Err(EwkbError::RingTooShort {
+            offset: ring_offset,
+            points: npoints,
+        });
+    }
+    let compared = 2 + usize::from(has_z);
+    let last = *offset + (npoints - 1) * stride;
+    for i in 0..compared {
+        let first = read_f64(buf, &mut (*offset + i * 8), endian)?;
+        let end = read_f64(buf, &mut (last + i * 8), endian)?;
+        if first != end {
+            return Err(EwkbError::RingNotClosed {
+                offset: ring_offset,
+            });
+        }
+    }
+    *offset += npoints * stride;
+    Ok(())
+}
+
+fn validate_geometry(
+    buf: &[u8],
+    offset: &mut usize,
+    parent_dims: Option<(bool, bool)>,
+) -> Result<(), EwkbError> {
+    let geometry_offset = *offset;
+    let endian = read_endian(buf, offset)?;
+    let type_word = read_u32(buf, offset, endian)?;
+    let (gtype, has_z, has_m) = decode_type(type_word);
+
+    if type_word & EWKB_SRID != 0 {
+        if parent_dims.is_some() {
+            return Err(EwkbError::NestedSrid {
+                offset: geometry_offset,
+            });
+        }
+        skip_bytes(buf, offset, 4)?;
+    }
+    if let Some((parent_z, parent_m)) = parent_dims
+        && (parent_z, parent_m) != (has_z, has_m)
+    {
+        return Err(EwkbError::DimensionMismatch {
+            offset: geometry_offset,
+            expected: dims_name(parent_z, parent_m),
+            found: dims_name(has_z, has_m),
+        });
+    }
+
+    let stride = (2 + usize::from(has_z) + usize::from(has_m)) * 8;
+    match gtype {
+        1 => skip_bytes(buf, offset, stride),
+        2 | 8 | 13 => {
+            let npoints = read_u32(buf, offset, endian)? as usize;
+            skip_bytes(buf, offset, npoints.saturating_mul(stride))
+        }
+        3 | 17 => {
+            let nrings = read_u32(buf, offset, endian)? as usize;
+            for _ in 0..nrings {
+                validate_ring(buf, offset, endian, has_z, has_m)?;
+            }
+            Ok(())
+        }
         4 | 5 | 6 | 7 | 9 | 10 | 11 | 12 | 14 | 15 | 16 => {
-            transform_collection(buf, offset, endian, kind)
+            let ngeoms = read_u32(buf, offset, endian)? as usize;
+            for _ in 0..ngeoms {
+                validate_geometry(buf, offset, Some((has_z, has_m)))?;
+            }
+            Ok(())
         }
-        _ => Err(EwkbError::UnsupportedType(gtype)),
+        _ => Err(EwkbError::UnsupportedType {
+            offset: geometry_offset,
+            gtype,
+        }),
     }
 }
 
-pub fn transform_ewkb_in_place(buf: &mut [u8], kind: TransformKind) -> Result<(), EwkbError> {
+// Strict structural check for diagnosing bad imports. Unlike the transform
+// walkers, which only need each sub-geometry to be readable, this rejects
+// sub-geometries whose Z/M flags differ from their parent, SRIDs below the
+// top level, and polygon or triangle rings that are short or not closed.
+// Mixed byte orders are valid EWKB and are accepted.
+pub fn validate_ewkb(buf: &[u8]) -> Result<(), EwkbError> {
     let mut offset = 0usize;
-    transform_geometry(buf, &mut offset, kind)?;
+    validate_geometry(buf, &mut offset, None)?;
     if offset != buf.len() {
-        return Err(EwkbError::TrailingData(buf.len() - offset));
+        return Err(EwkbError::TrailingData {
+            offset,
+            len: buf.len() - offset,
+        });
     }
     Ok(())
 }
 
+// The rewriting walker below produces a new buffer, for options that change
+// the size of the geometry (dropping Z/M, adding M). Each sub-geometry keeps
+// its own byte order.
+fn read_vertices(
+    buf: &[u8],
+    offset: &mut usize,
+    endian: Endian,
+    has_z: bool,
+    has_m: bool,
+    npoints: usize,
+) -> Result<Vec<Vertex>, EwkbError> {
+    let stride = (2 + usize::from(has_z) + usize::from(has_m)) * 8;
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+    let mut points = Vec::with_capacity(npoints);
+    for _ in 0..npoints {
+        let mut p = [0.0; 4];
+        p[0] = read_f64(buf, offset, endian)?;
+        p[1] = read_f64(buf, offset, endian)?;
+        if has_z {
+            p[2] = read_f64(buf, offset, endian)?;
+        }
+        if has_m {
+            p[3] = read_f64(buf, offset, endian)?;
+        }
+        points.push(p);
+    }
+    Ok(points)
+}
+
+fn write_vertices(out: &mut Vec<u8>, endian: Endian, has_z: bool, has_m: bool, points: &[Vertex]) {
+    for p in points {
+        out.extend_from_slice(&endian.write_f64(p[0]));
+        out.extend_from_slice(&endian.write_f64(p[1]));
+        if has_z {
+            out.extend_from_slice(&endian.write_f64(p[2]));
+        }
+        if has_m {
+            out.extend_from_slice(&endian.write_f64(p[3]));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rewrite<'a> {
+    transform: Transform,
+    options: &'a GeometryOptions,
+}
+
+struct Header {
+    offset: usize,
+    marker: u8,
+    endian: Endian,
+    type_word: u32,
+    dims: (bool, bool),
+    srid: Option<u32>,
+    gtype: u32,
+}
+
+fn read_header(buf: &[u8], offset: &mut usize) -> Result<Header, EwkbError> {
+    let start = *offset;
+    let marker = read_u8(buf, offset)?;
+    let endian = Endian::from_marker(marker, start)?;
+    let type_word = read_u32(buf, offset, endian)?;
+    let srid = if (type_word & EWKB_SRID) != 0 {
+        Some(read_u32(buf, offset, endian)?)
+    } else {
+        None
+    };
+    let (gtype, has_z, has_m) = decode_type(type_word);
+    Ok(Header {
+        offset: start,
+        marker,
+        endian,
+        type_word,
+        dims: (has_z, has_m),
+        srid,
+        gtype,
+    })
+}
+
+fn write_header(out: &mut Vec<u8>, header: &Header, gtype: u32, rewrite: Rewrite<'_>) {
+    let (out_z, out_m) = rewrite.options.output_dims(header.dims.0, header.dims.1);
+    let mut out_word = (header.type_word & !(EWKB_Z | EWKB_M | EWKB_TYPE_MASK)) | gtype;
+    if out_z {
+        out_word |= EWKB_Z;
+    }
+    if out_m {
+        out_word |= EWKB_M;
+    }
+    out.push(header.marker);
+    out.extend_from_slice(&header.endian.write_u32(out_word));
+    if let Some(srid) = header.srid {
+        out.extend_from_slice(&header.endian.write_u32(srid));
+    }
+}
+
+// Writes the point count and the transformed points; the count is only known
+// here because segmentizing a linear array adds vertices.
+fn write_point_array(
+    out: &mut Vec<u8>,
+    header: &Header,
+    points: &mut Vec<Vertex>,
+    linear: bool,
+    rewrite: Rewrite<'_>,
+) -> Result<(), EwkbError> {
+    transform_vertices_segmentized(points, Some(rewrite.transform), rewrite.options, linear)
+        .map_err(|TooManyPoints| EwkbError::TooManyPoints {
+            offset: header.offset,
+        })?;
+    let (out_z, out_m) = rewrite.options.output_dims(header.dims.0, header.dims.1);
+    out.extend_from_slice(&header.endian.write_u32(points.len() as u32));
+    write_vertices(out, header.endian, out_z, out_m, points);
+    Ok(())
+}
+
+fn rewrite_point_array(
+    buf: &[u8],
+    offset: &mut usize,
+    out: &mut Vec<u8>,
+    header: &Header,
+    rewrite: Rewrite<'_>,
+) -> Result<(), EwkbError> {
+    let (has_z, has_m) = header.dims;
+    let npoints = read_u32(buf, offset, header.endian)? as usize;
+    let mut points = read_vertices(buf, offset, header.endian, has_z, has_m, npoints)?;
+    let arcs = header.gtype == 8;
+    if arcs && rewrite.options.curve == CurveMode::Refit {
+        center_arc_controls(&mut points);
+    }
+    write_point_array(out, header, &mut points, !arcs, rewrite)
+}
+
+// Reads the body of a LINESTRING, CIRCULARSTRING or COMPOUNDCURVE as one
+// linear point array, with arcs replaced by chords.
+fn read_linearized(
+    buf: &[u8],
+    offset: &mut usize,
+    header: &Header,
+    tolerance: f64,
+) -> Result<Vec<Vertex>, EwkbError> {
+    let (has_z, has_m) = header.dims;
+    let n = read_u32(buf, offset, header.endian)? as usize;
+    match header.gtype {
+        2 => read_vertices(buf, offset, header.endian, has_z, has_m, n),
+        8 => {
+            let points = read_vertices(buf, offset, header.endian, has_z, has_m, n)?;
+            Ok(linearize_arcs(&points, tolerance))
+        }
+        9 => {
+            let mut points: Vec<Vertex> = Vec::new();
+            for _ in 0..n {
+                let part_header = read_header(buf, offset)?;
+                let part = read_linearized(buf, offset, &part_header, tolerance)?;
+                // Consecutive parts share their end points.
+                let skip = usize::from(!points.is_empty() && !part.is_empty());
+                points.extend_from_slice(&part[skip..]);
+            }
+            Ok(points)
+        }
+        gtype => Err(EwkbError::UnsupportedType {
+            offset: header.offset,
+            gtype,
+        }),
+    }
+}
+
+// The geometry type written in place of a curve type when arcs are densified.
+fn linear_type(gtype: u32) -> u32 {
+    match gtype {
+        8 | 9 => 2,
+        10 => 3,
+        11 => 5,
+        12 => 6,
+        other => other,
+    }
+}
+
+fn rewrite_geometry(
+    buf: &[u8],
+    offset: &mut usize,
+    out: &mut Vec<u8>,
+    rewrite: Rewrite<'_>,
+) -> Result<(), EwkbError> {
+    let header = read_header(buf, offset)?;
+    let endian = header.endian;
+    let gtype = header.gtype;
+
+    if let CurveMode::Densify(tolerance) = rewrite.options.curve {
+        match gtype {
+            8 | 9 => {
+                let mut points = read_linearized(buf, offset, &header, tolerance)?;
+                write_header(out, &header, 2, rewrite);
+                write_point_array(out, &header, &mut points, true, rewrite)?;
+                return Ok(());
+            }
+            10 => {
+                let nrings = read_u32(buf, offset, endian)?;
+                let mut rings = Vec::new();
+                for _ in 0..nrings {
+                    let ring_header = read_header(buf, offset)?;
+                    rings.push(read_linearized(buf, offset, &ring_header, tolerance)?);
+                }
+                write_header(out, &header, 3, rewrite);
+                out.extend_from_slice(&endian.write_u32(nrings));
+                for ring in &mut rings {
+                    write_point_array(out, &header, ring, true, rewrite)?;
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+    let out_type = match rewrite.options.curve {
+        CurveMode::Densify(_) => linear_type(gtype),
+        _ => gtype,
+    };
+    write_header(out, &header, out_type, rewrite);
+
+    let count = |buf: &[u8], offset: &mut usize, out: &mut Vec<u8>| {
+        let n = read_u32(buf, offset, endian)?;
+        out.extend_from_slice(&endian.write_u32(n));
+        Ok::<usize, EwkbError>(n as usize)
+    };
+
+    match gtype {
+        1 => {
+            let (has_z, has_m) = header.dims;
+            let mut points = read_vertices(buf, offset, endian, has_z, has_m, 1)?;
+            transform_vertices(&mut points, Some(rewrite.transform), rewrite.options);
+            let (out_z, out_m) = rewrite.options.output_dims(has_z, has_m);
+            write_vertices(out, endian, out_z, out_m, &points);
+            Ok(())
+        }
+        2 | 8 | 13 => rewrite_point_array(buf, offset, out, &header, rewrite),
+        3 | 17 => {
+            let nrings = count(buf, offset, out)?;
+            for _ in 0..nrings {
+                rewrite_point_array(buf, offset, out, &header, rewrite)?;
+            }
+            Ok(())
+        }
+        4 | 5 | 6 | 7 | 9 | 10 | 11 | 12 | 14 | 15 | 16 => {
+            let ngeoms = count(buf, offset, out)?;
+            for _ in 0..ngeoms {
+                rewrite_geometry(buf, offset, out, rewrite)?;
+            }
+            Ok(())
+        }
+        _ => Err(EwkbError::UnsupportedType {
+            offset: header.offset,
+            gtype,
+        }),
+    }
+}
+
+pub fn transform_ewkb(
+    input: &[u8],
+    transform: impl Into<Transform>,
+    options: &GeometryOptions,
+) -> Result<Vec<u8>, EwkbError> {
+    let rewrite = Rewrite {
+        transform: transform.into(),
+        options,
+    };
+    let mut offset = 0usize;
+    let mut out = Vec::with_capacity(input.len());
+    rewrite_geometry(input, &mut offset, &mut out, rewrite)?;
+    if offset != input.len() {
+        return Err(EwkbError::TrailingData {
+            offset,
+            len: input.len() - offset,
+        });
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::coord::TransformKind;
+    use crate::options::{CurveMode, MMode, ZMode};
 
     fn hex_to_bytes(hex: &str) -> Vec<u8> {
         let mut out = Vec::with_capacity(hex.len() / 2);
@@ -258,10 +853,723 @@ mod tests {
         assert!((lat - 29.997_534_331_696_1).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_transform_iso_wkb_z() {
+        // ISO POINT Z (type 1001) and its EWKB spelling.
+        let mut iso = hex_to_bytes("01E90300000000000000005E400000000000003E400000000000005940");
+        let mut ewkb = hex_to_bytes("01010000800000000000005E400000000000003E400000000000005940");
+        transform_ewkb_in_place(&mut iso, TransformKind::Wgs2Gcj).unwrap();
+        transform_ewkb_in_place(&mut ewkb, TransformKind::Wgs2Gcj).unwrap();
+        assert_eq!(iso[5..], ewkb[5..]);
+        assert_eq!(read_le_f64(&iso, 21), 100.0);
+        assert_eq!(geometry_type(&iso), Ok(1));
+        assert_eq!(validate_ewkb(&iso), Ok(()));
+    }
+
+    #[test]
+    fn test_get_and_set_srid() {
+        let mut ewkb = hex_to_bytes("01010000000000000000005E400000000000003E40");
+        assert_eq!(get_srid(&ewkb), Ok(None));
+        set_srid(&mut ewkb, 4326).unwrap();
+        assert_eq!(get_srid(&ewkb), Ok(Some(4326)));
+        assert_eq!(read_le_f64(&ewkb, 9), 120.0);
+        set_srid(&mut ewkb, 990001).unwrap();
+        assert_eq!(get_srid(&ewkb), Ok(Some(990001)));
+        assert_eq!(ewkb.len(), 25);
+        assert_eq!(
+            get_srid(&ewkb[..7]),
+            Err(EwkbError::UnexpectedEof { offset: 5 })
+        );
+        assert_eq!(geometry_type(&ewkb), Ok(1));
+    }
+
+    #[test]
+    fn test_type_name_uses_wkb_codes() {
+        assert_eq!(type_name(1), Some("Point"));
+        assert_eq!(type_name(15), Some("PolyhedralSurface"));
+        assert_eq!(type_name(16), Some("Tin"));
+        assert_eq!(type_name(17), Some("Triangle"));
+        assert_eq!(type_name(13), None);
+        assert_eq!(type_name(99), None);
+    }
+
+    // A little-endian polygon with one ring of `points` (x, y) pairs.
+    fn polygon(type_word: u32, points: &[(f64, f64)]) -> Vec<u8> {
+        let mut ewkb = vec![1u8];
+        ewkb.extend_from_slice(&type_word.to_le_bytes());
+        ewkb.extend_from_slice(&1u32.to_le_bytes());
+        ewkb.extend_from_slice(&(points.len() as u32).to_le_bytes());
+        for &(x, y) in points {
+            ewkb.extend_from_slice(&x.to_le_bytes());
+            ewkb.extend_from_slice(&y.to_le_bytes());
+        }
+        ewkb
+    }
+
+    fn collection(type_word: u32, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut ewkb = vec![1u8];
+        ewkb.extend_from_slice(&type_word.to_le_bytes());
+        ewkb.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        for child in children {
+            ewkb.extend_from_slice(child);
+        }
+        ewkb
+    }
+
+    const SQUARE: [(f64, f64); 5] = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+
+    #[test]
+    fn test_validate_accepts_mixed_endianness() {
+        let big_point = hex_to_bytes("00000000014044000000000000403E000000000000");
+        let little_point = hex_to_bytes("01010000000000000000005E400000000000003E40");
+        let mut ewkb = vec![1u8];
+        ewkb.extend_from_slice(&(7 | EWKB_SRID).to_le_bytes());
+        ewkb.extend_from_slice(&4326u32.to_le_bytes());
+        ewkb.extend_from_slice(&3u32.to_le_bytes());
+        ewkb.extend_from_slice(&big_point);
+        ewkb.extend_from_slice(&little_point);
+        ewkb.extend_from_slice(&polygon(3, &SQUARE));
+        assert_eq!(validate_ewkb(&ewkb), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_nested_srid_and_dimensions() {
+        let mut child = polygon(3 | EWKB_SRID, &[]);
+        child.splice(5..5, 4326u32.to_le_bytes());
+        let ewkb = collection(6, &[polygon(3, &SQUARE), child]);
+        assert_eq!(
+            validate_ewkb(&ewkb),
+            Err(EwkbError::NestedSrid { offset: 9 + 93 })
+        );
+
+        let mut child = vec![1u8];
+        child.extend_from_slice(&(1 | EWKB_Z).to_le_bytes());
+        for v in [1.0f64, 2.0, 3.0] {
+            child.extend_from_slice(&v.to_le_bytes());
+        }
+        let err = validate_ewkb(&collection(4, &[child])).unwrap_err();
+        assert_eq!(
+            err,
+            EwkbError::DimensionMismatch {
+                offset: 9,
+                expected: "XY",
+                found: "XYZ"
+            }
+        );
+        assert_eq!(err.offset(), 9);
+        assert_eq!(
+            err.to_string(),
+            "EWKB sub-geometry at byte 9 is XYZ, expected XY"
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_bad_rings() {
+        assert_eq!(
+            validate_ewkb(&polygon(3, &SQUARE[..3])),
+            Err(EwkbError::RingTooShort {
+                offset: 9,
+                points: 3
+            })
+        );
+        let mut open = SQUARE;
+        open[4] = (0.0, 0.5);
+        let ewkb = collection(6, &[polygon(3, &SQUARE), polygon(3, &open)]);
+        assert_eq!(
+            validate_ewkb(&ewkb),
+            Err(EwkbError::RingNotClosed { offset: 9 + 93 + 9 })
+        );
+        assert_eq!(validate_ewkb(&polygon(3, &SQUARE)), Ok(()));
+    }
+
+    #[test]
+    fn test_every_error_has_an_offset() {
+        let mut bad_endian = wrap(4, &[vec![7, 1, 0, 0, 0]]);
+        let err = transform_ewkb_in_place(&mut bad_endian, TransformKind::Wgs2Gcj).unwrap_err();
+        assert_eq!(
+            err,
+            EwkbError::InvalidEndian {
+                offset: 9,
+                marker: 7
+            }
+        );
+        assert_eq!(err.to_string(), "invalid EWKB endian marker 7 at byte 9");
+
+        let mut trailing = ewkb_le(1, &[], &[120.0, 30.0]);
+        trailing.extend_from_slice(&[0, 0]);
+        assert_eq!(
+            validate_ewkb(&trailing),
+            Err(EwkbError::TrailingData { offset: 21, len: 2 })
+        );
+
+        let nested = wrap(7, &[vec![1, 0xFF, 0, 0, 0]]);
+        let err = transform_ewkb(&nested, TransformKind::Wgs2Gcj, &GeometryOptions::default())
+            .unwrap_err();
+        assert_eq!(err.offset(), 9);
+
+        let line = wrap(7, &[ewkb_le(2, &[2], &[100.0, 20.0, 130.0, 45.0])]);
+        let options = GeometryOptions {
+            max_segment_error: Some(0.0),
+            ..Default::default()
+        };
+        let err = transform_ewkb(&line, TransformKind::Wgs2Gcj, &options).unwrap_err();
+        assert_eq!(err, EwkbError::TooManyPoints { offset: 9 });
+    }
+
     #[test]
     fn test_invalid_geometry_type() {
         let mut ewkb = vec![1, 0xFF, 0, 0, 0];
         let err = transform_ewkb_in_place(&mut ewkb, TransformKind::Wgs2Gcj).unwrap_err();
-        assert_eq!(err, EwkbError::UnsupportedType(255));
+        assert_eq!(
+            err,
+            EwkbError::UnsupportedType {
+                offset: 0,
+                gtype: 255
+            }
+        );
+    }
+
+    #[test]
+    fn test_point_array_batches_match_scalar() {
+        let npoints = 150u32;
+        let mut ewkb = vec![1u8];
+        ewkb.extend_from_slice(&(2u32 | EWKB_Z).to_le_bytes());
+        ewkb.extend_from_slice(&npoints.to_le_bytes());
+        let coords: Vec<(f64, f64, f64)> = (0..npoints)
+            .map(|i| {
+                (
+                    100.0 + f64::from(i) * 0.2,
+                    20.0 + f64::from(i) * 0.1,
+                    f64::from(i),
+                )
+            })
+            .collect();
+        for &(x, y, z) in &coords {
+            for v in [x, y, z] {
+                ewkb.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        transform_ewkb_in_place(&mut ewkb, TransformKind::Wgs2Gcj).unwrap();
+
+        for (i, &(x, y, z)) in coords.iter().enumerate() {
+            let base = 9 + i * 24;
+            let (lat, lng) = crate::coord::apply(TransformKind::Wgs2Gcj, y, x);
+            assert_eq!(read_le_f64(&ewkb, base), lng);
+            assert_eq!(read_le_f64(&ewkb, base + 8), lat);
+            assert_eq!(read_le_f64(&ewkb, base + 16), z);
+        }
+    }
+
+    fn ewkb_le(type_word: u32, counts: &[u32], coords: &[f64]) -> Vec<u8> {
+        let mut out = vec![1u8];
+        out.extend_from_slice(&type_word.to_le_bytes());
+        for n in counts {
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        for v in coords {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out
+    }
+
+    fn coords_le(bytes: &[u8], offset: usize) -> Vec<f64> {
+        (offset..bytes.len())
+            .step_by(8)
+            .map(|i| read_le_f64(bytes, i))
+            .collect()
+    }
+
+    fn gcj(x: f64, y: f64) -> (f64, f64) {
+        let (lat, lng) = crate::coord::apply(TransformKind::Wgs2Gcj, y, x);
+        (lng, lat)
+    }
+
+    #[test]
+    fn test_rewrite_passthrough_matches_in_place() {
+        for type_word in [1, 1 | EWKB_Z, 1 | EWKB_M, 1 | EWKB_Z | EWKB_M] {
+            let ndims = 2 + (type_word >> 31) + ((type_word >> 30) & 1);
+            let coords = [120.0, 30.0, 7.0, 9.0];
+            let input = ewkb_le(type_word, &[], &coords[..ndims as usize]);
+            let mut in_place = input.clone();
+            transform_ewkb_in_place(&mut in_place, TransformKind::Wgs2Gcj).unwrap();
+            let rewritten =
+                transform_ewkb(&input, TransformKind::Wgs2Gcj, &GeometryOptions::default())
+                    .unwrap();
+            assert_eq!(rewritten, in_place);
+        }
+    }
+
+    #[test]
+    fn test_point_z_offset_and_drop() {
+        let input = ewkb_le(1 | EWKB_Z, &[], &[120.0, 30.0, 7.0]);
+        let (x, y) = gcj(120.0, 30.0);
+
+        let offset = GeometryOptions {
+            z: ZMode::Offset(2.5),
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &offset).unwrap();
+        assert_eq!(out[1..5], (1 | EWKB_Z).to_le_bytes());
+        assert_eq!(coords_le(&out, 5), vec![x, y, 9.5]);
+
+        let drop = GeometryOptions {
+            z: ZMode::Drop,
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &drop).unwrap();
+        assert_eq!(out[1..5], 1u32.to_le_bytes());
+        assert_eq!(coords_le(&out, 5), vec![x, y]);
+    }
+
+    #[test]
+    fn test_point_m_drop_and_cumulative() {
+        let input = ewkb_le(1 | EWKB_M, &[], &[120.0, 30.0, 42.0]);
+        let (x, y) = gcj(120.0, 30.0);
+
+        let drop = GeometryOptions {
+            m: MMode::Drop,
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &drop).unwrap();
+        assert_eq!(out[1..5], 1u32.to_le_bytes());
+        assert_eq!(coords_le(&out, 5), vec![x, y]);
+
+        let measure = GeometryOptions {
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &measure).unwrap();
+        assert_eq!(coords_le(&out, 5), vec![x, y, 0.0]);
+    }
+
+    #[test]
+    fn test_point_zm_keeps_srid_and_drops_both() {
+        let input = {
+            let mut v = vec![1u8];
+            v.extend_from_slice(&(1 | EWKB_Z | EWKB_M | EWKB_SRID).to_le_bytes());
+            v.extend_from_slice(&4326u32.to_le_bytes());
+            for c in [120.0f64, 30.0, 7.0, 42.0] {
+                v.extend_from_slice(&c.to_le_bytes());
+            }
+            v
+        };
+        let options = GeometryOptions {
+            z: ZMode::Drop,
+            m: MMode::Drop,
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &options).unwrap();
+        assert_eq!(out[1..5], (1 | EWKB_SRID).to_le_bytes());
+        assert_eq!(out[5..9], 4326u32.to_le_bytes());
+        let (x, y) = gcj(120.0, 30.0);
+        assert_eq!(coords_le(&out, 9), vec![x, y]);
+    }
+
+    #[test]
+    fn test_linestring_cumulative_length_adds_m() {
+        let input = ewkb_le(
+            2 | EWKB_Z,
+            &[3],
+            &[120.0, 30.0, 1.0, 121.0, 30.0, 2.0, 121.0, 31.0, 3.0],
+        );
+        let options = GeometryOptions {
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &options).unwrap();
+        assert_eq!(out[1..5], (2 | EWKB_Z | EWKB_M).to_le_bytes());
+
+        let got = coords_le(&out, 9);
+        let pts: Vec<(f64, f64)> = [(120.0, 30.0), (121.0, 30.0), (121.0, 31.0)]
+            .into_iter()
+            .map(|(x, y)| gcj(x, y))
+            .collect();
+        let d1 = (pts[1].0 - pts[0].0).hypot(pts[1].1 - pts[0].1);
+        let d2 = d1 + (pts[2].0 - pts[1].0).hypot(pts[2].1 - pts[1].1);
+        assert_eq!(got[3], 0.0);
+        assert_eq!(got[7], d1);
+        assert_eq!(got[11], d2);
+        assert_eq!((got[2], got[6], got[10]), (1.0, 2.0, 3.0));
+    }
+
+    fn wrap(gtype: u32, parts: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = ewkb_le(gtype, &[parts.len() as u32], &[]);
+        for part in parts {
+            out.extend_from_slice(part);
+        }
+        out
+    }
+
+    // Flattens XY little-endian EWKB into (type, point array) pairs.
+    fn point_arrays(bytes: &[u8], offset: &mut usize, out: &mut Vec<(u32, Vec<(f64, f64)>)>) {
+        let read_u32 = |offset: &mut usize| {
+            let v = u32::from_le_bytes(bytes[*offset..*offset + 4].try_into().unwrap());
+            *offset += 4;
+            v
+        };
+        let read_points = |offset: &mut usize, n: u32| {
+            let points: Vec<(f64, f64)> = (0..n as usize)
+                .map(|i| {
+                    let base = *offset + i * 16;
+                    (read_le_f64(bytes, base), read_le_f64(bytes, base + 8))
+                })
+                .collect();
+            *offset += n as usize * 16;
+            points
+        };
+
+        *offset += 1;
+        let gtype = read_u32(offset);
+        match gtype {
+            1 => out.push((gtype, read_points(offset, 1))),
+            2 | 8 => {
+                let n = read_u32(offset);
+                out.push((gtype, read_points(offset, n)));
+            }
+            3 | 17 => {
+                for _ in 0..read_u32(offset) {
+                    let n = read_u32(offset);
+                    out.push((gtype, read_points(offset, n)));
+                }
+            }
+            _ => {
+                for _ in 0..read_u32(offset) {
+                    point_arrays(bytes, offset, out);
+                }
+            }
+        }
+    }
+
+    fn densify(input: &[u8]) -> Vec<(u32, Vec<(f64, f64)>)> {
+        let options = GeometryOptions {
+            curve: CurveMode::Densify(1e-4),
+            ..Default::default()
+        };
+        let out = transform_ewkb(input, TransformKind::Wgs2Gcj, &options).unwrap();
+        let mut arrays = Vec::new();
+        let mut offset = 0;
+        point_arrays(&out, &mut offset, &mut arrays);
+        assert_eq!(offset, out.len());
+        arrays
+    }
+
+    fn type_word(bytes: &[u8]) -> u32 {
+        u32::from_le_bytes(bytes[1..5].try_into().unwrap())
+    }
+
+    // A half circle of radius 0.5 around (120, 30), bulging north, with its
+    // control point off the arc's midpoint.
+    const HALF_CIRCLE: [f64; 6] = [120.5, 30.0, 120.3, 30.4, 119.5, 30.0];
+
+    fn assert_on_half_circle(points: &[(f64, f64)]) {
+        let (cx, cy) = gcj(120.0, 30.0);
+        assert_eq!(points.first(), Some(&gcj(120.5, 30.0)));
+        assert_eq!(points.last(), Some(&gcj(119.5, 30.0)));
+        assert!(points.len() > 20);
+        for &(x, y) in points {
+            // GCJ-02 distorts the circle by a few 1e-4 degrees at this size.
+            assert!(((x - cx).hypot(y - cy) - 0.5).abs() < 1e-3);
+            assert!(y > cy - 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_densify_circular_string() {
+        let input = ewkb_le(8, &[3], &HALF_CIRCLE);
+        let arrays = densify(&input);
+        assert_eq!(arrays.len(), 1);
+        assert_eq!(arrays[0].0, 2);
+        assert_on_half_circle(&arrays[0].1);
+    }
+
+    #[test]
+    fn test_densify_compound_curve() {
+        let input = wrap(
+            9,
+            &[
+                ewkb_le(8, &[3], &HALF_CIRCLE),
+                ewkb_le(2, &[2], &[119.5, 30.0, 119.5, 29.0]),
+            ],
+        );
+        let out = transform_ewkb(
+            &input,
+            TransformKind::Wgs2Gcj,
+            &GeometryOptions {
+                curve: CurveMode::Densify(1e-4),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(type_word(&out), 2);
+
+        let arrays = densify(&input);
+        let points = &arrays[0].1;
+        assert_on_half_circle(&points[..points.len() - 1]);
+        assert_eq!(points.last(), Some(&gcj(119.5, 29.0)));
+    }
+
+    #[test]
+    fn test_densify_curve_polygon() {
+        let circle = ewkb_le(8, &[3], &[120.5, 30.0, 119.5, 30.0, 120.5, 30.0]);
+        let input = wrap(10, &[circle]);
+        let out = transform_ewkb(
+            &input,
+            TransformKind::Wgs2Gcj,
+            &GeometryOptions {
+                curve: CurveMode::Densify(1e-4),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(type_word(&out), 3);
+
+        let arrays = densify(&input);
+        assert_eq!(arrays.len(), 1);
+        let ring = &arrays[0].1;
+        assert_eq!(ring.first(), ring.last());
+        let (_, cy) = gcj(120.0, 30.0);
+        assert!(ring.iter().any(|p| p.1 > cy + 0.49));
+        assert!(ring.iter().any(|p| p.1 < cy - 0.49));
+    }
+
+    #[test]
+    fn test_densify_multi_curve_and_multi_surface() {
+        let line = ewkb_le(2, &[2], &[121.0, 31.0, 121.5, 31.5]);
+        let input = wrap(11, &[line.clone(), ewkb_le(8, &[3], &HALF_CIRCLE)]);
+        let options = GeometryOptions {
+            curve: CurveMode::Densify(1e-4),
+            ..Default::default()
+        };
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &options).unwrap();
+        assert_eq!(type_word(&out), 5);
+        let arrays = densify(&input);
+        assert_eq!(arrays[0], (2, vec![gcj(121.0, 31.0), gcj(121.5, 31.5)]));
+        assert_eq!(arrays[1].0, 2);
+        assert_on_half_circle(&arrays[1].1);
+
+        let square = ewkb_le(
+            3,
+            &[1, 4],
+            &[121.0, 31.0, 122.0, 31.0, 122.0, 32.0, 121.0, 31.0],
+        );
+        let closed_arc = ewkb_le(
+            8,
+            &[5],
+            &[
+                120.5, 30.0, 120.0, 30.5, 119.5, 30.0, 120.0, 29.5, 120.5, 30.0,
+            ],
+        );
+        let input = wrap(12, &[square, wrap(10, &[closed_arc])]);
+        let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &options).unwrap();
+        assert_eq!(type_word(&out), 6);
+        let arrays = densify(&input);
+        assert_eq!(arrays.len(), 2);
+        assert_eq!(arrays[0].1.len(), 4);
+        assert!(
+            arrays
+                .iter()
+                .all(|(gtype, ring)| *gtype == 3 && ring.first() == ring.last())
+        );
+        assert!(arrays[1].1.len() > 20);
+    }
+
+    #[test]
+    fn test_densify_keeps_linear_surfaces() {
+        let triangle = ewkb_le(
+            17,
+            &[1, 4],
+            &[120.0, 30.0, 121.0, 30.0, 120.0, 31.0, 120.0, 30.0],
+        );
+        let options = GeometryOptions {
+            curve: CurveMode::Densify(1e-4),
+            ..Default::default()
+        };
+        for input in [
+            triangle.clone(),
+            wrap(15, std::slice::from_ref(&triangle)),
+            wrap(16, &[triangle]),
+        ] {
+            let densified = transform_ewkb(&input, TransformKind::Wgs2Gcj, &options).unwrap();
+            let plain = transform_ewkb(&input, TransformKind::Wgs2Gcj, &GeometryOptions::default())
+                .unwrap();
+            assert_eq!(densified, plain);
+        }
+    }
+
+    #[test]
+    fn test_refit_moves_arc_controls_in_every_curve_type() {
+        let arc = ewkb_le(8, &[3], &HALF_CIRCLE);
+        let inputs = [
+            arc.clone(),
+            wrap(9, std::slice::from_ref(&arc)),
+            wrap(11, std::slice::from_ref(&arc)),
+            wrap(7, std::slice::from_ref(&arc)),
+            wrap(
+                12,
+                &[wrap(
+                    10,
+                    &[wrap(
+                        9,
+                        &[arc, ewkb_le(2, &[2], &[119.5, 30.0, 120.5, 30.0])],
+                    )],
+                )],
+            ),
+        ];
+        let refit = GeometryOptions {
+            curve: CurveMode::Refit,
+            ..Default::default()
+        };
+        for input in inputs {
+            let out = transform_ewkb(&input, TransformKind::Wgs2Gcj, &refit).unwrap();
+            assert_eq!(out.len(), input.len());
+            assert_eq!(type_word(&out), type_word(&input));
+
+            let mut arrays = Vec::new();
+            point_arrays(&out, &mut 0, &mut arrays);
+            let (gtype, points) = &arrays[0];
+            assert_eq!(*gtype, 8);
+            assert_eq!(points[0], gcj(120.5, 30.0));
+            let (x, y) = points[1];
+            let (mx, my) = gcj(120.0, 30.5);
+            assert!((x - mx).abs() < 1e-12 && (y - my).abs() < 1e-12);
+            assert_eq!(points[2], gcj(119.5, 30.0));
+        }
+
+        let plain = transform_ewkb(
+            &ewkb_le(8, &[3], &HALF_CIRCLE),
+            TransformKind::Wgs2Gcj,
+            &GeometryOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            coords_le(&plain, 9)[2..4],
+            [gcj(120.3, 30.4).0, gcj(120.3, 30.4).1]
+        );
+    }
+
+    fn distance_to_line(p: (f64, f64), line: &[(f64, f64)]) -> f64 {
+        line.windows(2)
+            .map(|w| {
+                let (a, b) = (w[0], w[1]);
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let len2 = dx * dx + dy * dy;
+                let t = if len2 == 0.0 {
+                    0.0
+                } else {
+                    (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+                };
+                (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    // Densifying the input and transforming every vertex is the reference;
+    // each curve mode's output arcs are densified afterwards and compared
+    // against it, for a large and a street-sized arc.
+    #[test]
+    fn test_refit_arcs_follow_the_densified_transform() {
+        let arrays = |input: &[u8], kind: TransformKind, curve: CurveMode| {
+            let options = GeometryOptions {
+                curve,
+                ..Default::default()
+            };
+            let out = transform_ewkb(input, kind, &options).unwrap();
+            let mut arrays = Vec::new();
+            point_arrays(&out, &mut 0, &mut arrays);
+            arrays
+        };
+        for r in [0.5, 0.01] {
+            let (cx, cy) = (120.0, 30.0);
+            // The control points are on the arcs but away from their midpoints.
+            let (dx, dy) = (0.966 * r, 0.259 * r);
+            let arc = ewkb_le(8, &[3], &[cx + r, cy, cx + dx, cy + dy, cx - r, cy]);
+            let tail = ewkb_le(2, &[2], &[cx - r, cy, cx - r, cy - r]);
+            let circle = ewkb_le(
+                8,
+                &[5],
+                &[
+                    cx + r,
+                    cy,
+                    cx + dx,
+                    cy + dy,
+                    cx - r,
+                    cy,
+                    cx - dx,
+                    cy - dy,
+                    cx + r,
+                    cy,
+                ],
+            );
+            let inputs = [
+                arc.clone(),
+                wrap(9, &[arc.clone(), tail.clone()]),
+                wrap(10, &[circle]),
+                wrap(11, &[tail.clone(), arc.clone()]),
+                wrap(12, &[wrap(10, &[wrap(9, &[arc, tail])])]),
+            ];
+            for input in inputs {
+                let tolerance = r * 1e-6;
+                let expected = arrays(
+                    &input,
+                    TransformKind::Wgs2Gcj,
+                    CurveMode::Densify(tolerance),
+                );
+                let deviation = |curve: CurveMode| {
+                    let out = transform_ewkb(
+                        &input,
+                        TransformKind::Wgs2Gcj,
+                        &GeometryOptions {
+                            curve,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+                    let got = arrays(&out, TransformKind::Identity, CurveMode::Densify(tolerance));
+                    assert_eq!(got.len(), expected.len());
+                    got.iter()
+                        .zip(&expected)
+                        .flat_map(|((_, got), (_, expected))| {
+                            expected.iter().map(|&p| distance_to_line(p, got))
+                        })
+                        .fold(0.0, f64::max)
+                };
+                let refit = deviation(CurveMode::Refit);
+                let controls = deviation(CurveMode::ControlPoints);
+                // Across the 0.5 degree arcs the offset bends the circle by
+                // about 2e-4 degrees, which no arc follows, so only the small
+                // arcs show the full gain.
+                let bound = if r < 0.1 { controls * 0.5 } else { controls };
+                assert!(refit < bound, "refit {refit}, control points {controls}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_segment_error_grows_linear_arrays_only() {
+        let options = GeometryOptions {
+            max_segment_error: Some(1e-6),
+            ..Default::default()
+        };
+        let square = ewkb_le(
+            3,
+            &[1, 5],
+            &[
+                110.0, 25.0, 120.0, 25.0, 120.0, 35.0, 110.0, 35.0, 110.0, 25.0,
+            ],
+        );
+        let out = transform_ewkb(&square, TransformKind::Wgs2Gcj, &options).unwrap();
+        let mut arrays = Vec::new();
+        point_arrays(&out, &mut 0, &mut arrays);
+        let ring = &arrays[0].1;
+        assert!(ring.len() > 5);
+        assert_eq!(ring.first(), ring.last());
+        assert_eq!(ring[0], gcj(110.0, 25.0));
+
+        let arc = ewkb_le(8, &[3], &HALF_CIRCLE);
+        let segmentized = transform_ewkb(&arc, TransformKind::Wgs2Gcj, &options).unwrap();
+        let plain =
+            transform_ewkb(&arc, TransformKind::Wgs2Gcj, &GeometryOptions::default()).unwrap();
+        assert_eq!(segmentized, plain);
     }
 }
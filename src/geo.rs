@@ -0,0 +1,318 @@
+use geo_types::{
+    Coord, Geometry, GeometryCollection, Line, LineString, MultiLineString, MultiPoint,
+    MultiPolygon, Point, Polygon,
+};
+
+use crate::api::{Error, Transformer};
+use crate::coord::Transform;
+use crate::srid::Datum;
+
+/// In-place coordinate transforms for `geo_types` geometries, with x as
+/// longitude and y as latitude.
+///
+/// Takes a `TransformKind` or a full `Transform`, like
+/// `ewkb::transform_ewkb_in_place`, and gives the same coordinates.
+///
+/// ```
+/// use geo_types::line_string;
+/// use pg_eviltransform::{EvilTransform, Transform, TransformKind};
+///
+/// let mut line = line_string![(x: 120.0, y: 30.0), (x: 121.0, y: 31.0)];
+/// line.evil_transform(TransformKind::Wgs2Gcj);
+/// let (lat, lng) = Transform::from(TransformKind::Wgs2Gcj).apply(31.0, 121.0);
+/// assert_eq!((line[1].x, line[1].y), (lng, lat));
+/// ```
+pub trait EvilTransform {
+    fn evil_transform(&mut self, transform: impl Into<Transform>);
+}
+
+impl EvilTransform for Coord<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let (lat, lng) = transform.into().apply(self.y, self.x);
+        self.x = lng;
+        self.y = lat;
+    }
+}
+
+impl EvilTransform for Point<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        self.0.evil_transform(transform);
+    }
+}
+
+impl EvilTransform for Line<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        self.start.evil_transform(transform);
+        self.end.evil_transform(transform);
+    }
+}
+
+impl EvilTransform for LineString<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        for coord in &mut self.0 {
+            coord.evil_transform(transform);
+        }
+    }
+}
+
+impl EvilTransform for Polygon<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        self.exterior_mut(|ring| ring.evil_transform(transform));
+        self.interiors_mut(|rings| {
+            for ring in rings {
+                ring.evil_transform(transform);
+            }
+        });
+    }
+}
+
+impl EvilTransform for MultiPoint<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        for point in &mut self.0 {
+            point.evil_transform(transform);
+        }
+    }
+}
+
+impl EvilTransform for MultiLineString<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        for line in &mut self.0 {
+            line.evil_transform(transform);
+        }
+    }
+}
+
+impl EvilTransform for MultiPolygon<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        for polygon in &mut self.0 {
+            polygon.evil_transform(transform);
+        }
+    }
+}
+
+impl EvilTransform for GeometryCollection<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        for geometry in &mut self.0 {
+            geometry.evil_transform(transform);
+        }
+    }
+}
+
+// A warped rect is no longer axis-aligned, so rects and triangles come back
+// as polygons.
+impl EvilTransform for Geometry<f64> {
+    fn evil_transform(&mut self, transform: impl Into<Transform>) {
+        let transform = transform.into();
+        match self {
+            Geometry::Point(point) => point.evil_transform(transform),
+            Geometry::Line(line) => line.evil_transform(transform),
+            Geometry::LineString(line) => line.evil_transform(transform),
+            Geometry::Polygon(polygon) => polygon.evil_transform(transform),
+            Geometry::MultiPoint(points) => points.evil_transform(transform),
+            Geometry::MultiLineString(lines) => lines.evil_transform(transform),
+            Geometry::MultiPolygon(polygons) => polygons.evil_transform(transform),
+            Geometry::GeometryCollection(collection) => collection.evil_transform(transform),
+            Geometry::Rect(rect) => {
+                let mut polygon = rect.to_polygon();
+                polygon.evil_transform(transform);
+                *self = Geometry::Polygon(polygon);
+            }
+            Geometry::Triangle(triangle) => {
+                let mut polygon = triangle.to_polygon();
+                polygon.evil_transform(transform);
+                *self = Geometry::Polygon(polygon);
+            }
+        }
+    }
+}
+
+impl Transformer {
+    /// Transforms a `geo_types` geometry in place, with x as longitude and y
+    /// as latitude. `Rect` and `Triangle` values become `Polygon`s.
+    pub fn transform_geo(
+        &self,
+        geometry: &mut Geometry<f64>,
+        from: Datum,
+        to: Datum,
+    ) -> Result<(), Error> {
+        geometry.evil_transform(self.transform(from, to)?);
+        Ok(())
+    }
+}
+
+/// Transforms a `geo_types` geometry in place with the default settings.
+///
+/// ```
+/// use geo_types::{Geometry, point};
+/// use pg_eviltransform::{Datum, transform_geo, transform_point};
+///
+/// let mut geometry = Geometry::Point(point!(x: 120.0, y: 30.0));
+/// transform_geo(&mut geometry, Datum::Wgs84, Datum::Gcj02)?;
+/// let (lat, lng) = transform_point(Datum::Wgs84, Datum::Gcj02, 30.0, 120.0)?;
+/// assert_eq!(geometry, Geometry::Point(point!(x: lng, y: lat)));
+/// # Ok::<(), pg_eviltransform::Error>(())
+/// ```
+pub fn transform_geo(geometry: &mut Geometry<f64>, from: Datum, to: Datum) -> Result<(), Error> {
+    Transformer::default().transform_geo(geometry, from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::TransformKind;
+    use crate::ewkb::transform_ewkb_in_place;
+    use geo_types::{Rect, coord, polygon};
+    use proptest::prelude::*;
+
+    const KINDS: [TransformKind; 9] = [
+        TransformKind::Wgs2Gcj,
+        TransformKind::Gcj2Wgs,
+        TransformKind::Gcj2Bd,
+        TransformKind::Bd2Gcj,
+        TransformKind::Wgs2Bd,
+        TransformKind::Bd2Wgs,
+        TransformKind::Gcj2WgsExact,
+        TransformKind::Bd2GcjExact,
+        TransformKind::Bd2WgsExact,
+    ];
+
+    // Little-endian WKB, enough to feed the EWKB path the same geometry.
+    fn write_wkb(out: &mut Vec<u8>, geometry: &Geometry<f64>) {
+        fn header(out: &mut Vec<u8>, gtype: u32) {
+            out.push(1);
+            out.extend_from_slice(&gtype.to_le_bytes());
+        }
+        fn coords(out: &mut Vec<u8>, line: &LineString<f64>) {
+            out.extend_from_slice(&(line.0.len() as u32).to_le_bytes());
+            for c in &line.0 {
+                out.extend_from_slice(&c.x.to_le_bytes());
+                out.extend_from_slice(&c.y.to_le_bytes());
+            }
+        }
+        fn rings(out: &mut Vec<u8>, polygon: &Polygon<f64>) {
+            out.extend_from_slice(&(polygon.interiors().len() as u32 + 1).to_le_bytes());
+            coords(out, polygon.exterior());
+            for ring in polygon.interiors() {
+                coords(out, ring);
+            }
+        }
+        fn parts(
+            out: &mut Vec<u8>,
+            gtype: u32,
+            parts: impl ExactSizeIterator<Item = Geometry<f64>>,
+        ) {
+            header(out, gtype);
+            out.extend_from_slice(&(parts.len() as u32).to_le_bytes());
+            for part in parts {
+                write_wkb(out, &part);
+            }
+        }
+        match geometry {
+            Geometry::Point(point) => {
+                header(out, 1);
+                out.extend_from_slice(&point.x().to_le_bytes());
+                out.extend_from_slice(&point.y().to_le_bytes());
+            }
+            Geometry::LineString(line) => {
+                header(out, 2);
+                coords(out, line);
+            }
+            Geometry::Polygon(polygon) => {
+                header(out, 3);
+                rings(out, polygon);
+            }
+            Geometry::MultiPoint(points) => {
+                parts(out, 4, points.0.iter().map(|p| Geometry::Point(*p)))
+            }
+            Geometry::MultiLineString(lines) => parts(
+                out,
+                5,
+                lines.0.iter().map(|l| Geometry::LineString(l.clone())),
+            ),
+            Geometry::MultiPolygon(polygons) => parts(
+                out,
+                6,
+                polygons.0.iter().map(|p| Geometry::Polygon(p.clone())),
+            ),
+            Geometry::GeometryCollection(collection) => parts(out, 7, collection.0.iter().cloned()),
+            _ => unreachable!(),
+        }
+    }
+
+    // Inside and well outside China, so both offset and passthrough points
+    // are covered.
+    fn arb_coord() -> impl Strategy<Value = Coord<f64>> {
+        (60.0..150.0f64, -10.0..60.0f64).prop_map(|(x, y)| coord! { x: x, y: y })
+    }
+
+    fn arb_line() -> impl Strategy<Value = LineString<f64>> {
+        prop::collection::vec(arb_coord(), 0..8).prop_map(LineString::new)
+    }
+
+    fn arb_ring() -> impl Strategy<Value = LineString<f64>> {
+        prop::collection::vec(arb_coord(), 3..8).prop_map(LineString::new)
+    }
+
+    fn arb_polygon() -> impl Strategy<Value = Polygon<f64>> {
+        (arb_ring(), prop::collection::vec(arb_ring(), 0..3))
+            .prop_map(|(exterior, interiors)| Polygon::new(exterior, interiors))
+    }
+
+    fn arb_geometry() -> impl Strategy<Value = Geometry<f64>> {
+        let leaf = prop_oneof![
+            arb_coord().prop_map(|c| Geometry::Point(c.into())),
+            arb_line().prop_map(Geometry::LineString),
+            arb_polygon().prop_map(Geometry::Polygon),
+            prop::collection::vec(arb_coord(), 0..6)
+                .prop_map(|cs| Geometry::MultiPoint(cs.into_iter().map(Point::from).collect())),
+            prop::collection::vec(arb_line(), 0..4)
+                .prop_map(|ls| Geometry::MultiLineString(MultiLineString::new(ls))),
+            prop::collection::vec(arb_polygon(), 0..3)
+                .prop_map(|ps| Geometry::MultiPolygon(MultiPolygon::new(ps))),
+        ];
+        leaf.prop_recursive(2, 16, 4, |inner| {
+            prop::collection::vec(inner, 0..4)
+                .prop_map(|gs| Geometry::GeometryCollection(GeometryCollection::new_from(gs)))
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_ewkb_path(geometry in arb_geometry(), kind in prop::sample::select(&KINDS[..])) {
+            let mut wkb = Vec::new();
+            write_wkb(&mut wkb, &geometry);
+            transform_ewkb_in_place(&mut wkb, kind).unwrap();
+
+            let mut transformed = geometry.clone();
+            transformed.evil_transform(kind);
+            let mut expected = Vec::new();
+            write_wkb(&mut expected, &transformed);
+            prop_assert_eq!(wkb, expected);
+        }
+    }
+
+    #[test]
+    fn test_rect_becomes_polygon() {
+        let mut geometry = Geometry::Rect(Rect::new(
+            coord! { x: 120.0, y: 30.0 },
+            coord! { x: 121.0, y: 31.0 },
+        ));
+        transform_geo(&mut geometry, Datum::Wgs84, Datum::Bd09).unwrap();
+
+        let mut expected = polygon![
+            (x: 121.0, y: 30.0),
+            (x: 121.0, y: 31.0),
+            (x: 120.0, y: 31.0),
+            (x: 120.0, y: 30.0),
+        ];
+        expected.evil_transform(TransformKind::Wgs2Bd);
+        assert_eq!(geometry, Geometry::Polygon(expected));
+    }
+}
@@ -0,0 +1,468 @@
+use crate::coord::Transform;
+use crate::wkt::{format_like, number_len};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GeoJsonError {
+    UnexpectedEnd,
+    Unexpected { offset: usize, found: char },
+    InvalidNumber { offset: usize },
+    MissingMember { offset: usize, name: &'static str },
+    UnsupportedType { offset: usize, name: String },
+    TooFewOrdinates { offset: usize },
+    TrailingData { offset: usize },
+}
+
+impl std::fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeoJsonError::UnexpectedEnd => write!(f, "unexpected end of GeoJSON"),
+            GeoJsonError::Unexpected { offset, found } => {
+                write!(f, "unexpected {found:?} at GeoJSON offset {offset}")
+            }
+            GeoJsonError::InvalidNumber { offset } => {
+                write!(f, "invalid number at GeoJSON offset {offset}")
+            }
+            GeoJsonError::MissingMember { offset, name } => {
+                write!(
+                    f,
+                    "GeoJSON object at offset {offset} has no {name:?} member"
+                )
+            }
+            GeoJsonError::UnsupportedType { offset, name } => {
+                write!(f, "unsupported GeoJSON type {name:?} at offset {offset}")
+            }
+            GeoJsonError::TooFewOrdinates { offset } => {
+                write!(
+                    f,
+                    "GeoJSON position at offset {offset} has fewer than 2 ordinates"
+                )
+            }
+            GeoJsonError::TrailingData { offset } => {
+                write!(f, "GeoJSON has trailing data at offset {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+struct Rewriter<'a> {
+    src: &'a str,
+    pos: usize,
+    // (offset, token length, replacement), in no particular order since an
+    // object's "type" may follow its "coordinates".
+    edits: Vec<(usize, usize, String)>,
+    // None only checks the structure.
+    transform: Option<Transform>,
+}
+
+impl<'a> Rewriter<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&self) -> GeoJsonError {
+        match self.src[self.pos..].chars().next() {
+            Some(found) => GeoJsonError::Unexpected {
+                offset: self.pos,
+                found,
+            },
+            None => GeoJsonError::UnexpectedEnd,
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), GeoJsonError> {
+        self.skip_ws();
+        if self.peek() != Some(byte) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // Consumes `close` or a comma, returning whether the list ended.
+    fn list_end(&mut self, close: u8) -> Result<bool, GeoJsonError> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b) if b == close => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // An empty list: consumes the closing byte if it comes first.
+    fn empty(&mut self, close: u8) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    // The raw text between the quotes; escapes are left as written.
+    fn string(&mut self) -> Result<&'a str, GeoJsonError> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(GeoJsonError::UnexpectedEnd),
+            }
+        }
+        let raw = &self.src[start..self.pos.min(self.src.len())];
+        self.pos += 1;
+        Ok(raw)
+    }
+
+    fn number(&mut self) -> Result<(&'a str, f64), GeoJsonError> {
+        self.skip_ws();
+        let start = self.pos;
+        let len = number_len(&self.src.as_bytes()[start..]);
+        let token = &self.src[start..start + len];
+        let value = token
+            .parse::<f64>()
+            .map_err(|_| GeoJsonError::InvalidNumber { offset: start })?;
+        self.pos += len;
+        Ok((token, value))
+    }
+
+    fn skip_value(&mut self) -> Result<(), GeoJsonError> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'{') => {
+                self.members()?;
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                if !self.empty(b']') {
+                    loop {
+                        self.skip_value()?;
+                        if self.list_end(b']')? {
+                            break;
+                        }
+                    }
+                }
+            }
+            Some(b'"') => {
+                self.string()?;
+            }
+            Some(b't' | b'f' | b'n') => {
+                let rest = &self.src[self.pos..];
+                let word = ["true", "false", "null"]
+                    .into_iter()
+                    .find(|word| rest.starts_with(word))
+                    .ok_or_else(|| self.unexpected())?;
+                self.pos += word.len();
+            }
+            Some(_) => {
+                self.number()?;
+            }
+            None => return Err(GeoJsonError::UnexpectedEnd),
+        }
+        Ok(())
+    }
+
+    // Keys and value offsets of the object at `pos`.
+    fn members(&mut self) -> Result<Vec<(&'a str, usize)>, GeoJsonError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.empty(b'}') {
+            return Ok(members);
+        }
+        loop {
+            self.skip_ws();
+            let key = self.string()?;
+            self.expect(b':')?;
+            self.skip_ws();
+            members.push((key, self.pos));
+            self.skip_value()?;
+            if self.list_end(b'}')? {
+                return Ok(members);
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<(), GeoJsonError> {
+        self.skip_ws();
+        let start = self.pos;
+        let members = self.members()?;
+        let end = self.pos;
+        let member = |name: &'static str| {
+            members
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|&(_, offset)| offset)
+                .ok_or(GeoJsonError::MissingMember {
+                    offset: start,
+                    name,
+                })
+        };
+        self.pos = member("type")?;
+        let type_offset = self.pos;
+        let name = self.string()?;
+        let depth = match name {
+            "Point" => 0,
+            "LineString" | "MultiPoint" => 1,
+            "Polygon" | "MultiLineString" => 2,
+            "MultiPolygon" => 3,
+            "GeometryCollection" => {
+                self.pos = member("geometries")?;
+                self.objects()?;
+                self.pos = end;
+                return Ok(());
+            }
+            "FeatureCollection" => {
+                self.pos = member("features")?;
+                self.objects()?;
+                self.pos = end;
+                return Ok(());
+            }
+            "Feature" => {
+                self.pos = member("geometry")?;
+                if !self.src[self.pos..].starts_with("null") {
+                    self.object()?;
+                }
+                self.pos = end;
+                return Ok(());
+            }
+            _ => {
+                return Err(GeoJsonError::UnsupportedType {
+                    offset: type_offset,
+                    name: name.to_string(),
+                });
+            }
+        };
+        self.pos = member("coordinates")?;
+        self.coordinates(depth)?;
+        self.pos = end;
+        Ok(())
+    }
+
+    fn objects(&mut self) -> Result<(), GeoJsonError> {
+        self.expect(b'[')?;
+        if self.empty(b']') {
+            return Ok(());
+        }
+        loop {
+            self.object()?;
+            if self.list_end(b']')? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn coordinates(&mut self, depth: usize) -> Result<(), GeoJsonError> {
+        self.expect(b'[')?;
+        if self.empty(b']') {
+            return Ok(());
+        }
+        if depth == 0 {
+            return self.position();
+        }
+        loop {
+            self.coordinates(depth - 1)?;
+            if self.list_end(b']')? {
+                return Ok(());
+            }
+        }
+    }
+
+    // The rest of a non-empty position, after its `[`.
+    fn position(&mut self) -> Result<(), GeoJsonError> {
+        self.skip_ws();
+        let x_start = self.pos;
+        let (x_token, x) = self.number()?;
+        if self.list_end(b']')? {
+            return Err(GeoJsonError::TooFewOrdinates { offset: x_start });
+        }
+        self.skip_ws();
+        let y_start = self.pos;
+        let (y_token, y) = self.number()?;
+        // Elevation and any further ordinates are kept as written.
+        while !self.list_end(b']')? {
+            self.number()?;
+        }
+        let Some(transform) = self.transform else {
+            return Ok(());
+        };
+        let (lat, lng) = transform.apply(y, x);
+        self.edits
+            .push((x_start, x_token.len(), format_like(x_token, x, lng)));
+        self.edits
+            .push((y_start, y_token.len(), format_like(y_token, y, lat)));
+        Ok(())
+    }
+}
+
+/// Transforms the positions of a GeoJSON geometry, Feature or
+/// FeatureCollection, keeping everything else as written.
+///
+/// Transformed longitudes and latitudes keep the input's decimal places,
+/// with at least six; elevations, whitespace, member order, properties and
+/// foreign members are copied unchanged. `bbox` members are not updated.
+///
+/// ```
+/// use pg_eviltransform::TransformKind;
+/// use pg_eviltransform::transform_geojson;
+///
+/// let json = r#"{"type": "Point", "coordinates": [120.000000, 30.000000, 12.5]}"#;
+/// let gcj = transform_geojson(json, TransformKind::Wgs2Gcj)?;
+/// assert_eq!(gcj, r#"{"type": "Point", "coordinates": [120.004661, 29.997534, 12.5]}"#);
+/// # Ok::<(), pg_eviltransform::geojson::GeoJsonError>(())
+/// ```
+pub fn transform_geojson(
+    json: &str,
+    transform: impl Into<Transform>,
+) -> Result<String, GeoJsonError> {
+    let mut edits = rewrite(json, Some(transform.into()))?;
+    edits.sort_unstable_by_key(|edit| edit.0);
+    let mut out = String::with_capacity(json.len() + 16 * edits.len());
+    let mut copied = 0;
+    for (offset, len, text) in edits {
+        out.push_str(&json[copied..offset]);
+        out.push_str(&text);
+        copied = offset + len;
+    }
+    out.push_str(&json[copied..]);
+    Ok(out)
+}
+
+/// Checks `json` the way [`transform_geojson`] reads it, without changing
+/// anything.
+///
+/// ```
+/// use pg_eviltransform::geojson::{GeoJsonError, validate_geojson};
+///
+/// assert_eq!(validate_geojson(r#"{"type": "Point", "coordinates": [120, 30]}"#), Ok(()));
+/// assert_eq!(
+///     validate_geojson(r#"{"type": "Point", "coordinates": [120]}"#),
+///     Err(GeoJsonError::TooFewOrdinates { offset: 34 })
+/// );
+/// ```
+pub fn validate_geojson(json: &str) -> Result<(), GeoJsonError> {
+    rewrite(json, None).map(drop)
+}
+
+fn rewrite(
+    json: &str,
+    transform: Option<Transform>,
+) -> Result<Vec<(usize, usize, String)>, GeoJsonError> {
+    let mut rewriter = Rewriter {
+        src: json,
+        pos: 0,
+        edits: Vec::new(),
+        transform,
+    };
+    rewriter.object()?;
+    rewriter.skip_ws();
+    if rewriter.pos != json.len() {
+        return Err(GeoJsonError::TrailingData {
+            offset: rewriter.pos,
+        });
+    }
+    Ok(rewriter.edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::{TransformKind, apply};
+    use crate::wkt::transform_wkt;
+
+    #[test]
+    fn test_matches_wkt() {
+        let json = r#"{
+  "features": [
+    {"properties": {"coordinates": [1, 2], "type": "Point"}, "type": "Feature",
+     "geometry": {"coordinates": [[[120.5, 30.25], [121, 30], [121, 31.125], [120.5, 30.25]]], "type": "Polygon"}},
+    {"type": "Feature", "geometry": null, "properties": null},
+    {"type": "Feature", "properties": {}, "geometry": {"type": "GeometryCollection", "geometries": [
+      {"type": "MultiPoint", "coordinates": [[120.123456789, 30, 5], [-120, 30]]},
+      {"type": "MultiLineString", "coordinates": [[], [[120, 30], [121, 31]]]},
+      {"type": "MultiPolygon", "coordinates": []}
+    ]}}
+  ],
+  "type": "FeatureCollection", "bbox": [120, 30, 121, 31]
+}"#;
+        let out = transform_geojson(json, TransformKind::Wgs2Bd).unwrap();
+        let polygon = transform_wkt(
+            "POLYGON((120.5 30.25,121 30,121 31.125,120.5 30.25))",
+            TransformKind::Wgs2Bd,
+        )
+        .unwrap();
+        let polygon = polygon
+            .trim_start_matches("POLYGON((")
+            .trim_end_matches("))")
+            .split(',')
+            .map(|p| format!("[{}]", p.replace(' ', ", ")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        assert!(
+            out.contains(&format!(r#""coordinates": [[{polygon}]]"#)),
+            "{out}"
+        );
+
+        let (lat, lng) = apply(TransformKind::Wgs2Bd, 30.0, 120.123456789);
+        assert!(out.contains(&format!("[[{lng:.9}, {lat:.6}, 5], [-120, 30]]")));
+        assert!(out.contains(r#""properties": {"coordinates": [1, 2], "type": "Point"}"#));
+        assert!(out.ends_with(
+            r#""bbox": [120, 30, 121, 31]
+}"#
+        ));
+        assert_eq!(out.lines().count(), json.lines().count());
+    }
+
+    #[test]
+    fn test_errors() {
+        let kind = TransformKind::Wgs2Gcj;
+        assert_eq!(
+            transform_geojson(r#"{"type": "Point"}"#, kind),
+            Err(GeoJsonError::MissingMember {
+                offset: 0,
+                name: "coordinates"
+            })
+        );
+        assert_eq!(
+            transform_geojson(r#"{"type": "Circle", "coordinates": []}"#, kind),
+            Err(GeoJsonError::UnsupportedType {
+                offset: 9,
+                name: "Circle".into()
+            })
+        );
+        assert_eq!(
+            transform_geojson(r#"{"type": "Point", "coordinates": [120]}"#, kind),
+            Err(GeoJsonError::TooFewOrdinates { offset: 34 })
+        );
+        assert_eq!(
+            transform_geojson(r#"{"type": "LineString", "coordinates": [120, 30]}"#, kind),
+            Err(GeoJsonError::Unexpected {
+                offset: 39,
+                found: '1'
+            })
+        );
+        assert_eq!(
+            transform_geojson(r#"{"type": "Point", "coordinates": [120, 30]} {}"#, kind),
+            Err(GeoJsonError::TrailingData { offset: 44 })
+        );
+        assert_eq!(
+            transform_geojson(r#"{"type": "Point", "coordinates": [120, 30"#, kind),
+            Err(GeoJsonError::UnexpectedEnd)
+        );
+    }
+}
@@ -0,0 +1,1711 @@
+use crate::coord::Transform;
+use crate::curve::{arc_center, center_arc_controls, linearize_arcs, segment_side};
+use crate::options::{
+    CurveMode, GeometryOptions, TooManyPoints, Vertex, transform_vertices_segmentized,
+};
+
+const VARHDRSZ: usize = 4;
+const HEADER_SIZE: usize = 8;
+const EXTENDED_FLAGS_SIZE: usize = 8;
+
+const GFLAG_Z: u8 = 0x01;
+const GFLAG_M: u8 = 0x02;
+const GFLAG_BBOX: u8 = 0x04;
+const GFLAG_GEODETIC: u8 = 0x08;
+const G2FLAG_EXTENDED: u8 = 0x10;
+const G2FLAG_VER_0: u8 = 0x40;
+
+const SRID_MAXIMUM: i32 = 999_999;
+
+const FP_TOLERANCE: f64 = 1e-12;
+
+pub const POINTTYPE: u32 = 1;
+pub const LINETYPE: u32 = 2;
+pub const POLYGONTYPE: u32 = 3;
+pub const MULTIPOINTTYPE: u32 = 4;
+pub const MULTILINETYPE: u32 = 5;
+pub const MULTIPOLYGONTYPE: u32 = 6;
+pub const COLLECTIONTYPE: u32 = 7;
+pub const CIRCSTRINGTYPE: u32 = 8;
+pub const COMPOUNDTYPE: u32 = 9;
+pub const CURVEPOLYTYPE: u32 = 10;
+pub const MULTICURVETYPE: u32 = 11;
+pub const MULTISURFACETYPE: u32 = 12;
+pub const POLYHEDRALSURFACETYPE: u32 = 13;
+pub const TRIANGLETYPE: u32 = 14;
+pub const TINTYPE: u32 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GserializedError {
+    UnexpectedEof,
+    UnsupportedType(u32),
+    TrailingData(usize),
+    InvalidSrid(i32),
+    TooManyPoints,
+}
+
+impl std::fmt::Display for GserializedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GserializedError::UnexpectedEof => write!(f, "unexpected end of GSERIALIZED"),
+            GserializedError::UnsupportedType(v) => {
+                write!(f, "unsupported GSERIALIZED geometry type: {v}")
+            }
+            GserializedError::TrailingData(n) => write!(f, "GSERIALIZED has {n} trailing bytes"),
+            GserializedError::InvalidSrid(v) => write!(f, "SRID {v} does not fit GSERIALIZED"),
+            GserializedError::TooManyPoints => write!(f, "{TooManyPoints}"),
+        }
+    }
+}
+
+impl std::error::Error for GserializedError {}
+
+impl From<TooManyPoints> for GserializedError {
+    fn from(_: TooManyPoints) -> Self {
+        GserializedError::TooManyPoints
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Version {
+    V1,
+    V2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: Version,
+    pub srid: i32,
+    pub has_z: bool,
+    pub has_m: bool,
+    pub has_bbox: bool,
+    pub is_geodetic: bool,
+    pub bbox_offset: usize,
+    pub data_offset: usize,
+}
+
+impl Header {
+    #[inline]
+    pub fn ndims(&self) -> usize {
+        2 + usize::from(self.has_z) + usize::from(self.has_m)
+    }
+
+    #[inline]
+    pub fn bbox_size(&self) -> usize {
+        if !self.has_bbox {
+            0
+        } else if self.is_geodetic {
+            6 * 4
+        } else {
+            2 * self.ndims() * 4
+        }
+    }
+}
+
+#[inline]
+fn ensure_remaining(buf: &[u8], offset: usize, need: usize) -> Result<(), GserializedError> {
+    if buf.len().saturating_sub(offset) < need {
+        return Err(GserializedError::UnexpectedEof);
+    }
+    Ok(())
+}
+
+#[inline]
+fn read_u32(buf: &[u8], offset: &mut usize) -> Result<u32, GserializedError> {
+    ensure_remaining(buf, *offset, 4)?;
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&buf[*offset..*offset + 4]);
+    *offset += 4;
+    Ok(u32::from_ne_bytes(raw))
+}
+
+#[inline]
+fn read_f64(buf: &[u8], offset: usize) -> Result<f64, GserializedError> {
+    ensure_remaining(buf, offset, 8)?;
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&buf[offset..offset + 8]);
+    Ok(f64::from_ne_bytes(raw))
+}
+
+#[inline]
+fn write_f64(buf: &mut [u8], offset: usize, value: f64) -> Result<(), GserializedError> {
+    ensure_remaining(buf, offset, 8)?;
+    buf[offset..offset + 8].copy_from_slice(&value.to_ne_bytes());
+    Ok(())
+}
+
+pub fn read_header(buf: &[u8]) -> Result<Header, GserializedError> {
+    ensure_remaining(buf, 0, HEADER_SIZE)?;
+
+    let flags = buf[VARHDRSZ + 3];
+    let version = if flags & G2FLAG_VER_0 != 0 {
+        Version::V2
+    } else {
+        Version::V1
+    };
+
+    let mut srid = (i32::from(buf[VARHDRSZ]) << 16)
+        | (i32::from(buf[VARHDRSZ + 1]) << 8)
+        | i32::from(buf[VARHDRSZ + 2]);
+    srid = (srid << 11) >> 11;
+
+    let mut bbox_offset = HEADER_SIZE;
+    if version == Version::V2 && flags & G2FLAG_EXTENDED != 0 {
+        bbox_offset += EXTENDED_FLAGS_SIZE;
+    }
+
+    let mut header = Header {
+        version,
+        srid,
+        has_z: flags & GFLAG_Z != 0,
+        has_m: flags & GFLAG_M != 0,
+        has_bbox: flags & GFLAG_BBOX != 0,
+        is_geodetic: flags & GFLAG_GEODETIC != 0,
+        bbox_offset,
+        data_offset: bbox_offset,
+    };
+    header.data_offset += header.bbox_size();
+    ensure_remaining(buf, 0, header.data_offset)?;
+    Ok(header)
+}
+
+#[inline]
+pub fn get_srid(buf: &[u8]) -> Result<i32, GserializedError> {
+    Ok(read_header(buf)?.srid)
+}
+
+pub fn set_srid(buf: &mut [u8], srid: i32) -> Result<(), GserializedError> {
+    if !(0..=SRID_MAXIMUM).contains(&srid) {
+        return Err(GserializedError::InvalidSrid(srid));
+    }
+    ensure_remaining(buf, 0, HEADER_SIZE)?;
+    buf[VARHDRSZ] = ((srid & 0x001F_0000) >> 16) as u8;
+    buf[VARHDRSZ + 1] = ((srid & 0x0000_FF00) >> 8) as u8;
+    buf[VARHDRSZ + 2] = (srid & 0x0000_00FF) as u8;
+    Ok(())
+}
+
+pub fn geometry_type(buf: &[u8]) -> Result<u32, GserializedError> {
+    let mut offset = read_header(buf)?.data_offset;
+    read_u32(buf, &mut offset)
+}
+
+// The name ST_GeometryType reports for a type code, without its "ST_" prefix.
+pub fn type_name(gtype: u32) -> Option<&'static str> {
+    Some(match gtype {
+        POINTTYPE => "Point",
+        LINETYPE => "LineString",
+        POLYGONTYPE => "Polygon",
+        MULTIPOINTTYPE => "MultiPoint",
+        MULTILINETYPE => "MultiLineString",
+        MULTIPOLYGONTYPE => "MultiPolygon",
+        COLLECTIONTYPE => "GeometryCollection",
+        CIRCSTRINGTYPE => "CircularString",
+        COMPOUNDTYPE => "CompoundCurve",
+        CURVEPOLYTYPE => "CurvePolygon",
+        MULTICURVETYPE => "MultiCurve",
+        MULTISURFACETYPE => "MultiSurface",
+        POLYHEDRALSURFACETYPE => "PolyhedralSurface",
+        TRIANGLETYPE => "Triangle",
+        TINTYPE => "Tin",
+        _ => return None,
+    })
+}
+
+const POINT_BATCH: usize = 64;
+
+fn transform_point_array(
+    buf: &mut [u8],
+    offset: &mut usize,
+    npoints: usize,
+    layout: (usize, bool),
+    map: &mut impl FnMut(&mut [f64], &mut [f64], Option<&mut [f64]>),
+) -> Result<(), GserializedError> {
+    let (ndims, has_z) = layout;
+    let stride = ndims * 8;
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+
+    let mut xs = [0.0; POINT_BATCH];
+    let mut ys = [0.0; POINT_BATCH];
+    let mut zs = [0.0; POINT_BATCH];
+    let mut remaining = npoints;
+    while remaining > 0 {
+        let n = remaining.min(POINT_BATCH);
+        for i in 0..n {
+            xs[i] = read_f64(buf, *offset + i * stride)?;
+            ys[i] = read_f64(buf, *offset + i * stride + 8)?;
+            if has_z {
+                zs[i] = read_f64(buf, *offset + i * stride + 16)?;
+            }
+        }
+        map(&mut xs[..n], &mut ys[..n], has_z.then_some(&mut zs[..n]));
+        for i in 0..n {
+            write_f64(buf, *offset + i * stride, xs[i])?;
+            write_f64(buf, *offset + i * stride + 8, ys[i])?;
+            if has_z {
+                write_f64(buf, *offset + i * stride + 16, zs[i])?;
+            }
+        }
+        *offset += n * stride;
+        remaining -= n;
+    }
+    Ok(())
+}
+
+fn transform_polygon(
+    buf: &mut [u8],
+    offset: &mut usize,
+    layout: (usize, bool),
+    map: &mut impl FnMut(&mut [f64], &mut [f64], Option<&mut [f64]>),
+) -> Result<(), GserializedError> {
+    let nrings = read_u32(buf, offset)? as usize;
+    ensure_remaining(buf, *offset, nrings.saturating_mul(4))?;
+    let mut ring_offset = *offset;
+    *offset += nrings * 4;
+    if nrings % 2 == 1 {
+        *offset += 4;
+    }
+    for _ in 0..nrings {
+        let npoints = read_u32(buf, &mut ring_offset)? as usize;
+        transform_point_array(buf, offset, npoints, layout, map)?;
+    }
+    Ok(())
+}
+
+fn transform_geometry(
+    buf: &mut [u8],
+    offset: &mut usize,
+    layout: (usize, bool),
+    map: &mut impl FnMut(&mut [f64], &mut [f64], Option<&mut [f64]>),
+) -> Result<(), GserializedError> {
+    let gtype = read_u32(buf, offset)?;
+    match gtype {
+        POINTTYPE | LINETYPE | CIRCSTRINGTYPE | TRIANGLETYPE => {
+            let npoints = read_u32(buf, offset)? as usize;
+            transform_point_array(buf, offset, npoints, layout, map)
+        }
+        POLYGONTYPE => transform_polygon(buf, offset, layout, map),
+        MULTIPOINTTYPE
+        | MULTILINETYPE
+        | MULTIPOLYGONTYPE
+        | COLLECTIONTYPE
+        | COMPOUNDTYPE
+        | CURVEPOLYTYPE
+        | MULTICURVETYPE
+        | MULTISURFACETYPE
+        | POLYHEDRALSURFACETYPE
+        | TINTYPE => {
+            let ngeoms = read_u32(buf, offset)? as usize;
+            for _ in 0..ngeoms {
+                transform_geometry(buf, offset, layout, map)?;
+            }
+            Ok(())
+        }
+        _ => Err(GserializedError::UnsupportedType(gtype)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gbox {
+    pub xmin: f64,
+    pub xmax: f64,
+    pub ymin: f64,
+    pub ymax: f64,
+    pub zmin: f64,
+    pub zmax: f64,
+    pub mmin: f64,
+    pub mmax: f64,
+}
+
+impl Gbox {
+    fn empty() -> Self {
+        Self {
+            xmin: f64::INFINITY,
+            xmax: f64::NEG_INFINITY,
+            ymin: f64::INFINITY,
+            ymax: f64::NEG_INFINITY,
+            zmin: f64::INFINITY,
+            zmax: f64::NEG_INFINITY,
+            mmin: f64::INFINITY,
+            mmax: f64::NEG_INFINITY,
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.xmin > self.xmax
+    }
+
+    #[inline]
+    fn add_xyz(&mut self, x: f64, y: f64, z: f64) {
+        self.xmin = self.xmin.min(x);
+        self.xmax = self.xmax.max(x);
+        self.ymin = self.ymin.min(y);
+        self.ymax = self.ymax.max(y);
+        self.zmin = self.zmin.min(z);
+        self.zmax = self.zmax.max(z);
+    }
+
+    #[inline]
+    fn add_m(&mut self, m: f64) {
+        self.mmin = self.mmin.min(m);
+        self.mmax = self.mmax.max(m);
+    }
+
+    fn merge(&mut self, other: &Gbox) {
+        self.xmin = self.xmin.min(other.xmin);
+        self.xmax = self.xmax.max(other.xmax);
+        self.ymin = self.ymin.min(other.ymin);
+        self.ymax = self.ymax.max(other.ymax);
+        self.zmin = self.zmin.min(other.zmin);
+        self.zmax = self.zmax.max(other.zmax);
+        self.mmin = self.mmin.min(other.mmin);
+        self.mmax = self.mmax.max(other.mmax);
+    }
+}
+
+#[inline]
+fn next_float_down(d: f64) -> f32 {
+    if d > f64::from(f32::MAX) {
+        return f32::MAX;
+    }
+    if d <= -f64::from(f32::MAX) {
+        return -f32::MAX;
+    }
+    let result = d as f32;
+    if f64::from(result) <= d {
+        result
+    } else {
+        result.next_down()
+    }
+}
+
+#[inline]
+fn next_float_up(d: f64) -> f32 {
+    if d >= f64::from(f32::MAX) {
+        return f32::MAX;
+    }
+    if d < -f64::from(f32::MAX) {
+        return -f32::MAX;
+    }
+    let result = d as f32;
+    if f64::from(result) >= d {
+        result
+    } else {
+        result.next_up()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PointLayout {
+    ndims: usize,
+    z_index: Option<usize>,
+    m_index: Option<usize>,
+}
+
+impl PointLayout {
+    fn from_header(header: &Header) -> Self {
+        Self {
+            ndims: header.ndims(),
+            z_index: header.has_z.then_some(2),
+            m_index: header.has_m.then_some(if header.has_z { 3 } else { 2 }),
+        }
+    }
+
+    #[inline]
+    fn stride(self) -> usize {
+        self.ndims * 8
+    }
+}
+
+#[inline]
+fn read_point(
+    buf: &[u8],
+    offset: usize,
+    layout: PointLayout,
+) -> Result<[f64; 4], GserializedError> {
+    let mut p = [0.0; 4];
+    p[0] = read_f64(buf, offset)?;
+    p[1] = read_f64(buf, offset + 8)?;
+    if let Some(i) = layout.z_index {
+        p[2] = read_f64(buf, offset + i * 8)?;
+    }
+    if let Some(i) = layout.m_index {
+        p[3] = read_f64(buf, offset + i * 8)?;
+    }
+    Ok(p)
+}
+
+// Mirrors liblwgeom's lw_arc_calculate_gbox_cartesian: the arc extent, with
+// Z/M taken from the arc end points only.
+fn arc_gbox(a1: [f64; 4], a2: [f64; 4], a3: [f64; 4], gbox: &mut Gbox) {
+    let p1 = (a1[0], a1[1]);
+    let p2 = (a2[0], a2[1]);
+    let p3 = (a3[0], a3[1]);
+
+    let mut arc = Gbox::empty();
+    arc.add_xyz(p1.0, p1.1, a1[2]);
+    arc.add_xyz(p3.0, p3.1, a3[2]);
+    arc.add_m(a1[3]);
+    arc.add_m(a3[3]);
+
+    if let Some((c, r)) = arc_center(p1, p2, p3) {
+        if p1 == p3 {
+            arc.xmin = c.0 - r;
+            arc.xmax = c.0 + r;
+            arc.ymin = c.1 - r;
+            arc.ymax = c.1 + r;
+        } else {
+            let side = segment_side(p1, p3, p2);
+            if side == segment_side(p1, p3, (c.0 - r, c.1)) {
+                arc.xmin = c.0 - r;
+            }
+            if side == segment_side(p1, p3, (c.0, c.1 - r)) {
+                arc.ymin = c.1 - r;
+            }
+            if side == segment_side(p1, p3, (c.0 + r, c.1)) {
+                arc.xmax = c.0 + r;
+            }
+            if side == segment_side(p1, p3, (c.0, c.1 + r)) {
+                arc.ymax = c.1 + r;
+            }
+        }
+    }
+    gbox.merge(&arc);
+}
+
+fn cartesian_point_array_gbox(
+    buf: &[u8],
+    offset: &mut usize,
+    npoints: usize,
+    layout: PointLayout,
+    gbox: &mut Gbox,
+) -> Result<(), GserializedError> {
+    for _ in 0..npoints {
+        let p = read_point(buf, *offset, layout)?;
+        gbox.add_xyz(p[0], p[1], p[2]);
+        gbox.add_m(p[3]);
+        *offset += layout.stride();
+    }
+    Ok(())
+}
+
+fn cartesian_arc_array_gbox(
+    buf: &[u8],
+    offset: &mut usize,
+    npoints: usize,
+    layout: PointLayout,
+    gbox: &mut Gbox,
+) -> Result<(), GserializedError> {
+    ensure_remaining(buf, *offset, npoints.saturating_mul(layout.stride()))?;
+    let start = *offset;
+    *offset += npoints * layout.stride();
+    let point = |i: usize| read_point(buf, start + i * layout.stride(), layout);
+    let mut i = 2;
+    while i < npoints {
+        arc_gbox(point(i - 2)?, point(i - 1)?, point(i)?, gbox);
+        i += 2;
+    }
+    Ok(())
+}
+
+fn cartesian_gbox(
+    buf: &[u8],
+    offset: &mut usize,
+    layout: PointLayout,
+    gbox: &mut Gbox,
+) -> Result<(), GserializedError> {
+    let gtype = read_u32(buf, offset)?;
+    match gtype {
+        POINTTYPE | LINETYPE | TRIANGLETYPE => {
+            let npoints = read_u32(buf, offset)? as usize;
+            cartesian_point_array_gbox(buf, offset, npoints, layout, gbox)
+        }
+        CIRCSTRINGTYPE => {
+            let npoints = read_u32(buf, offset)? as usize;
+            cartesian_arc_array_gbox(buf, offset, npoints, layout, gbox)
+        }
+        POLYGONTYPE => {
+            let nrings = read_u32(buf, offset)? as usize;
+            ensure_remaining(buf, *offset, nrings.saturating_mul(4))?;
+            let mut ring_offset = *offset;
+            *offset += nrings * 4;
+            if nrings % 2 == 1 {
+                *offset += 4;
+            }
+            for ring in 0..nrings {
+                let npoints = read_u32(buf, &mut ring_offset)? as usize;
+                if ring == 0 {
+                    cartesian_point_array_gbox(buf, offset, npoints, layout, gbox)?;
+                } else {
+                    ensure_remaining(buf, *offset, npoints.saturating_mul(layout.stride()))?;
+                    *offset += npoints * layout.stride();
+                }
+            }
+            Ok(())
+        }
+        MULTIPOINTTYPE
+        | MULTILINETYPE
+        | MULTIPOLYGONTYPE
+        | COLLECTIONTYPE
+        | COMPOUNDTYPE
+        | CURVEPOLYTYPE
+        | MULTICURVETYPE
+        | MULTISURFACETYPE
+        | POLYHEDRALSURFACETYPE
+        | TINTYPE => {
+            let ngeoms = read_u32(buf, offset)? as usize;
+            for _ in 0..ngeoms {
+                cartesian_gbox(buf, offset, layout, gbox)?;
+            }
+            Ok(())
+        }
+        _ => Err(GserializedError::UnsupportedType(gtype)),
+    }
+}
+
+type Point3d = [f64; 3];
+
+#[inline]
+fn dot(a: Point3d, b: Point3d) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn cross(a: Point3d, b: Point3d) -> Point3d {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[inline]
+fn normalize(p: Point3d) -> Point3d {
+    let d = dot(p, p).sqrt();
+    if d.abs() <= FP_TOLERANCE {
+        return [0.0; 3];
+    }
+    [p[0] / d, p[1] / d, p[2] / d]
+}
+
+#[inline]
+fn ll2cart(lng: f64, lat: f64) -> Point3d {
+    let x_rad = lng.to_radians();
+    let y_rad = lat.to_radians();
+    let cos_y = y_rad.cos();
+    [cos_y * x_rad.cos(), cos_y * x_rad.sin(), y_rad.sin()]
+}
+
+fn unit_normal(p1: Point3d, p2: Point3d) -> Point3d {
+    let p_dot = dot(p1, p2);
+    let p3 = if p_dot < 0.0 {
+        normalize([p1[0] + p2[0], p1[1] + p2[1], p1[2] + p2[2]])
+    } else if p_dot > 0.95 {
+        normalize([p2[0] - p1[0], p2[1] - p1[1], p2[2] - p1[2]])
+    } else {
+        p2
+    };
+    normalize(cross(p1, p3))
+}
+
+// Port of liblwgeom's edge_calculate_gbox: the geocentric box of the great
+// circle arc A1-A2, including any axis extrema the arc sweeps past.
+fn edge_gbox(a1: Point3d, a2: Point3d, gbox: &mut Gbox) {
+    gbox.add_xyz(a1[0], a1[1], a1[2]);
+    gbox.add_xyz(a2[0], a2[1], a2[2]);
+    if (0..3).all(|i| (a1[i] - a2[i]).abs() <= FP_TOLERANCE) {
+        return;
+    }
+    if (0..3).all(|i| (a1[i] + a2[i]).abs() <= FP_TOLERANCE) {
+        return;
+    }
+
+    let an = unit_normal(a1, a2);
+    let a3 = unit_normal(an, a1);
+    let r1 = (1.0, 0.0);
+    let r2 = (dot(a2, a1), dot(a2, a3));
+    let o_side = segment_side(r1, r2, (0.0, 0.0));
+
+    for axis in 0..6 {
+        let mut x = [0.0; 3];
+        x[axis / 2] = if axis % 2 == 0 { 1.0 } else { -1.0 };
+        let (mut rx, mut ry) = (dot(x, a1), dot(x, a3));
+        let d = rx.hypot(ry);
+        if d.abs() <= FP_TOLERANCE {
+            rx = 0.0;
+            ry = 0.0;
+        } else {
+            rx /= d;
+            ry /= d;
+        }
+        if segment_side(r1, r2, (rx, ry)) != o_side {
+            gbox.add_xyz(
+                rx * a1[0] + ry * a3[0],
+                rx * a1[1] + ry * a3[1],
+                rx * a1[2] + ry * a3[2],
+            );
+        }
+    }
+}
+
+fn geodetic_point_array_gbox(
+    buf: &[u8],
+    offset: &mut usize,
+    npoints: usize,
+    layout: PointLayout,
+    gbox: &mut Gbox,
+) -> Result<(), GserializedError> {
+    ensure_remaining(buf, *offset, npoints.saturating_mul(layout.stride()))?;
+    let mut prev: Option<Point3d> = None;
+    for _ in 0..npoints {
+        let p = ll2cart(read_f64(buf, *offset)?, read_f64(buf, *offset + 8)?);
+        match prev {
+            None => gbox.add_xyz(p[0], p[1], p[2]),
+            Some(a1) => edge_gbox(a1, p, gbox),
+        }
+        prev = Some(p);
+        *offset += layout.stride();
+    }
+    Ok(())
+}
+
+fn check_poles(gbox: &mut Gbox) {
+    if gbox.xmin < 0.0 && gbox.xmax > 0.0 && gbox.ymin < 0.0 && gbox.ymax > 0.0 {
+        if gbox.zmin + gbox.zmax > 0.0 {
+            gbox.zmax = 1.0;
+        } else {
+            gbox.zmin = -1.0;
+        }
+    }
+    if gbox.xmin < 0.0 && gbox.xmax > 0.0 && gbox.zmin < 0.0 && gbox.zmax > 0.0 {
+        if gbox.ymin + gbox.ymax > 0.0 {
+            gbox.ymax = 1.0;
+        } else {
+            gbox.ymin = -1.0;
+        }
+    }
+    if gbox.ymin < 0.0 && gbox.ymax > 0.0 && gbox.zmin < 0.0 && gbox.zmax > 0.0 {
+        if gbox.xmin + gbox.xmax > 0.0 {
+            gbox.xmax = 1.0;
+        } else {
+            gbox.xmin = -1.0;
+        }
+    }
+}
+
+fn geodetic_gbox(
+    buf: &[u8],
+    offset: &mut usize,
+    layout: PointLayout,
+    gbox: &mut Gbox,
+) -> Result<(), GserializedError> {
+    let gtype = read_u32(buf, offset)?;
+    match gtype {
+        POINTTYPE | LINETYPE | TRIANGLETYPE => {
+            let npoints = read_u32(buf, offset)? as usize;
+            geodetic_point_array_gbox(buf, offset, npoints, layout, gbox)
+        }
+        POLYGONTYPE => {
+            let nrings = read_u32(buf, offset)? as usize;
+            ensure_remaining(buf, *offset, nrings.saturating_mul(4))?;
+            let mut ring_offset = *offset;
+            *offset += nrings * 4;
+            if nrings % 2 == 1 {
+                *offset += 4;
+            }
+            let mut poly = Gbox::empty();
+            for _ in 0..nrings {
+                let npoints = read_u32(buf, &mut ring_offset)? as usize;
+                geodetic_point_array_gbox(buf, offset, npoints, layout, &mut poly)?;
+            }
+            if !poly.is_empty() {
+                check_poles(&mut poly);
+                gbox.merge(&poly);
+            }
+            Ok(())
+        }
+        MULTIPOINTTYPE
+        | MULTILINETYPE
+        | MULTIPOLYGONTYPE
+        | COLLECTIONTYPE
+        | POLYHEDRALSURFACETYPE
+        | TINTYPE => {
+            let ngeoms = read_u32(buf, offset)? as usize;
+            for _ in 0..ngeoms {
+                geodetic_gbox(buf, offset, layout, gbox)?;
+            }
+            Ok(())
+        }
+        _ => Err(GserializedError::UnsupportedType(gtype)),
+    }
+}
+
+pub fn calculate_gbox(buf: &[u8]) -> Result<Option<Gbox>, GserializedError> {
+    let header = read_header(buf)?;
+    let layout = PointLayout::from_header(&header);
+    let mut gbox = Gbox::empty();
+    let mut offset = header.data_offset;
+    if header.is_geodetic {
+        geodetic_gbox(buf, &mut offset, layout, &mut gbox)?;
+    } else {
+        cartesian_gbox(buf, &mut offset, layout, &mut gbox)?;
+    }
+    Ok((!gbox.is_empty()).then_some(gbox))
+}
+
+// Rewrites the cached float box in place, rounded outwards the same way
+// gserialized_from_lwgeom does. Geometries without a cached box are untouched.
+pub fn update_cached_bbox(buf: &mut [u8]) -> Result<(), GserializedError> {
+    let header = read_header(buf)?;
+    if !header.has_bbox {
+        return Ok(());
+    }
+    let Some(gbox) = calculate_gbox(buf)? else {
+        return Ok(());
+    };
+
+    let mut floats = vec![
+        next_float_down(gbox.xmin),
+        next_float_up(gbox.xmax),
+        next_float_down(gbox.ymin),
+        next_float_up(gbox.ymax),
+    ];
+    if header.is_geodetic || header.has_z {
+        floats.push(next_float_down(gbox.zmin));
+        floats.push(next_float_up(gbox.zmax));
+    }
+    if !header.is_geodetic && header.has_m {
+        floats.push(next_float_down(gbox.mmin));
+        floats.push(next_float_up(gbox.mmax));
+    }
+
+    let mut offset = header.bbox_offset;
+    for value in floats {
+        buf[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+        offset += 4;
+    }
+    Ok(())
+}
+
+pub fn transform_gserialized_in_place(
+    buf: &mut [u8],
+    transform: impl Into<Transform>,
+) -> Result<(), GserializedError> {
+    let transform = transform.into();
+    map_gserialized_in_place(buf, |xs, ys| transform.apply_batch(xs, ys))
+}
+
+// Rewrites every x/y pair in place, in batches of up to POINT_BATCH points,
+// and refreshes the cached bbox.
+pub fn map_gserialized_in_place(
+    buf: &mut [u8],
+    mut map: impl FnMut(&mut [f64], &mut [f64]),
+) -> Result<(), GserializedError> {
+    map_gserialized_xyz_in_place(buf, |xs, ys, _| map(xs, ys))
+}
+
+// Like map_gserialized_in_place, but also hands over the Z ordinates when the
+// geometry has them.
+pub fn map_gserialized_xyz_in_place(
+    buf: &mut [u8],
+    mut map: impl FnMut(&mut [f64], &mut [f64], Option<&mut [f64]>),
+) -> Result<(), GserializedError> {
+    let header = read_header(buf)?;
+    let mut offset = header.data_offset;
+    transform_geometry(buf, &mut offset, (header.ndims(), header.has_z), &mut map)?;
+    if offset != buf.len() {
+        return Err(GserializedError::TrailingData(buf.len() - offset));
+    }
+    update_cached_bbox(buf)
+}
+
+#[cfg(target_endian = "little")]
+fn set_varsize(buf: &mut [u8]) {
+    let header = (buf.len() as u32) << 2;
+    buf[..VARHDRSZ].copy_from_slice(&header.to_le_bytes());
+}
+
+#[cfg(target_endian = "big")]
+fn set_varsize(buf: &mut [u8]) {
+    let header = buf.len() as u32 & 0x3FFF_FFFF;
+    buf[..VARHDRSZ].copy_from_slice(&header.to_be_bytes());
+}
+
+#[derive(Clone, Copy)]
+struct Rewrite<'a> {
+    transform: Option<Transform>,
+    options: &'a GeometryOptions,
+    input: PointLayout,
+    output: PointLayout,
+}
+
+fn read_points(
+    buf: &[u8],
+    offset: &mut usize,
+    npoints: usize,
+    layout: PointLayout,
+) -> Result<Vec<Vertex>, GserializedError> {
+    let stride = layout.stride();
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+    let mut points: Vec<Vertex> = Vec::with_capacity(npoints);
+    for _ in 0..npoints {
+        points.push(read_point(buf, *offset, layout)?);
+        *offset += stride;
+    }
+    Ok(points)
+}
+
+fn write_coords(out: &mut Vec<u8>, points: &[Vertex], rewrite: Rewrite<'_>) {
+    for p in points {
+        out.extend_from_slice(&p[0].to_ne_bytes());
+        out.extend_from_slice(&p[1].to_ne_bytes());
+        if rewrite.output.z_index.is_some() {
+            out.extend_from_slice(&p[2].to_ne_bytes());
+        }
+        if rewrite.output.m_index.is_some() {
+            out.extend_from_slice(&p[3].to_ne_bytes());
+        }
+    }
+}
+
+// Transforms and writes a point count followed by the points. The count is
+// only known here because segmentizing a linear array adds vertices.
+fn write_point_array(
+    out: &mut Vec<u8>,
+    points: &mut Vec<Vertex>,
+    linear: bool,
+    rewrite: Rewrite<'_>,
+) -> Result<(), GserializedError> {
+    transform_vertices_segmentized(points, rewrite.transform, rewrite.options, linear)?;
+    out.extend_from_slice(&(points.len() as u32).to_ne_bytes());
+    write_coords(out, points, rewrite);
+    Ok(())
+}
+
+// Transforms and writes a polygon body: ring count, point counts (padded to
+// 8 bytes), then the rings.
+fn write_rings(
+    out: &mut Vec<u8>,
+    rings: &mut [Vec<Vertex>],
+    rewrite: Rewrite<'_>,
+) -> Result<(), GserializedError> {
+    out.extend_from_slice(&(rings.len() as u32).to_ne_bytes());
+    for ring in rings.iter_mut() {
+        transform_vertices_segmentized(ring, rewrite.transform, rewrite.options, true)?;
+        out.extend_from_slice(&(ring.len() as u32).to_ne_bytes());
+    }
+    if rings.len() % 2 == 1 {
+        out.extend_from_slice(&[0; 4]);
+    }
+    for ring in rings.iter() {
+        write_coords(out, ring, rewrite);
+    }
+    Ok(())
+}
+
+fn rewrite_point_array(
+    buf: &[u8],
+    offset: &mut usize,
+    out: &mut Vec<u8>,
+    arcs: bool,
+    rewrite: Rewrite<'_>,
+) -> Result<(), GserializedError> {
+    let npoints = read_u32(buf, offset)? as usize;
+    let mut points = read_points(buf, offset, npoints, rewrite.input)?;
+    if arcs && rewrite.options.curve == CurveMode::Refit {
+        center_arc_controls(&mut points);
+    }
+    write_point_array(out, &mut points, !arcs, rewrite)
+}
+
+// Reads a LINESTRING, CIRCULARSTRING or COMPOUNDCURVE as one linear point
+// array, with arcs replaced by chords.
+fn read_linearized(
+    buf: &[u8],
+    offset: &mut usize,
+    layout: PointLayout,
+    tolerance: f64,
+) -> Result<Vec<Vertex>, GserializedError> {
+    let gtype = read_u32(buf, offset)?;
+    let n = read_u32(buf, offset)? as usize;
+    match gtype {
+        LINETYPE => read_points(buf, offset, n, layout),
+        CIRCSTRINGTYPE => {
+            let points = read_points(buf, offset, n, layout)?;
+            Ok(linearize_arcs(&points, tolerance))
+        }
+        COMPOUNDTYPE => {
+            let mut points: Vec<Vertex> = Vec::new();
+            for _ in 0..n {
+                let part = read_linearized(buf, offset, layout, tolerance)?;
+                // Consecutive parts share their end points.
+                let skip = usize::from(!points.is_empty() && !part.is_empty());
+                points.extend_from_slice(&part[skip..]);
+            }
+            Ok(points)
+        }
+        other => Err(GserializedError::UnsupportedType(other)),
+    }
+}
+
+// Writes CIRCULARSTRING and COMPOUNDCURVE as LINESTRING and CURVEPOLYGON as
+// POLYGON; returns false for every other type.
+fn rewrite_densified(
+    buf: &[u8],
+    offset: &mut usize,
+    out: &mut Vec<u8>,
+    tolerance: f64,
+    rewrite: Rewrite<'_>,
+) -> Result<bool, GserializedError> {
+    let mut body = *offset;
+    match read_u32(buf, &mut body)? {
+        CIRCSTRINGTYPE | COMPOUNDTYPE => {
+            let mut points = read_linearized(buf, offset, rewrite.input, tolerance)?;
+            out.extend_from_slice(&LINETYPE.to_ne_bytes());
+            write_point_array(out, &mut points, true, rewrite)?;
+        }
+        CURVEPOLYTYPE => {
+            let nrings = read_u32(buf, &mut body)?;
+            *offset = body;
+            let mut rings = Vec::new();
+            for _ in 0..nrings {
+                rings.push(read_linearized(buf, offset, rewrite.input, tolerance)?);
+            }
+            out.extend_from_slice(&POLYGONTYPE.to_ne_bytes());
+            write_rings(out, &mut rings, rewrite)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn rewrite_geometry(
+    buf: &[u8],
+    offset: &mut usize,
+    out: &mut Vec<u8>,
+    rewrite: Rewrite<'_>,
+) -> Result<(), GserializedError> {
+    if let CurveMode::Densify(tolerance) = rewrite.options.curve
+        && rewrite_densified(buf, offset, out, tolerance, rewrite)?
+    {
+        return Ok(());
+    }
+
+    let count = |buf: &[u8], offset: &mut usize, out: &mut Vec<u8>| {
+        let n = read_u32(buf, offset)?;
+        out.extend_from_slice(&n.to_ne_bytes());
+        Ok::<usize, GserializedError>(n as usize)
+    };
+
+    let gtype = read_u32(buf, offset)?;
+    let out_type = match (rewrite.options.curve, gtype) {
+        (CurveMode::Densify(_), MULTICURVETYPE) => MULTILINETYPE,
+        (CurveMode::Densify(_), MULTISURFACETYPE) => MULTIPOLYGONTYPE,
+        _ => gtype,
+    };
+    out.extend_from_slice(&out_type.to_ne_bytes());
+    match gtype {
+        POINTTYPE | LINETYPE | CIRCSTRINGTYPE | TRIANGLETYPE => {
+            rewrite_point_array(buf, offset, out, gtype == CIRCSTRINGTYPE, rewrite)
+        }
+        POLYGONTYPE => {
+            let nrings = read_u32(buf, offset)? as usize;
+            ensure_remaining(buf, *offset, nrings.saturating_mul(4))?;
+            let mut ring_offset = *offset;
+            *offset += nrings * 4;
+            if nrings % 2 == 1 {
+                ensure_remaining(buf, *offset, 4)?;
+                *offset += 4;
+            }
+            let mut rings = Vec::with_capacity(nrings);
+            for _ in 0..nrings {
+                let npoints = read_u32(buf, &mut ring_offset)? as usize;
+                rings.push(read_points(buf, offset, npoints, rewrite.input)?);
+            }
+            write_rings(out, &mut rings, rewrite)
+        }
+        MULTIPOINTTYPE
+        | MULTILINETYPE
+        | MULTIPOLYGONTYPE
+        | COLLECTIONTYPE
+        | COMPOUNDTYPE
+        | CURVEPOLYTYPE
+        | MULTICURVETYPE
+        | MULTISURFACETYPE
+        | POLYHEDRALSURFACETYPE
+        | TINTYPE => {
+            let ngeoms = count(buf, offset, out)?;
+            for _ in 0..ngeoms {
+                rewrite_geometry(buf, offset, out, rewrite)?;
+            }
+            Ok(())
+        }
+        _ => Err(GserializedError::UnsupportedType(gtype)),
+    }
+}
+
+// Builds a new GSERIALIZED with the same version, SRID and flags, applying
+// `transform` to XY (when given) and `options` to Z/M. Dropping or adding a
+// dimension updates the flags, and a cached box is resized and recomputed.
+pub fn transform_gserialized(
+    input: &[u8],
+    transform: Option<Transform>,
+    options: &GeometryOptions,
+) -> Result<Vec<u8>, GserializedError> {
+    let header = read_header(input)?;
+    let (has_z, has_m) = options.output_dims(header.has_z, header.has_m);
+    let out_header = Header {
+        has_z,
+        has_m,
+        ..header
+    };
+
+    let mut out = Vec::with_capacity(input.len());
+    out.extend_from_slice(&input[..header.bbox_offset]);
+    out[VARHDRSZ + 3] &= !(GFLAG_Z | GFLAG_M);
+    if has_z {
+        out[VARHDRSZ + 3] |= GFLAG_Z;
+    }
+    if has_m {
+        out[VARHDRSZ + 3] |= GFLAG_M;
+    }
+    out.resize(header.bbox_offset + out_header.bbox_size(), 0);
+
+    let rewrite = Rewrite {
+        transform,
+        options,
+        input: PointLayout::from_header(&header),
+        output: PointLayout::from_header(&out_header),
+    };
+    let mut offset = header.data_offset;
+    rewrite_geometry(input, &mut offset, &mut out, rewrite)?;
+    if offset != input.len() {
+        return Err(GserializedError::TrailingData(input.len() - offset));
+    }
+    set_varsize(&mut out);
+    update_cached_bbox(&mut out)?;
+    Ok(out)
+}
+
+fn visit_point_array<F>(
+    buf: &[u8],
+    offset: &mut usize,
+    npoints: usize,
+    ndims: usize,
+    f: &mut F,
+) -> Result<(), GserializedError>
+where
+    F: FnMut(f64, f64),
+{
+    let stride = ndims * 8;
+    ensure_remaining(buf, *offset, npoints.saturating_mul(stride))?;
+    for _ in 0..npoints {
+        f(read_f64(buf, *offset)?, read_f64(buf, *offset + 8)?);
+        *offset += stride;
+    }
+    Ok(())
+}
+
+fn visit_geometry<F>(
+    buf: &[u8],
+    offset: &mut usize,
+    ndims: usize,
+    f: &mut F,
+) -> Result<(), GserializedError>
+where
+    F: FnMut(f64, f64),
+{
+    let gtype = read_u32(buf, offset)?;
+    match gtype {
+        POINTTYPE | LINETYPE | CIRCSTRINGTYPE | TRIANGLETYPE => {
+            let npoints = read_u32(buf, offset)? as usize;
+            visit_point_array(buf, offset, npoints, ndims, f)
+        }
+        POLYGONTYPE => {
+            let nrings = read_u32(buf, offset)? as usize;
+            ensure_remaining(buf, *offset, nrings.saturating_mul(4))?;
+            let mut ring_offset = *offset;
+            *offset += nrings * 4;
+            if nrings % 2 == 1 {
+                *offset += 4;
+            }
+            for _ in 0..nrings {
+                let npoints = read_u32(buf, &mut ring_offset)? as usize;
+                visit_point_array(buf, offset, npoints, ndims, f)?;
+            }
+            Ok(())
+        }
+        MULTIPOINTTYPE
+        | MULTILINETYPE
+        | MULTIPOLYGONTYPE
+        | COLLECTIONTYPE
+        | COMPOUNDTYPE
+        | CURVEPOLYTYPE
+        | MULTICURVETYPE
+        | MULTISURFACETYPE
+        | POLYHEDRALSURFACETYPE
+        | TINTYPE => {
+            let ngeoms = read_u32(buf, offset)? as usize;
+            for _ in 0..ngeoms {
+                visit_geometry(buf, offset, ndims, f)?;
+            }
+            Ok(())
+        }
+        _ => Err(GserializedError::UnsupportedType(gtype)),
+    }
+}
+
+// Calls `f(x, y)` for every stored vertex, including arc control points.
+pub fn for_each_point<F>(buf: &[u8], mut f: F) -> Result<(), GserializedError>
+where
+    F: FnMut(f64, f64),
+{
+    let header = read_header(buf)?;
+    let mut offset = header.data_offset;
+    visit_geometry(buf, &mut offset, header.ndims(), &mut f)?;
+    if offset != buf.len() {
+        return Err(GserializedError::TrailingData(buf.len() - offset));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::{TransformKind, apply};
+    use crate::options::{CurveMode, MMode, ZMode};
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(hex.len() / 2);
+        let bytes = hex.as_bytes();
+        let mut i = 0;
+        while i + 1 < bytes.len() {
+            let hi = (bytes[i] as char).to_digit(16).unwrap();
+            let lo = (bytes[i + 1] as char).to_digit(16).unwrap();
+            out.push(((hi << 4) | lo) as u8);
+            i += 2;
+        }
+        out
+    }
+
+    struct Builder(Vec<u8>);
+
+    impl Builder {
+        fn new(flags: u8, bbox_floats: usize) -> Self {
+            let mut buf = vec![0u8; HEADER_SIZE + bbox_floats * 4];
+            buf[VARHDRSZ + 3] = G2FLAG_VER_0 | flags;
+            Self(buf)
+        }
+
+        fn u32(mut self, value: u32) -> Self {
+            self.0.extend_from_slice(&value.to_ne_bytes());
+            self
+        }
+
+        fn coords(mut self, values: &[f64]) -> Self {
+            for value in values {
+                self.0.extend_from_slice(&value.to_ne_bytes());
+            }
+            self
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            let len = (self.0.len() as u32) << 2;
+            self.0[..4].copy_from_slice(&len.to_ne_bytes());
+            self.0
+        }
+    }
+
+    fn read_bbox(buf: &[u8], nfloats: usize) -> Vec<f32> {
+        (0..nfloats)
+            .map(|i| {
+                let mut raw = [0u8; 4];
+                raw.copy_from_slice(&buf[8 + i * 4..12 + i * 4]);
+                f32::from_ne_bytes(raw)
+            })
+            .collect()
+    }
+
+    fn read_coord(bytes: &[u8], offset: usize) -> (f64, f64) {
+        (
+            read_f64(bytes, offset).unwrap(),
+            read_f64(bytes, offset + 8).unwrap(),
+        )
+    }
+
+    // SRID=4326;POINT(120 30) as written by PostGIS 3 (v2) and PostGIS 2 (v1).
+    const POINT_V2: &str = "800000000010E64001000000010000000000000000005E400000000000003E40";
+    const POINT_V1: &str = "800000000010E60001000000010000000000000000005E400000000000003E40";
+    // SRID=990001;POLYGON((120 30,121 30,121 31,120 30)) with a cached 2D box.
+    const POLYGON_BBOX_V2: &str = concat!(
+        "A00100000F1B3144",
+        "0000F0420000F2420000F0410000F841",
+        "0300000001000000",
+        "0400000000000000",
+        "0000000000005E400000000000003E40",
+        "0000000000405E400000000000003E40",
+        "0000000000405E400000000000003F40",
+        "0000000000005E400000000000003E40",
+    );
+
+    #[test]
+    fn test_read_header_v2_point() {
+        let buf = hex_to_bytes(POINT_V2);
+        let header = read_header(&buf).unwrap();
+        assert_eq!(header.version, Version::V2);
+        assert_eq!(header.srid, 4326);
+        assert!(!header.has_z && !header.has_m && !header.has_bbox && !header.is_geodetic);
+        assert_eq!(header.data_offset, 8);
+    }
+
+    #[test]
+    fn test_read_header_v1_point() {
+        let buf = hex_to_bytes(POINT_V1);
+        let header = read_header(&buf).unwrap();
+        assert_eq!(header.version, Version::V1);
+        assert_eq!(header.srid, 4326);
+        assert_eq!(header.data_offset, 8);
+    }
+
+    #[test]
+    fn test_read_header_bbox_and_custom_srid() {
+        let buf = hex_to_bytes(POLYGON_BBOX_V2);
+        let header = read_header(&buf).unwrap();
+        assert_eq!(header.srid, 990001);
+        assert!(header.has_bbox);
+        assert_eq!(header.bbox_offset, 8);
+        assert_eq!(header.data_offset, 24);
+        assert_eq!(geometry_type(&buf), Ok(POLYGONTYPE));
+        assert_eq!(type_name(POLYGONTYPE), Some("Polygon"));
+        assert_eq!(type_name(99), None);
+    }
+
+    #[test]
+    fn test_read_header_extended_flags() {
+        let mut buf = hex_to_bytes(POINT_V2);
+        buf[7] |= G2FLAG_EXTENDED;
+        buf.splice(8..8, [0u8; 8]);
+        let header = read_header(&buf).unwrap();
+        assert_eq!(header.bbox_offset, 16);
+        assert_eq!(header.data_offset, 16);
+
+        transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap();
+        let (lng, lat) = read_coord(&buf, 24);
+        assert!((lng - 120.004_660_445_597).abs() < 1e-6);
+        assert!((lat - 29.997_534_331_696_1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_set_srid_round_trip() {
+        let mut buf = hex_to_bytes(POINT_V2);
+        set_srid(&mut buf, 990002).unwrap();
+        assert_eq!(get_srid(&buf).unwrap(), 990002);
+        assert_eq!(&buf[4..7], &[0x0F, 0x1B, 0x32]);
+        set_srid(&mut buf, 0).unwrap();
+        assert_eq!(get_srid(&buf).unwrap(), 0);
+        assert_eq!(
+            set_srid(&mut buf, 1_000_000),
+            Err(GserializedError::InvalidSrid(1_000_000))
+        );
+    }
+
+    #[test]
+    fn test_transform_point_matches_ewkb() {
+        let mut buf = hex_to_bytes(POINT_V1);
+        transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap();
+        let (lng, lat) = read_coord(&buf, 16);
+        let (want_lat, want_lng) = apply(TransformKind::Wgs2Gcj, 30.0, 120.0);
+        assert_eq!((lng, lat), (want_lng, want_lat));
+    }
+
+    #[test]
+    fn test_transform_polygon_with_bbox() {
+        let mut buf = hex_to_bytes(POLYGON_BBOX_V2);
+        transform_gserialized_in_place(&mut buf, TransformKind::Gcj2Bd).unwrap();
+        for (i, (x, y)) in [(120.0, 30.0), (121.0, 30.0), (121.0, 31.0), (120.0, 30.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let (lng, lat) = read_coord(&buf, 40 + i * 16);
+            let (want_lat, want_lng) = apply(TransformKind::Gcj2Bd, y, x);
+            assert_eq!((lng, lat), (want_lng, want_lat));
+        }
+        assert_eq!(get_srid(&buf).unwrap(), 990001);
+    }
+
+    #[test]
+    fn test_transform_recomputes_cached_bbox() {
+        let mut buf = hex_to_bytes(POLYGON_BBOX_V2);
+        transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap();
+
+        let ring: Vec<(f64, f64)> = (0..4).map(|i| read_coord(&buf, 40 + i * 16)).collect();
+        let xmin = ring.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let xmax = ring.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let ymin = ring.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let ymax = ring.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
+        let bbox = read_bbox(&buf, 4);
+        assert_eq!(
+            bbox,
+            vec![
+                next_float_down(xmin),
+                next_float_up(xmax),
+                next_float_down(ymin),
+                next_float_up(ymax)
+            ]
+        );
+        assert!(f64::from(bbox[0]) <= xmin && f64::from(bbox[1]) >= xmax);
+        assert!(f64::from(bbox[2]) <= ymin && f64::from(bbox[3]) >= ymax);
+        assert_ne!(bbox[0], 120.0);
+    }
+
+    #[test]
+    fn test_map_xyz_hands_over_z_only() {
+        let mut zm = Builder::new(GFLAG_Z | GFLAG_M, 0)
+            .u32(LINETYPE)
+            .u32(2)
+            .coords(&[120.0, 30.0, 7.0, 42.0, 121.0, 31.0, 8.0, 43.0])
+            .finish();
+        map_gserialized_xyz_in_place(&mut zm, |xs, ys, zs| {
+            let zs = zs.expect("geometry has Z");
+            for i in 0..xs.len() {
+                (xs[i], ys[i], zs[i]) = (xs[i] + 1.0, ys[i] + 1.0, zs[i] * 2.0);
+            }
+        })
+        .unwrap();
+        assert_eq!(
+            read_coords(&zm, 16),
+            vec![121.0, 31.0, 14.0, 42.0, 122.0, 32.0, 16.0, 43.0]
+        );
+
+        let mut m = Builder::new(GFLAG_M, 0)
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[120.0, 30.0, 42.0])
+            .finish();
+        map_gserialized_xyz_in_place(&mut m, |_, _, zs| assert!(zs.is_none())).unwrap();
+        assert_eq!(read_coords(&m, 16), vec![120.0, 30.0, 42.0]);
+    }
+
+    #[test]
+    fn test_for_each_point_visits_every_vertex() {
+        let buf = Builder::new(GFLAG_Z, 0)
+            .u32(MULTIPOINTTYPE)
+            .u32(2)
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[1.0, 2.0, 3.0])
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[4.0, 5.0, 6.0])
+            .finish();
+        let mut seen = Vec::new();
+        for_each_point(&buf, |x, y| seen.push((x, y))).unwrap();
+        assert_eq!(seen, vec![(1.0, 2.0), (4.0, 5.0)]);
+
+        let mut count = 0;
+        for_each_point(&hex_to_bytes(POLYGON_BBOX_V2), |_, _| count += 1).unwrap();
+        assert_eq!(count, 4);
+    }
+
+    fn read_coords(buf: &[u8], offset: usize) -> Vec<f64> {
+        (offset..buf.len())
+            .step_by(8)
+            .map(|i| read_f64(buf, i).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrite_passthrough_matches_in_place() {
+        for (flags, coords) in [
+            (GFLAG_BBOX, &[120.0, 30.0][..]),
+            (GFLAG_BBOX | GFLAG_Z, &[120.0, 30.0, 7.0][..]),
+            (GFLAG_BBOX | GFLAG_M, &[120.0, 30.0, 9.0][..]),
+            (GFLAG_BBOX | GFLAG_Z | GFLAG_M, &[120.0, 30.0, 7.0, 9.0][..]),
+        ] {
+            let nfloats = 2 * coords.len();
+            let input = Builder::new(flags, nfloats)
+                .u32(POINTTYPE)
+                .u32(1)
+                .coords(coords)
+                .finish();
+            let mut in_place = input.clone();
+            transform_gserialized_in_place(&mut in_place, TransformKind::Wgs2Gcj).unwrap();
+            let transform = Some(TransformKind::Wgs2Gcj.into());
+            let rewritten =
+                transform_gserialized(&input, transform, &GeometryOptions::default()).unwrap();
+            assert_eq!(rewritten, in_place);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_point_z_drop_shrinks_bbox() {
+        let input = Builder::new(GFLAG_BBOX | GFLAG_Z, 6)
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[120.0, 30.0, 7.0])
+            .finish();
+        let options = GeometryOptions {
+            z: ZMode::Drop,
+            ..Default::default()
+        };
+        let transform = Some(TransformKind::Wgs2Gcj.into());
+        let out = transform_gserialized(&input, transform, &options).unwrap();
+
+        let header = read_header(&out).unwrap();
+        assert!(!header.has_z && header.has_bbox);
+        assert_eq!(header.data_offset, 8 + 16);
+        assert_eq!(out.len(), header.data_offset + 8 + 16);
+        assert_eq!(
+            u32::from_ne_bytes(out[..4].try_into().unwrap()) >> 2,
+            out.len() as u32
+        );
+        let (lat, lng) = apply(TransformKind::Wgs2Gcj, 30.0, 120.0);
+        assert_eq!(read_coords(&out, header.data_offset + 8), vec![lng, lat]);
+        assert_eq!(
+            read_bbox(&out, 4),
+            vec![
+                next_float_down(lng),
+                next_float_up(lng),
+                next_float_down(lat),
+                next_float_up(lat)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_point_m_cumulative_and_z_offset() {
+        let input = Builder::new(GFLAG_M, 0)
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[120.0, 30.0, 42.0])
+            .finish();
+        let options = GeometryOptions {
+            z: ZMode::Offset(5.0),
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        let out = transform_gserialized(&input, None, &options).unwrap();
+        assert_eq!(read_coords(&out, 16), vec![120.0, 30.0, 0.0]);
+
+        let zm = Builder::new(GFLAG_Z | GFLAG_M, 0)
+            .u32(POINTTYPE)
+            .u32(1)
+            .coords(&[120.0, 30.0, 7.0, 42.0])
+            .finish();
+        let out = transform_gserialized(&zm, None, &options).unwrap();
+        assert_eq!(read_coords(&out, 16), vec![120.0, 30.0, 12.0, 0.0]);
+
+        let drop_m = GeometryOptions {
+            m: MMode::Drop,
+            ..Default::default()
+        };
+        let out = transform_gserialized(&zm, None, &drop_m).unwrap();
+        let header = read_header(&out).unwrap();
+        assert!(header.has_z && !header.has_m);
+        assert_eq!(read_coords(&out, 16), vec![120.0, 30.0, 7.0]);
+    }
+
+    #[test]
+    fn test_rewrite_polygon_adds_measure_per_ring() {
+        let input = Builder::new(0, 0)
+            .u32(POLYGONTYPE)
+            .u32(1)
+            .u32(4)
+            .u32(0)
+            .coords(&[0.0, 0.0, 3.0, 0.0, 3.0, 4.0, 0.0, 0.0])
+            .finish();
+        let options = GeometryOptions {
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        let out = transform_gserialized(&input, None, &options).unwrap();
+        assert!(read_header(&out).unwrap().has_m);
+        assert_eq!(
+            read_coords(&out, 24),
+            vec![0.0, 0.0, 0.0, 3.0, 0.0, 3.0, 3.0, 4.0, 7.0, 0.0, 0.0, 12.0]
+        );
+    }
+
+    fn densified(input: &[u8]) -> Vec<u8> {
+        let options = GeometryOptions {
+            curve: CurveMode::Densify(1e-3),
+            ..Default::default()
+        };
+        transform_gserialized(input, None, &options).unwrap()
+    }
+
+    #[test]
+    fn test_densify_curve_polygon_writes_padded_polygon() {
+        let input = Builder::new(0, 0)
+            .u32(CURVEPOLYTYPE)
+            .u32(1)
+            .u32(CIRCSTRINGTYPE)
+            .u32(3)
+            .coords(&[1.0, 0.0, -1.0, 0.0, 1.0, 0.0])
+            .finish();
+        let out = densified(&input);
+        let mut offset = HEADER_SIZE;
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), POLYGONTYPE);
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), 1);
+        let npoints = read_u32(&out, &mut offset).unwrap() as usize;
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), 0);
+        assert_eq!(out.len(), offset + npoints * 16);
+
+        let coords = read_coords(&out, offset);
+        assert_eq!(coords[..2], coords[coords.len() - 2..]);
+        for p in coords.chunks(2) {
+            assert!((p[0].hypot(p[1]) - 1.0).abs() < 1e-12);
+        }
+        assert!(coords.chunks(2).any(|p| p[1] < -0.99));
+    }
+
+    #[test]
+    fn test_densify_multi_curve_writes_multi_linestring() {
+        let input = Builder::new(GFLAG_BBOX, 4)
+            .u32(MULTICURVETYPE)
+            .u32(1)
+            .u32(COMPOUNDTYPE)
+            .u32(2)
+            .u32(CIRCSTRINGTYPE)
+            .u32(3)
+            .coords(&[0.0, 0.0, 1.0, 1.0, 2.0, 0.0])
+            .u32(LINETYPE)
+            .u32(2)
+            .coords(&[2.0, 0.0, 2.0, -3.0])
+            .finish();
+        let out = densified(&input);
+        let mut offset = HEADER_SIZE + 16;
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), MULTILINETYPE);
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), 1);
+        assert_eq!(read_u32(&out, &mut offset).unwrap(), LINETYPE);
+        let npoints = read_u32(&out, &mut offset).unwrap() as usize;
+        let coords = read_coords(&out, offset);
+        assert_eq!(coords.len(), npoints * 2);
+        assert_eq!(coords[..2], [0.0, 0.0]);
+        assert_eq!(coords[coords.len() - 4..], [2.0, 0.0, 2.0, -3.0]);
+        assert!(coords.chunks(2).any(|p| p[1] > 0.999));
+
+        let bbox = read_bbox(&out, 4);
+        assert_eq!(bbox[2], -3.0);
+        assert!(bbox[3] >= 0.999);
+    }
+
+    #[test]
+    fn test_refit_centers_arc_control_point() {
+        let input = Builder::new(0, 0)
+            .u32(CIRCSTRINGTYPE)
+            .u32(3)
+            .coords(&[1.0, 0.0, 0.6, 0.8, -1.0, 0.0])
+            .finish();
+        let options = GeometryOptions {
+            curve: CurveMode::Refit,
+            ..Default::default()
+        };
+        let out = transform_gserialized(&input, None, &options).unwrap();
+        assert_eq!(out.len(), input.len());
+        let coords = read_coords(&out, HEADER_SIZE + 8);
+        assert_eq!((coords[0], coords[1]), (1.0, 0.0));
+        assert!(coords[2].abs() < 1e-12 && (coords[3] - 1.0).abs() < 1e-12);
+        assert_eq!((coords[4], coords[5]), (-1.0, 0.0));
+    }
+
+    #[test]
+    fn test_bbox_ignores_polygon_holes() {
+        let buf = Builder::new(GFLAG_BBOX, 4)
+            .u32(POLYGONTYPE)
+            .u32(2)
+            .u32(5)
+            .u32(4)
+            .coords(&[0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0, 0.0, 0.0])
+            .coords(&[2.0, 2.0, 3.0, 2.0, 3.0, 3.0, 2.0, 2.0])
+            .finish();
+        let gbox = calculate_gbox(&buf).unwrap().unwrap();
+        assert_eq!(
+            (gbox.xmin, gbox.xmax, gbox.ymin, gbox.ymax),
+            (0.0, 10.0, 0.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn test_bbox_covers_circular_arc_extent() {
+        let buf = Builder::new(GFLAG_BBOX, 4)
+            .u32(CIRCSTRINGTYPE)
+            .u32(3)
+            .coords(&[0.0, 0.0, 1.0, 1.0, 2.0, 0.0])
+            .finish();
+        let gbox = calculate_gbox(&buf).unwrap().unwrap();
+        assert_eq!((gbox.xmin, gbox.xmax), (0.0, 2.0));
+        assert_eq!((gbox.ymin, gbox.ymax), (0.0, 1.0));
+
+        let circle = Builder::new(GFLAG_BBOX, 4)
+            .u32(CIRCSTRINGTYPE)
+            .u32(3)
+            .coords(&[0.0, 0.0, 2.0, 0.0, 0.0, 0.0])
+            .finish();
+        let gbox = calculate_gbox(&circle).unwrap().unwrap();
+        assert_eq!((gbox.xmin, gbox.xmax), (0.0, 2.0));
+        assert_eq!((gbox.ymin, gbox.ymax), (-1.0, 1.0));
+    }
+
+    #[test]
+    fn test_bbox_zm_layout() {
+        let mut buf = Builder::new(GFLAG_BBOX | GFLAG_Z | GFLAG_M, 8)
+            .u32(LINETYPE)
+            .u32(3)
+            .coords(&[120.0, 30.0, 5.0, 0.0])
+            .coords(&[121.0, 31.0, -2.0, 1.5])
+            .coords(&[122.0, 30.5, 7.0, 3.0])
+            .finish();
+        transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap();
+        let bbox = read_bbox(&buf, 8);
+        assert_eq!(&bbox[4..], &[-2.0, 7.0, 0.0, 3.0]);
+
+        let (lng, lat) = read_coord(&buf, 8 + 32 + 8);
+        let (want_lat, want_lng) = apply(TransformKind::Wgs2Gcj, 30.0, 120.0);
+        assert_eq!((lng, lat), (want_lng, want_lat));
+    }
+
+    #[test]
+    fn test_geodetic_bbox_includes_edge_extrema() {
+        let buf = Builder::new(GFLAG_BBOX | GFLAG_GEODETIC, 6)
+            .u32(LINETYPE)
+            .u32(2)
+            .coords(&[-45.0, 0.0, 45.0, 0.0])
+            .finish();
+        let header = read_header(&buf).unwrap();
+        assert_eq!(header.data_offset, 32);
+        let gbox = calculate_gbox(&buf).unwrap().unwrap();
+        assert!((gbox.xmax - 1.0).abs() < 1e-12);
+        assert!((gbox.xmin - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert!((gbox.ymin + std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert!((gbox.ymax - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert!(gbox.zmin.abs() < 1e-12 && gbox.zmax.abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bbox_float_rounding_is_outward() {
+        let v = 120.004_660_445_597;
+        assert!(f64::from(next_float_down(v)) <= v);
+        assert!(f64::from(next_float_up(v)) >= v);
+        assert_eq!(next_float_down(2.0), 2.0);
+        assert_eq!(next_float_up(2.0), 2.0);
+    }
+
+    #[test]
+    fn test_transform_rejects_truncated_input() {
+        let mut buf = hex_to_bytes(POINT_V2);
+        buf.truncate(buf.len() - 4);
+        let err = transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap_err();
+        assert_eq!(err, GserializedError::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_transform_rejects_unknown_type() {
+        let mut buf = hex_to_bytes(POINT_V2);
+        buf[8] = 0x63;
+        let err = transform_gserialized_in_place(&mut buf, TransformKind::Wgs2Gcj).unwrap_err();
+        assert_eq!(err, GserializedError::UnsupportedType(0x63));
+    }
+}
@@ -1,6 +1,52 @@
+//! GCJ02, BD09 and Baidu Mercator coordinate transforms, built as a
+//! PostgreSQL extension with the `extension` feature and usable as a plain
+//! library without it.
+//!
+//! ```
+//! use pg_eviltransform::{Datum, transform_point};
+//!
+//! let (lat, lng) = transform_point(Datum::Wgs84, Datum::Bd09, 39.915, 116.404)?;
+//! assert!(lat > 39.915 && lng > 116.404);
+//! # Ok::<(), pg_eviltransform::Error>(())
+//! ```
+
+mod api;
+mod boundary;
+// The hidden modules are internals of the extension, public only so that the
+// benchmarks can reach them; they are not part of the library API.
+#[doc(hidden)]
 pub mod coord;
+mod curve;
+#[doc(hidden)]
 pub mod ewkb;
+#[cfg(feature = "geo")]
+mod geo;
+pub mod geojson;
+#[doc(hidden)]
+pub mod gserialized;
 pub mod jenks;
+#[doc(hidden)]
+pub mod options;
+#[doc(hidden)]
+pub mod plan;
+#[cfg(feature = "proj")]
+pub(crate) mod proj;
+#[doc(hidden)]
+pub mod raster;
+#[doc(hidden)]
+pub mod sqlstate;
+pub(crate) mod srid;
+pub mod wkt;
+pub mod zone;
+
+pub use api::{Error, Transformer, transform_ewkb, transform_point, transform_wkb};
+pub use coord::{Transform, TransformKind};
+#[cfg(feature = "geo")]
+pub use geo::{EvilTransform, transform_geo};
+pub use geojson::transform_geojson;
+pub use srid::{CustomSrids, Datum, InvalidSrids};
+pub use wkt::transform_wkt;
+pub use zone::OffsetZone;
 
 #[cfg(all(test, feature = "extension"))]
 pub mod pg_test {
@@ -20,21 +66,34 @@ mod eviltransform_internal {}
 
 #[cfg(feature = "extension")]
 mod extension {
+    use std::cell::RefCell;
+    #[cfg(feature = "proj")]
+    use std::collections::HashMap;
     use std::ffi::CString;
-    use std::ptr;
+    #[cfg(feature = "proj")]
+    use std::rc::Rc;
     use std::sync::OnceLock;
 
     use pgrx::Internal;
     use pgrx::datum::AnyElement;
     use pgrx::direct_function_call;
+    use pgrx::guc::{GucContext, GucFlags, GucRegistry, GucSetting, PostgresGucEnum};
+    use pgrx::pg_sys::panic::ErrorReport;
     use pgrx::prelude::*;
 
-    use crate::coord::TransformKind;
+    use crate::coord::{Projection, Transform, TransformKind};
+    use crate::ewkb::EwkbError;
+    use crate::geojson::{transform_geojson, validate_geojson};
+    use crate::gserialized::GserializedError;
     use crate::jenks::{self, JenksCounts};
-
-    const SRID_WGS84: i32 = 4326;
-    const SRID_GCJ02: i32 = 990001;
-    const SRID_BD09: i32 = 990002;
+    use crate::options::{CurveMode, GeometryOptions, MMode, OnError, ZMode};
+    use crate::plan::{Planner, Step};
+    #[cfg(feature = "proj")]
+    use crate::proj::ProjTransform;
+    use crate::raster::Resampling;
+    use crate::sqlstate::{HasSqlState, SqlState};
+    use crate::srid::{self, CUSTOM_SYSTEMS, CustomSrids, Datum, SRID_WGS84};
+    use crate::zone::OffsetZone;
 
     const MODE_WGS2GCJ: i32 = 1;
     const MODE_GCJ2WGS: i32 = 2;
@@ -42,11 +101,140 @@ mod extension {
     const MODE_BD2WGS: i32 = 4;
     const MODE_GCJ2BD: i32 = 5;
     const MODE_BD2GCJ: i32 = 6;
+    const MODE_GCJ2WGS_EXACT: i32 = 7;
+    const MODE_BD2WGS_EXACT: i32 = 8;
+    const MODE_BD2GCJ_EXACT: i32 = 9;
+    const MODE_BD2BDMC: i32 = 10;
+    const MODE_BDMC2BD: i32 = 11;
+    const MODE_WGS2BDMC: i32 = 12;
+    const MODE_BDMC2WGS: i32 = 13;
+    const MODE_GCJ2BDMC: i32 = 14;
+    const MODE_BDMC2GCJ: i32 = 15;
+    const MODE_BDMC2WGS_EXACT: i32 = 16;
+    const MODE_BDMC2GCJ_EXACT: i32 = 17;
+
+    #[derive(Clone, Copy, PostgresGucEnum)]
+    enum OffsetZoneSetting {
+        #[name = c"rectangle"]
+        Rectangle,
+        #[name = c"border"]
+        Border,
+        #[name = c"never_skip"]
+        NeverSkip,
+        #[name = c"skip_hk_mo_tw"]
+        SkipHkMoTw,
+    }
+
+    static OFFSET_ZONE: GucSetting<OffsetZoneSetting> =
+        GucSetting::<OffsetZoneSetting>::new(OffsetZoneSetting::Rectangle);
+
+    // One setting per entry of srid::CUSTOM_SYSTEMS, in the same order.
+    static CUSTOM_SRID_GUCS: [GucSetting<i32>; CUSTOM_SYSTEMS.len()] = {
+        let mut gucs = [const { GucSetting::<i32>::new(0) }; CUSTOM_SYSTEMS.len()];
+        let mut i = 0;
+        while i < gucs.len() {
+            gucs[i] = GucSetting::<i32>::new(CUSTOM_SYSTEMS[i].default_srid);
+            i += 1;
+        }
+        gucs
+    };
+
+    #[pg_guard]
+    pub extern "C-unwind" fn _PG_init() {
+        GucRegistry::define_enum_guc(
+            c"eviltransform.offset_zone",
+            c"Area in which GCJ02/BD09 offsets are applied.",
+            c"One of rectangle, border, never_skip or skip_hk_mo_tw. Points outside the zone are passed through unchanged.",
+            &OFFSET_ZONE,
+            GucContext::Userset,
+            GucFlags::default(),
+        );
+        for (system, setting) in CUSTOM_SYSTEMS.iter().zip(&CUSTOM_SRID_GUCS) {
+            GucRegistry::define_int_guc(
+                system.guc_name,
+                system.description,
+                c"Must differ from the other eviltransform SRIDs and from 4326, 3857, 4490 and the WGS 84 UTM SRIDs.",
+                setting,
+                1,
+                // PostGIS rejects SRIDs above SRID_USER_MAXIMUM (998999); the
+                // range above it is reserved for PostGIS itself.
+                998_999,
+                GucContext::Userset,
+                GucFlags::default(),
+            );
+        }
+    }
+
+    fn errcode(state: SqlState) -> PgSqlErrorCode {
+        match state {
+            SqlState::InvalidParameterValue => PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            SqlState::InvalidBinaryRepresentation => {
+                PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION
+            }
+            SqlState::InvalidTextRepresentation => {
+                PgSqlErrorCode::ERRCODE_INVALID_TEXT_REPRESENTATION
+            }
+            SqlState::DatatypeMismatch => PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            SqlState::FeatureNotSupported => PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            SqlState::ProgramLimitExceeded => PgSqlErrorCode::ERRCODE_PROGRAM_LIMIT_EXCEEDED,
+            SqlState::UndefinedFunction => PgSqlErrorCode::ERRCODE_UNDEFINED_FUNCTION,
+            SqlState::UndefinedObject => PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            SqlState::DataCorrupted => PgSqlErrorCode::ERRCODE_DATA_CORRUPTED,
+            SqlState::Internal => PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+        }
+    }
+
+    #[track_caller]
+    fn report(state: SqlState, message: impl Into<String>) -> ErrorReport {
+        let message: String = message.into();
+        ErrorReport::new(errcode(state), message, function_name!())
+    }
+
+    #[track_caller]
+    fn raise_report(report: ErrorReport) -> ! {
+        report.report(PgLogLevel::ERROR);
+        unreachable!("ERROR reports do not return")
+    }
+
+    #[track_caller]
+    fn raise(state: SqlState, message: impl Into<String>) -> ! {
+        raise_report(report(state, message))
+    }
+
+    #[track_caller]
+    fn raise_err<E: HasSqlState + std::fmt::Display>(err: E) -> ! {
+        raise(err.sqlstate(), err.to_string())
+    }
+
+    fn current_offset_zone() -> OffsetZone {
+        match OFFSET_ZONE.get() {
+            OffsetZoneSetting::Rectangle => OffsetZone::Rectangle,
+            OffsetZoneSetting::Border => OffsetZone::Border,
+            OffsetZoneSetting::NeverSkip => OffsetZone::NeverSkip,
+            OffsetZoneSetting::SkipHkMoTw => OffsetZone::SkipHkMoTw,
+        }
+    }
+
+    fn current_custom_srids() -> CustomSrids {
+        let srids = CustomSrids::new(CUSTOM_SRID_GUCS.each_ref().map(GucSetting::get));
+        srids.validate().unwrap_or_else(|err| {
+            raise_report(
+                report(err.sqlstate(), err.to_string())
+                    .set_hint("Change the eviltransform.*_srid settings so they are all distinct."),
+            )
+        });
+        srids
+    }
+
+    fn parse_offset_zone(spec: &str) -> OffsetZone {
+        spec.parse().unwrap_or_else(|err| raise_err(err))
+    }
 
     #[inline]
     fn transform_bytes(mut input: Vec<u8>, kind: TransformKind) -> Vec<u8> {
-        if let Err(err) = crate::ewkb::transform_ewkb_in_place(&mut input, kind) {
-            error!("failed to transform EWKB geometry: {err}");
+        let transform = Transform::new(kind, current_offset_zone());
+        if let Err(err) = crate::ewkb::transform_ewkb_in_place(&mut input, transform) {
+            raise_row_error(RowError::Ewkb(err), ewkb_context(&input, None));
         }
         input
     }
@@ -60,12 +248,26 @@ mod extension {
             MODE_BD2WGS => TransformKind::Bd2Wgs,
             MODE_GCJ2BD => TransformKind::Gcj2Bd,
             MODE_BD2GCJ => TransformKind::Bd2Gcj,
-            _ => error!("unsupported transform mode: {mode}"),
+            MODE_GCJ2WGS_EXACT => TransformKind::Gcj2WgsExact,
+            MODE_BD2WGS_EXACT => TransformKind::Bd2WgsExact,
+            MODE_BD2GCJ_EXACT => TransformKind::Bd2GcjExact,
+            MODE_BD2BDMC => TransformKind::Bd2Bdmc,
+            MODE_BDMC2BD => TransformKind::Bdmc2Bd,
+            MODE_WGS2BDMC => TransformKind::Wgs2Bdmc,
+            MODE_BDMC2WGS => TransformKind::Bdmc2Wgs,
+            MODE_GCJ2BDMC => TransformKind::Gcj2Bdmc,
+            MODE_BDMC2GCJ => TransformKind::Bdmc2Gcj,
+            MODE_BDMC2WGS_EXACT => TransformKind::Bdmc2WgsExact,
+            MODE_BDMC2GCJ_EXACT => TransformKind::Bdmc2GcjExact,
+            _ => raise(
+                SqlState::InvalidParameterValue,
+                format!("unsupported transform mode: {mode}"),
+            ),
         }
     }
 
     #[pg_extern(
-        immutable,
+        stable,
         strict,
         parallel_safe,
         name = "__st_eviltransform_ewkb",
@@ -75,32 +277,43 @@ mod extension {
         transform_bytes(input, kind_from_mode(mode))
     }
 
-    unsafe extern "C" {
-        fn pgct_gserialized_get_srid(input: *const u8, out_srid: *mut i32) -> i32;
-        fn pgct_transform_gserialized(
-            input: *const u8,
-            mode: i32,
-            dst_srid: i32,
-            out_ptr: *mut *mut u8,
-            out_len: *mut usize,
-        ) -> i32;
-        fn pgct_free(ptr: *mut std::ffi::c_void);
-    }
-
     #[derive(Clone, Copy)]
     struct PostgisFns {
         st_transform_geom_int: pg_sys::Oid,
+        geometry_type: pg_sys::Oid,
+        geography_type: pg_sys::Oid,
     }
 
     fn lookup_regprocedure_oid(sig: &str) -> pg_sys::Oid {
-        let signature =
-            CString::new(sig).unwrap_or_else(|_| error!("invalid regprocedure signature: {sig}"));
+        let signature = CString::new(sig).unwrap_or_else(|_| {
+            raise(
+                SqlState::Internal,
+                format!("invalid regprocedure signature: {sig}"),
+            )
+        });
         unsafe {
             direct_function_call::<pg_sys::Oid>(
                 pg_sys::regprocedurein,
                 &[signature.as_c_str().into_datum()],
             )
-            .unwrap_or_else(|| error!("regprocedure not found: {sig}"))
+            .unwrap_or_else(|| {
+                raise(
+                    SqlState::UndefinedFunction,
+                    format!("regprocedure not found: {sig}"),
+                )
+            })
+        }
+    }
+
+    fn lookup_regtype_oid(name: &str) -> pg_sys::Oid {
+        let type_name = CString::new(name)
+            .unwrap_or_else(|_| raise(SqlState::Internal, format!("invalid type name: {name}")));
+        unsafe {
+            direct_function_call::<pg_sys::Oid>(
+                pg_sys::regtypein,
+                &[type_name.as_c_str().into_datum()],
+            )
+            .unwrap_or_else(|| raise(SqlState::UndefinedObject, format!("type not found: {name}")))
         }
     }
 
@@ -108,6 +321,8 @@ mod extension {
         static FNS: OnceLock<PostgisFns> = OnceLock::new();
         FNS.get_or_init(|| PostgisFns {
             st_transform_geom_int: lookup_regprocedure_oid("st_transform(geometry,integer)"),
+            geometry_type: lookup_regtype_oid("geometry"),
+            geography_type: lookup_regtype_oid("geography"),
         })
     }
 
@@ -116,284 +331,1122 @@ mod extension {
         unsafe { pg_sys::OidFunctionCall2Coll(oid, pg_sys::InvalidOid, a1, a2) }
     }
 
-    fn gserialized_get_srid(geom: pg_sys::Datum) -> i32 {
+    fn with_detoasted<R>(geom: pg_sys::Datum, f: impl FnOnce(&[u8]) -> R) -> R {
         let original = geom.cast_mut_ptr::<pg_sys::varlena>();
         let detoasted = unsafe { pg_sys::pg_detoast_datum(original) as *mut pg_sys::varlena };
         if detoasted.is_null() {
-            error!("failed to detoast geometry for SRID read");
+            raise(SqlState::Internal, "failed to detoast geometry");
         }
 
-        let mut srid = 0i32;
-        let rc = unsafe { pgct_gserialized_get_srid(detoasted.cast::<u8>(), &mut srid) };
+        let result = {
+            let len = unsafe { pgrx::varsize_any(detoasted) };
+            let buf = unsafe { std::slice::from_raw_parts(detoasted.cast::<u8>(), len) };
+            f(buf)
+        };
         if detoasted != original {
             unsafe { pg_sys::pfree(detoasted.cast()) };
         }
-        if rc != 0 {
-            error!("failed to read geometry SRID using librttopo, rc={rc}");
+        result
+    }
+
+    // Failures caused by one input value rather than by the call's
+    // arguments, which ST_EvilTransformSafe can turn into a NULL or
+    // passthrough row instead of an error.
+    #[derive(Debug)]
+    enum RowError {
+        ReadSrid(GserializedError),
+        Gserialized(GserializedError),
+        Ewkb(EwkbError),
+        MissingEwkbSrid,
+        UnknownSrid,
+        NoTransform { src_srid: i32, dst_srid: i32 },
+        NoSinglePass { src_srid: i32, dst_srid: i32 },
+    }
+
+    impl std::fmt::Display for RowError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                RowError::ReadSrid(err) => write!(f, "failed to read geometry SRID: {err}"),
+                RowError::Gserialized(err) => {
+                    write!(f, "failed to transform GSERIALIZED geometry: {err}")
+                }
+                RowError::Ewkb(err) => write!(f, "failed to transform EWKB geometry: {err}"),
+                RowError::MissingEwkbSrid => write!(f, "EWKB geometry has no SRID"),
+                RowError::UnknownSrid => write!(f, "input geometry has unknown (0) SRID"),
+                RowError::NoTransform { src_srid, dst_srid } => {
+                    write!(f, "no transform from SRID {src_srid} to {dst_srid}")
+                }
+                RowError::NoSinglePass { src_srid, dst_srid } => write!(
+                    f,
+                    "EWKB input needs a single custom pass, none from SRID {src_srid} to {dst_srid}"
+                ),
+            }
         }
-        srid
     }
 
-    fn apply_custom_mode(geom: pg_sys::Datum, mode: i32, dst_srid: i32) -> pg_sys::Datum {
-        let original = geom.cast_mut_ptr::<pg_sys::varlena>();
-        let detoasted = unsafe { pg_sys::pg_detoast_datum(original) as *mut pg_sys::varlena };
-        if detoasted.is_null() {
-            error!("failed to detoast geometry for custom transform");
+    impl std::error::Error for RowError {}
+
+    impl HasSqlState for RowError {
+        fn sqlstate(&self) -> SqlState {
+            match self {
+                RowError::ReadSrid(err) | RowError::Gserialized(err) => err.sqlstate(),
+                RowError::Ewkb(err) => err.sqlstate(),
+                RowError::MissingEwkbSrid
+                | RowError::UnknownSrid
+                | RowError::NoTransform { .. } => SqlState::InvalidParameterValue,
+                RowError::NoSinglePass { .. } => SqlState::FeatureNotSupported,
+            }
         }
+    }
 
-        let mut out_ptr: *mut u8 = ptr::null_mut();
-        let mut out_len = 0usize;
-        let rc = unsafe {
-            pgct_transform_gserialized(
-                detoasted.cast::<u8>(),
-                mode,
-                dst_srid,
-                &mut out_ptr,
-                &mut out_len,
+    impl RowError {
+        fn hint(&self) -> Option<&'static str> {
+            match self {
+                RowError::Ewkb(EwkbError::TooManyPoints { .. })
+                | RowError::Gserialized(GserializedError::TooManyPoints) => {
+                    Some("Use a larger max_segment_error.")
+                }
+                RowError::Ewkb(_) => Some("Check the input with ST_EvilValidateEWKB."),
+                RowError::MissingEwkbSrid => {
+                    Some("Write the EWKB with an SRID, for example with ST_AsEWKB.")
+                }
+                RowError::UnknownSrid => Some("Set the input SRID with ST_SetSRID."),
+                RowError::NoTransform { .. } => {
+                    Some("Use custom SRIDs or SRIDs known to ST_Transform on both sides.")
+                }
+                RowError::NoSinglePass { .. } => {
+                    Some("Convert the EWKB to geometry and use ST_EvilTransform.")
+                }
+                RowError::ReadSrid(_) | RowError::Gserialized(_) => None,
+            }
+        }
+    }
+
+    // DETAIL for a failed transform: the SRID pair and the input's type, as
+    // far as they are known.
+    fn transform_context(
+        src_srid: Option<i32>,
+        dst_srid: Option<i32>,
+        gtype: Option<&str>,
+    ) -> String {
+        let srid =
+            |srid: Option<i32>| srid.map_or_else(|| "unknown".to_string(), |s| s.to_string());
+        let gtype = gtype.unwrap_or("unknown");
+        format!(
+            "source SRID {}, target SRID {}, geometry type {gtype}",
+            srid(src_srid),
+            srid(dst_srid)
+        )
+    }
+
+    fn geometry_context(geom: pg_sys::Datum, dst_srid: i32) -> String {
+        let (src_srid, gtype) = with_detoasted(geom, |buf| {
+            (
+                crate::gserialized::get_srid(buf).ok(),
+                crate::gserialized::geometry_type(buf)
+                    .ok()
+                    .and_then(crate::gserialized::type_name),
             )
-        };
+        });
+        transform_context(src_srid, Some(dst_srid), gtype)
+    }
 
-        if detoasted != original {
-            unsafe { pg_sys::pfree(detoasted.cast()) };
-        }
+    fn ewkb_context(buf: &[u8], dst_srid: Option<i32>) -> String {
+        transform_context(
+            crate::ewkb::get_srid(buf).ok().flatten(),
+            dst_srid,
+            crate::ewkb::geometry_type(buf)
+                .ok()
+                .and_then(crate::ewkb::type_name),
+        )
+    }
 
-        if rc != 0 || out_ptr.is_null() || out_len == 0 {
-            error!("librttopo transform failed, rc={rc}, len={out_len}");
+    #[track_caller]
+    fn row_error_report(err: &RowError, message: String, context: String) -> ErrorReport {
+        let error_report = report(err.sqlstate(), message).set_detail(context);
+        match err.hint() {
+            Some(hint) => error_report.set_hint(hint),
+            None => error_report,
         }
-        if out_len > i32::MAX as usize {
-            unsafe { pgct_free(out_ptr.cast()) };
-            error!("librttopo transform output too large: {out_len}");
+    }
+
+    #[track_caller]
+    fn raise_row_error(err: RowError, context: String) -> ! {
+        raise_report(row_error_report(&err, err.to_string(), context))
+    }
+
+    fn gserialized_get_srid(geom: pg_sys::Datum) -> Result<i32, RowError> {
+        with_detoasted(geom, crate::gserialized::get_srid).map_err(RowError::ReadSrid)
+    }
+
+    fn apply_custom_mode(
+        geom: pg_sys::Datum,
+        transform: Transform,
+        dst_srid: i32,
+        options: &GeometryOptions,
+    ) -> Result<pg_sys::Datum, RowError> {
+        if !options.is_passthrough() {
+            let rewritten = with_detoasted(geom, |buf| {
+                crate::gserialized::transform_gserialized(buf, Some(transform), options)
+            })
+            .and_then(|mut buf| crate::gserialized::set_srid(&mut buf, dst_srid).map(|()| buf))
+            .map_err(RowError::Gserialized)?;
+            return Ok(bytes_to_datum(&rewritten));
         }
 
-        let out_varlena = unsafe { pg_sys::palloc(out_len) as *mut u8 };
-        if out_varlena.is_null() {
-            unsafe { pgct_free(out_ptr.cast()) };
-            error!("palloc failed for transformed geometry, len={out_len}");
+        map_in_place(geom, dst_srid, |xs, ys, _| transform.apply_batch(xs, ys))
+    }
+
+    // Rewrites the x/y pairs (and Z, for callers that want it) of a detoasted
+    // copy of `geom` and sets its SRID, leaving the layout untouched.
+    fn map_in_place(
+        geom: pg_sys::Datum,
+        dst_srid: i32,
+        map: impl FnMut(&mut [f64], &mut [f64], Option<&mut [f64]>),
+    ) -> Result<pg_sys::Datum, RowError> {
+        let original = geom.cast_mut_ptr::<pg_sys::varlena>();
+        let copy = unsafe { pg_sys::pg_detoast_datum_copy(original) };
+        if copy.is_null() {
+            raise(
+                SqlState::Internal,
+                "failed to detoast geometry for custom transform",
+            );
         }
 
-        unsafe {
-            ptr::copy_nonoverlapping(out_ptr, out_varlena, out_len);
-            pgct_free(out_ptr.cast());
-            pgrx::set_varsize_4b(out_varlena.cast::<pg_sys::varlena>(), out_len as i32);
+        let len = unsafe { pgrx::varsize_any(copy) };
+        let buf = unsafe { std::slice::from_raw_parts_mut(copy.cast::<u8>(), len) };
+        let result = crate::gserialized::map_gserialized_xyz_in_place(buf, map)
+            .and_then(|()| crate::gserialized::set_srid(buf, dst_srid));
+        if let Err(err) = result {
+            unsafe { pg_sys::pfree(copy.cast()) };
+            return Err(RowError::Gserialized(err));
         }
 
-        pg_sys::Datum::from(out_varlena.cast::<pg_sys::varlena>())
+        Ok(pg_sys::Datum::from(copy))
     }
 
-    #[pg_extern(immutable, strict, parallel_safe, name = "st_eviltransform")]
-    fn st_eviltransform_integer(geom: AnyElement, dst_srid: i32) -> AnyElement {
-        let fns = postgis_fns();
-        let input = geom.datum();
-        let src_srid = gserialized_get_srid(input);
-
-        let result = if src_srid == dst_srid {
-            input
-        } else if src_srid != SRID_GCJ02
-            && src_srid != SRID_BD09
-            && dst_srid != SRID_GCJ02
-            && dst_srid != SRID_BD09
-        {
-            unsafe { call2(fns.st_transform_geom_int, input, dst_srid.into()) }
-        } else if src_srid == SRID_GCJ02 && dst_srid == SRID_BD09 {
-            apply_custom_mode(input, MODE_GCJ2BD, SRID_BD09)
-        } else if src_srid == SRID_BD09 && dst_srid == SRID_GCJ02 {
-            apply_custom_mode(input, MODE_BD2GCJ, SRID_GCJ02)
-        } else if src_srid == SRID_GCJ02 {
-            let wgs = apply_custom_mode(input, MODE_GCJ2WGS, SRID_WGS84);
-            if dst_srid == SRID_WGS84 {
-                wgs
-            } else {
-                unsafe { call2(fns.st_transform_geom_int, wgs, dst_srid.into()) }
+    thread_local! {
+        static PLANNER: RefCell<Option<Planner>> = const { RefCell::new(None) };
+    }
+
+    // Plans are cached for the life of the backend and dropped when the
+    // custom SRID settings change.
+    fn with_planner<R>(f: impl FnOnce(&mut Planner) -> R) -> R {
+        let srids = current_custom_srids();
+        PLANNER.with_borrow_mut(|planner| {
+            if planner.as_ref().is_none_or(|p| *p.srids() != srids) {
+                *planner = Some(Planner::new(srids));
             }
-        } else if src_srid == SRID_BD09 {
-            let wgs = apply_custom_mode(input, MODE_BD2WGS, SRID_WGS84);
-            if dst_srid == SRID_WGS84 {
-                wgs
-            } else {
-                unsafe { call2(fns.st_transform_geom_int, wgs, dst_srid.into()) }
+            f(planner.as_mut().expect("planner was just created"))
+        })
+    }
+
+    fn step_transform(step: Step, zone: OffsetZone, tolerance: Option<f64>) -> Option<Transform> {
+        match step {
+            Step::Custom { kind, from, to, .. } => {
+                let transform = Transform::new(kind, zone).with_projections(from, to);
+                Some(tolerance.map_or(transform, |t| transform.with_tolerance(t)))
             }
-        } else if dst_srid == SRID_GCJ02 {
-            let wgs = if src_srid == SRID_WGS84 {
-                input
-            } else {
-                unsafe { call2(fns.st_transform_geom_int, input, SRID_WGS84.into()) }
-            };
-            apply_custom_mode(wgs, MODE_WGS2GCJ, SRID_GCJ02)
-        } else if dst_srid == SRID_BD09 {
-            let wgs = if src_srid == SRID_WGS84 {
-                input
-            } else {
-                unsafe { call2(fns.st_transform_geom_int, input, SRID_WGS84.into()) }
-            };
-            apply_custom_mode(wgs, MODE_WGS2BD, SRID_BD09)
-        } else {
-            unsafe { call2(fns.st_transform_geom_int, input, dst_srid.into()) }
-        };
+            Step::StTransform { .. } => None,
+        }
+    }
 
-        unsafe { <AnyElement as FromDatum>::from_polymorphic_datum(result, false, geom.oid()) }
-            .unwrap_or_else(|| error!("failed to build transformed geometry datum"))
+    // A single custom pass from `src_srid` to `dst_srid` when both have a frame.
+    fn single_pass(
+        src_srid: i32,
+        dst_srid: i32,
+        exact: bool,
+        zone: OffsetZone,
+    ) -> Option<Transform> {
+        step_transform(
+            with_planner(|p| p.direct(src_srid, dst_srid, exact))?,
+            zone,
+            None,
+        )
     }
 
-    fn values_to_jenks<I>(values: I, breaks: i32, invert: bool) -> Option<Vec<f64>>
-    where
-        I: IntoIterator<Item = f64>,
-    {
-        match jenks::breaks_from_values(values, breaks, invert) {
-            Ok(result) => result,
-            Err(err) => error!("{err}"),
+    // Errors raised by the ST_Transform steps themselves still abort the
+    // statement; only failures reading or rewriting the input are returned.
+    fn try_evil_transform(
+        input: pg_sys::Datum,
+        dst_srid: i32,
+        exact: bool,
+        zone: OffsetZone,
+        options: &GeometryOptions,
+    ) -> Result<pg_sys::Datum, RowError> {
+        let src_srid = gserialized_get_srid(input)?;
+        if src_srid == 0 && dst_srid != 0 {
+            return Err(RowError::UnknownSrid);
         }
+        let plan = with_planner(|p| p.plan(src_srid, dst_srid, exact))
+            .ok_or(RowError::NoTransform { src_srid, dst_srid })?;
+        #[cfg(feature = "proj")]
+        if let Some(output) = proj_fused(input, src_srid, dst_srid, &plan, zone, options) {
+            return output;
+        }
+        plan.iter().try_fold(input, |geom, &step| match step {
+            Step::StTransform { srid } => {
+                Ok(unsafe { call2(postgis_fns().st_transform_geom_int, geom, srid.into()) })
+            }
+            Step::Custom { srid, .. } => {
+                let transform =
+                    step_transform(step, zone, options.exact_tolerance).expect("custom step");
+                apply_custom_mode(geom, transform, srid, options)
+            }
+        })
     }
 
-    fn anynumeric_to_f64(value: AnyNumeric) -> f64 {
-        match f64::try_from(value) {
-            Ok(value) if value.is_finite() => value,
-            Ok(value) => error!("Jenks input must be finite f64, got {value}"),
-            Err(err) => error!("numeric value cannot be converted to finite f64: {err}"),
+    fn evil_transform(
+        input: pg_sys::Datum,
+        dst_srid: i32,
+        exact: bool,
+        zone: OffsetZone,
+        options: &GeometryOptions,
+    ) -> pg_sys::Datum {
+        try_evil_transform(input, dst_srid, exact, zone, options)
+            .unwrap_or_else(|err| raise_row_error(err, geometry_context(input, dst_srid)))
+    }
+
+    #[cfg(feature = "proj")]
+    thread_local! {
+        static PROJ_TRANSFORMS: RefCell<HashMap<(i32, i32), Rc<ProjTransform>>> =
+            RefCell::new(HashMap::new());
+    }
+
+    // Definitions for `srid` in the order PostGIS tries them: the authority
+    // code, then srtext, then proj4text.
+    #[cfg(feature = "proj")]
+    fn spatial_ref_definitions(srid: i32) -> Vec<String> {
+        let row = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT auth_name || ':' || auth_srid, srtext, proj4text
+                     FROM spatial_ref_sys WHERE srid = $1",
+                    Some(1),
+                    &[srid.into()],
+                )?
+                .first()
+                .get_three::<String, String, String>()
+        });
+        match row {
+            Ok((auth, srtext, proj4text)) => [auth, srtext, proj4text]
+                .into_iter()
+                .flatten()
+                .filter(|definition| !definition.trim().is_empty())
+                .collect(),
+            Err(pgrx::spi::SpiError::InvalidPosition) => raise(
+                SqlState::InvalidParameterValue,
+                format!("SRID {srid} not found in spatial_ref_sys"),
+            ),
+            Err(err) => raise(
+                SqlState::Internal,
+                format!("failed to read spatial_ref_sys: {err}"),
+            ),
         }
     }
 
-    fn collect_array_values<T, F>(values: Vec<Option<T>>, convert: F) -> Vec<f64>
-    where
-        F: Fn(T) -> f64,
-    {
-        values
-            .into_iter()
-            .filter_map(|value| value.map(&convert))
-            .collect()
+    // PROJ transformers are built once per SRID pair for the life of the
+    // backend, like PostGIS's own cache.
+    #[cfg(feature = "proj")]
+    fn proj_transform(src_srid: i32, dst_srid: i32) -> Rc<ProjTransform> {
+        PROJ_TRANSFORMS.with_borrow_mut(|cache| {
+            if let Some(transform) = cache.get(&(src_srid, dst_srid)) {
+                return transform.clone();
+            }
+            let src = spatial_ref_definitions(src_srid);
+            let dst = spatial_ref_definitions(dst_srid);
+            let transform = ProjTransform::new(
+                &src.iter().map(String::as_str).collect::<Vec<_>>(),
+                &dst.iter().map(String::as_str).collect::<Vec<_>>(),
+            )
+            .map(Rc::new)
+            .unwrap_or_else(|err| {
+                raise(
+                    err.sqlstate(),
+                    format!(
+                        "failed to build PROJ transform from SRID {src_srid} to {dst_srid}: {err}"
+                    ),
+                )
+            });
+            cache.insert((src_srid, dst_srid), transform.clone());
+            transform
+        })
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_float8_array(values: Vec<Option<f64>>, breaks: i32) -> Option<Vec<f64>> {
-        st_jenksbins_float8_array_invert(values, breaks, false)
+    // Runs a custom step and its neighbouring ST_Transform step in a single
+    // pass over the coordinates, calling PROJ directly. Plans of any other
+    // shape, and options that change the layout, take the step-by-step path.
+    #[cfg(feature = "proj")]
+    fn proj_fused(
+        input: pg_sys::Datum,
+        src_srid: i32,
+        dst_srid: i32,
+        plan: &[Step],
+        zone: OffsetZone,
+        options: &GeometryOptions,
+    ) -> Option<Result<pg_sys::Datum, RowError>> {
+        if !options.is_passthrough() {
+            return None;
+        }
+        let (custom, proj_src, proj_dst, custom_first) = match *plan {
+            [custom @ Step::Custom { srid, .. }, Step::StTransform { .. }] => {
+                (custom, srid, dst_srid, true)
+            }
+            [Step::StTransform { srid }, custom @ Step::Custom { .. }] => {
+                (custom, src_srid, srid, false)
+            }
+            _ => return None,
+        };
+        let transform = step_transform(custom, zone, options.exact_tolerance)?;
+        let proj = proj_transform(proj_src, proj_dst);
+        let project = |xs: &mut [f64], ys: &mut [f64], zs: Option<&mut [f64]>| {
+            proj.apply_batch(xs, ys, zs).unwrap_or_else(|err| {
+                raise(
+                    err.sqlstate(),
+                    format!("failed to transform from SRID {proj_src} to {proj_dst}: {err}"),
+                )
+            })
+        };
+        Some(map_in_place(input, dst_srid, |xs, ys, zs| {
+            if custom_first {
+                transform.apply_batch(xs, ys);
+                project(xs, ys, zs);
+            } else {
+                project(xs, ys, zs);
+                transform.apply_batch(xs, ys);
+            }
+        }))
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_float8_array_invert(
-        values: Vec<Option<f64>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(collect_array_values(values, |value| value), breaks, invert)
+    fn parse_geometry_options(z_mode: &str, m_mode: &str) -> GeometryOptions {
+        GeometryOptions {
+            z: z_mode.parse::<ZMode>().unwrap_or_else(|err| raise_err(err)),
+            m: m_mode.parse::<MMode>().unwrap_or_else(|err| raise_err(err)),
+            ..Default::default()
+        }
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_float4_array(values: Vec<Option<f32>>, breaks: i32) -> Option<Vec<f64>> {
-        st_jenksbins_float4_array_invert(values, breaks, false)
+    fn parse_curve_mode(curve_mode: &str) -> CurveMode {
+        curve_mode.parse().unwrap_or_else(|err| raise_err(err))
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_float4_array_invert(
-        values: Vec<Option<f32>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(
-            collect_array_values(values, |value| f64::from(value)),
-            breaks,
-            invert,
-        )
+    fn bytes_to_datum(bytes: &[u8]) -> pg_sys::Datum {
+        unsafe {
+            let ptr = pg_sys::palloc(bytes.len()).cast::<u8>();
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            pg_sys::Datum::from(ptr)
+        }
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int8_array(values: Vec<Option<i64>>, breaks: i32) -> Option<Vec<f64>> {
-        st_jenksbins_int8_array_invert(values, breaks, false)
+    fn apply_geometry_options(geom: pg_sys::Datum, options: &GeometryOptions) -> pg_sys::Datum {
+        if options.is_passthrough() {
+            return geom;
+        }
+        let rewritten = with_detoasted(geom, |buf| {
+            crate::gserialized::transform_gserialized(buf, None, options)
+        })
+        .unwrap_or_else(|err| {
+            raise(
+                err.sqlstate(),
+                format!("failed to rewrite GSERIALIZED geometry: {err}"),
+            )
+        });
+        bytes_to_datum(&rewritten)
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int8_array_invert(
-        values: Vec<Option<i64>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(
-            collect_array_values(values, |value| value as f64),
-            breaks,
-            invert,
-        )
+    fn geometry_datum(result: pg_sys::Datum, geom: &AnyElement) -> AnyElement {
+        unsafe { <AnyElement as FromDatum>::from_polymorphic_datum(result, false, geom.oid()) }
+            .unwrap_or_else(|| {
+                raise(
+                    SqlState::Internal,
+                    "failed to build transformed geometry datum",
+                )
+            })
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int4_array(values: Vec<Option<i32>>, breaks: i32) -> Option<Vec<f64>> {
-        st_jenksbins_int4_array_invert(values, breaks, false)
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer(geom: AnyElement, dst_srid: i32) -> AnyElement {
+        geometry_datum(
+            evil_transform(
+                geom.datum(),
+                dst_srid,
+                false,
+                current_offset_zone(),
+                &GeometryOptions::default(),
+            ),
+            &geom,
+        )
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int4_array_invert(
-        values: Vec<Option<i32>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(
-            collect_array_values(values, |value| f64::from(value)),
-            breaks,
-            invert,
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_exact(geom: AnyElement, dst_srid: i32, exact: bool) -> AnyElement {
+        geometry_datum(
+            evil_transform(
+                geom.datum(),
+                dst_srid,
+                exact,
+                current_offset_zone(),
+                &GeometryOptions::default(),
+            ),
+            &geom,
         )
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int2_array(values: Vec<Option<i16>>, breaks: i32) -> Option<Vec<f64>> {
-        st_jenksbins_int2_array_invert(values, breaks, false)
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_exact_tolerance(
+        geom: AnyElement,
+        dst_srid: i32,
+        exact: bool,
+        tolerance: f64,
+    ) -> AnyElement {
+        if !(tolerance.is_finite() && tolerance > 0.0) {
+            raise(
+                SqlState::InvalidParameterValue,
+                format!("tolerance must be a positive number, got {tolerance}"),
+            );
+        }
+        let options = GeometryOptions {
+            exact_tolerance: Some(tolerance),
+            ..Default::default()
+        };
+        geometry_datum(
+            evil_transform(
+                geom.datum(),
+                dst_srid,
+                exact,
+                current_offset_zone(),
+                &options,
+            ),
+            &geom,
+        )
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_int2_array_invert(
-        values: Vec<Option<i16>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(
-            collect_array_values(values, |value| f64::from(value)),
-            breaks,
-            invert,
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_zone(
+        geom: AnyElement,
+        dst_srid: i32,
+        offset_zone: &str,
+    ) -> AnyElement {
+        geometry_datum(
+            evil_transform(
+                geom.datum(),
+                dst_srid,
+                false,
+                parse_offset_zone(offset_zone),
+                &GeometryOptions::default(),
+            ),
+            &geom,
         )
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_numeric_array(
-        values: Vec<Option<AnyNumeric>>,
-        breaks: i32,
-    ) -> Option<Vec<f64>> {
-        st_jenksbins_numeric_array_invert(values, breaks, false)
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_dims(
+        geom: AnyElement,
+        dst_srid: i32,
+        z_mode: &str,
+        m_mode: &str,
+    ) -> AnyElement {
+        let options = parse_geometry_options(z_mode, m_mode);
+        let result = evil_transform(
+            geom.datum(),
+            dst_srid,
+            false,
+            current_offset_zone(),
+            &GeometryOptions::default(),
+        );
+        geometry_datum(apply_geometry_options(result, &options), &geom)
     }
 
-    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
-    fn st_jenksbins_numeric_array_invert(
-        values: Vec<Option<AnyNumeric>>,
-        breaks: i32,
-        invert: bool,
-    ) -> Option<Vec<f64>> {
-        values_to_jenks(
-            collect_array_values(values, anynumeric_to_f64),
-            breaks,
-            invert,
+    // Curve handling runs with the custom step, before the coordinates
+    // move; Z and M are applied to the final output as in the overload above.
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_dims_curve(
+        geom: AnyElement,
+        dst_srid: i32,
+        z_mode: &str,
+        m_mode: &str,
+        curve_mode: &str,
+    ) -> AnyElement {
+        let options = parse_geometry_options(z_mode, m_mode);
+        let curve = GeometryOptions {
+            curve: parse_curve_mode(curve_mode),
+            ..Default::default()
+        };
+        let result = evil_transform(geom.datum(), dst_srid, false, current_offset_zone(), &curve);
+        geometry_datum(apply_geometry_options(result, &options), &geom)
+    }
+
+    #[pg_extern(stable, strict, parallel_safe, name = "st_eviltransform")]
+    fn st_eviltransform_integer_max_error(
+        geom: AnyElement,
+        dst_srid: i32,
+        max_segment_error: f64,
+    ) -> AnyElement {
+        if !(max_segment_error.is_finite() && max_segment_error > 0.0) {
+            raise(
+                SqlState::InvalidParameterValue,
+                format!("max_segment_error must be a positive number, got {max_segment_error}"),
+            );
+        }
+        let options = GeometryOptions {
+            max_segment_error: Some(max_segment_error),
+            ..Default::default()
+        };
+        geometry_datum(
+            evil_transform(
+                geom.datum(),
+                dst_srid,
+                false,
+                current_offset_zone(),
+                &options,
+            ),
+            &geom,
         )
     }
 
-    #[derive(Clone, Default)]
-    struct JenksBinsState {
-        counts: JenksCounts,
-        breaks: i32,
-        invert: bool,
-        initialized: bool,
+    // Reports a row that failed to transform and picks what to return for it.
+    fn on_row_error<T>(err: RowError, context: String, on_error: OnError, input: T) -> Option<T> {
+        let (message, output) = match on_error {
+            OnError::Null => (format!("{err}, returning NULL"), None),
+            OnError::Passthrough => (format!("{err}, returning the input unchanged"), Some(input)),
+            OnError::Error => raise_row_error(err, context),
+        };
+        row_error_report(&err, message, context).report(PgLogLevel::WARNING);
+        output
     }
 
-    impl JenksBinsState {
-        fn add_value(&mut self, value: Option<f64>, breaks: i32, invert: bool) {
-            if breaks < 1 {
-                error!("breaks must be greater than or equal to 1");
+    fn parse_on_error(spec: &str) -> OnError {
+        spec.parse().unwrap_or_else(|err| raise_err(err))
+    }
+
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_eviltransform_safe",
+        schema = "eviltransform_internal"
+    )]
+    fn st_eviltransform_safe_internal(
+        geom: AnyElement,
+        dst_srid: i32,
+        on_error: &str,
+    ) -> Option<AnyElement> {
+        let on_error = parse_on_error(on_error);
+        match try_evil_transform(
+            geom.datum(),
+            dst_srid,
+            false,
+            current_offset_zone(),
+            &GeometryOptions::default(),
+        ) {
+            Ok(result) => Some(geometry_datum(result, &geom)),
+            Err(err) => {
+                let context = geometry_context(geom.datum(), dst_srid);
+                on_row_error(err, context, on_error, geom)
             }
-            if self.initialized {
-                if self.breaks != breaks || self.invert != invert {
-                    error!("ST_JenksBins aggregate breaks and invert arguments must be constant");
-                }
-            } else {
-                self.breaks = breaks;
-                self.invert = invert;
+        }
+    }
+
+    // Raw EWKB takes a single custom pass; the source SRID comes from its
+    // header.
+    fn transform_ewkb_to(buf: &mut Vec<u8>, dst_srid: i32) -> Result<(), RowError> {
+        let src_srid = crate::ewkb::get_srid(buf)
+            .map_err(RowError::Ewkb)?
+            .ok_or(RowError::MissingEwkbSrid)?;
+        let transform = single_pass(src_srid, dst_srid, false, current_offset_zone())
+            .ok_or(RowError::NoSinglePass { src_srid, dst_srid })?;
+        crate::ewkb::transform_ewkb_in_place(buf, transform)
+            .and_then(|()| crate::ewkb::set_srid(buf, dst_srid))
+            .map_err(RowError::Ewkb)
+    }
+
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_eviltransform_safe_ewkb",
+        schema = "eviltransform_internal"
+    )]
+    fn st_eviltransform_safe_ewkb_internal(
+        ewkb: Vec<u8>,
+        dst_srid: i32,
+        on_error: &str,
+    ) -> Option<Vec<u8>> {
+        let on_error = parse_on_error(on_error);
+        let mut output = ewkb.clone();
+        match transform_ewkb_to(&mut output, dst_srid) {
+            Ok(()) => Some(output),
+            Err(err) => {
+                let context = ewkb_context(&ewkb, Some(dst_srid));
+                on_row_error(err, context, on_error, ewkb)
+            }
+        }
+    }
+
+    // Whether geography values may use `srid`: a lng/lat system other than
+    // BD09MC.
+    fn is_geodetic(srids: &CustomSrids, srid: i32) -> bool {
+        matches!(
+            srids.frame(srid),
+            Some((
+                Datum::Wgs84 | Datum::Gcj02 | Datum::Bd09,
+                Projection::LngLat
+            ))
+        )
+    }
+
+    fn parse_geodetic(srids: &CustomSrids, spec: &str, what: &str) -> i32 {
+        let srid = match spec.trim().to_ascii_uppercase().as_str() {
+            "4326" | "EPSG:4326" | "WGS84" | "WGS-84" => Some(SRID_WGS84),
+            _ => srids.parse(spec),
+        };
+        srid.filter(|&srid| is_geodetic(srids, srid))
+            .unwrap_or_else(|| {
+                raise(
+                    SqlState::InvalidParameterValue,
+                    format!(
+                        "{what} must be a geodetic system (WGS84, GCJ02 or BD09), got {spec:?}"
+                    ),
+                )
+            })
+    }
+
+    // Geography values carry the geodetic flag and a lng/lat SRID, so only
+    // the offset itself is applied; the serialized header keeps its flags.
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_eviltransform_geography",
+        schema = "eviltransform_internal"
+    )]
+    fn st_eviltransform_geography_internal(geog: AnyElement, target: &str) -> AnyElement {
+        if geog.oid() != postgis_fns().geography_type {
+            raise(SqlState::DatatypeMismatch, "expected a geography value");
+        }
+        let srids = current_custom_srids();
+        let dst_srid = parse_geodetic(&srids, target, "geography target");
+        let src_srid = match gserialized_get_srid(geog.datum()) {
+            Ok(0) => SRID_WGS84,
+            Ok(srid) => srid,
+            Err(err) => raise_row_error(err, geometry_context(geog.datum(), dst_srid)),
+        };
+        if src_srid == dst_srid {
+            return geog;
+        }
+        if !is_geodetic(&srids, src_srid) {
+            let expected: Vec<String> = srids
+                .all()
+                .into_iter()
+                .filter(|&srid| is_geodetic(&srids, srid))
+                .map(|srid| srid.to_string())
+                .collect();
+            raise(
+                SqlState::InvalidParameterValue,
+                format!(
+                    "unsupported geography SRID {src_srid}, expected 4326, {}",
+                    expected.join(" or ")
+                ),
+            );
+        }
+        let transform = single_pass(src_srid, dst_srid, false, current_offset_zone())
+            .unwrap_or_else(|| {
+                raise_row_error(
+                    RowError::NoTransform { src_srid, dst_srid },
+                    geometry_context(geog.datum(), dst_srid),
+                )
+            });
+        geometry_datum(
+            apply_custom_mode(
+                geog.datum(),
+                transform,
+                dst_srid,
+                &GeometryOptions::default(),
+            )
+            .unwrap_or_else(|err| raise_row_error(err, geometry_context(geog.datum(), dst_srid))),
+            &geog,
+        )
+    }
+
+    // GeoJSON positions are lng/lat, so only the geodetic systems apply.
+    // The text is rewritten in place and the SQL wrappers cast it back to
+    // json or jsonb.
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_eviltransform_geojson",
+        schema = "eviltransform_internal"
+    )]
+    fn st_eviltransform_geojson_internal(geojson: &str, from_proj: &str, to_proj: &str) -> String {
+        let srids = current_custom_srids();
+        let datum = |spec: &str, what: &str| {
+            let srid = parse_geodetic(&srids, spec, what);
+            srids.frame(srid).expect("geodetic SRIDs have a frame").0
+        };
+        let (src, dst) = (
+            datum(from_proj, "GeoJSON source"),
+            datum(to_proj, "GeoJSON target"),
+        );
+        if src == dst {
+            // Nothing to move, but reject what the transform would reject.
+            validate_geojson(geojson).unwrap_or_else(|err| raise_err(err));
+            return geojson.to_string();
+        }
+        let kind = srid::datum_kind(src, dst, false).unwrap_or_else(|| {
+            raise(
+                SqlState::InvalidParameterValue,
+                format!("no transform from {from_proj:?} to {to_proj:?}"),
+            )
+        });
+        transform_geojson(geojson, Transform::new(kind, current_offset_zone()))
+            .unwrap_or_else(|err| raise_err(err))
+    }
+
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__parse_custom_srid",
+        schema = "eviltransform_internal"
+    )]
+    fn parse_custom_srid(spec: &str) -> Option<i32> {
+        current_custom_srids().parse(spec)
+    }
+
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__is_custom_srid",
+        schema = "eviltransform_internal"
+    )]
+    fn is_custom_srid(srid: i32) -> bool {
+        current_custom_srids().is_custom(srid)
+    }
+
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__custom_srid_definitions",
+        schema = "eviltransform_internal"
+    )]
+    fn custom_srid_definitions() -> TableIterator<
+        'static,
+        (
+            name!(srid, i32),
+            name!(srtext, String),
+            name!(proj4text, String),
+        ),
+    > {
+        let refs = current_custom_srids().spatial_refs();
+        TableIterator::new(refs.into_iter().map(|r| (r.srid, r.srtext, r.proj4text)))
+    }
+
+    // postgis_raster is optional, so its type is looked up on first use
+    // rather than with the other PostGIS objects.
+    fn raster_type() -> pg_sys::Oid {
+        static RASTER: OnceLock<pg_sys::Oid> = OnceLock::new();
+        *RASTER.get_or_init(|| lookup_regtype_oid("raster"))
+    }
+
+    // Warps the in-db bands onto a grid of the same size; output pixels are
+    // mapped back through the (iterative) inverse transform and resampled.
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_eviltransform_raster",
+        schema = "eviltransform_internal"
+    )]
+    fn st_eviltransform_raster_internal(
+        rast: AnyElement,
+        dst_srid: i32,
+        resampling: &str,
+    ) -> AnyElement {
+        if rast.oid() != raster_type() {
+            raise(SqlState::DatatypeMismatch, "expected a raster value");
+        }
+        let resampling: Resampling = resampling.parse().unwrap_or_else(|err| raise_err(err));
+        let src_srid = match with_detoasted(rast.datum(), crate::raster::read_header) {
+            Ok(header) if header.srid == 0 => SRID_WGS84,
+            Ok(header) => header.srid,
+            Err(err) => raise(err.sqlstate(), format!("failed to read raster: {err}")),
+        };
+        let warped = with_detoasted(rast.datum(), |buf| {
+            if src_srid == dst_srid {
+                return Ok(buf.to_vec());
+            }
+            let transform = single_pass(src_srid, dst_srid, false, current_offset_zone())
+                .unwrap_or_else(|| {
+                    raise(
+                        SqlState::InvalidParameterValue,
+                        format!(
+                            "raster transform needs a custom SRID, 4326, 3857, 4490 or WGS 84 UTM on both sides, got {src_srid} to {dst_srid}"
+                        ),
+                    )
+                });
+            crate::raster::warp_raster(
+                buf,
+                transform,
+                transform.inverse(),
+                dst_srid,
+                resampling,
+            )
+        })
+        .unwrap_or_else(|err| {
+            raise_report(
+                report(err.sqlstate(), format!("failed to transform raster: {err}"))
+                    .set_detail(format!("source SRID {src_srid}, target SRID {dst_srid}")),
+            )
+        });
+        geometry_datum(bytes_to_datum(&warped), &rast)
+    }
+
+    // Projected SRIDs are tested in lng/lat: the custom and standard frames
+    // are unprojected here, anything else goes through ST_Transform to 4326.
+    #[pg_extern(
+        stable,
+        strict,
+        parallel_safe,
+        name = "__st_isinchinaoffsetzone",
+        schema = "eviltransform_internal"
+    )]
+    fn st_isinchinaoffsetzone_internal(geom: AnyElement, offset_zone: &str) -> bool {
+        if geom.oid() != postgis_fns().geometry_type {
+            raise(SqlState::DatatypeMismatch, "expected a geometry value");
+        }
+        let zone = parse_offset_zone(offset_zone);
+        let src_srid = gserialized_get_srid(geom.datum())
+            .unwrap_or_else(|err| raise_row_error(err, geometry_context(geom.datum(), 0)));
+        let (datum, frame) = match src_srid {
+            0 => (geom.datum(), (Datum::Wgs84, Projection::LngLat)),
+            srid => match current_custom_srids().frame(srid) {
+                Some(frame) => (geom.datum(), frame),
+                None => (
+                    unsafe {
+                        call2(
+                            postgis_fns().st_transform_geom_int,
+                            geom.datum(),
+                            SRID_WGS84.into(),
+                        )
+                    },
+                    (Datum::Wgs84, Projection::LngLat),
+                ),
+            },
+        };
+        let mut any = false;
+        let mut all = true;
+        with_detoasted(datum, |buf| {
+            crate::gserialized::for_each_point(buf, |x, y| {
+                let (lat, lng) = match frame {
+                    (Datum::Bd09Mc, _) => crate::coord::bdmc2bd(y, x),
+                    (_, projection) => projection.unproject(y, x),
+                };
+                any = true;
+                all &= zone.contains(lat, lng);
+            })
+        })
+        .unwrap_or_else(|err| {
+            raise(
+                err.sqlstate(),
+                format!("failed to read GSERIALIZED geometry: {err}"),
+            )
+        });
+        any && all
+    }
+
+    // One row, like ST_IsValidDetail: byte_offset is where the error was
+    // found.
+    #[pg_extern(immutable, strict, parallel_safe, name = "st_evilvalidateewkb")]
+    fn st_evilvalidateewkb(
+        ewkb: &[u8],
+    ) -> TableIterator<
+        'static,
+        (
+            name!(valid, bool),
+            name!(reason, Option<String>),
+            name!(byte_offset, Option<i32>),
+        ),
+    > {
+        let row = match crate::ewkb::validate_ewkb(ewkb) {
+            Ok(()) => (true, None, None),
+            Err(err) => (
+                false,
+                Some(err.to_string()),
+                i32::try_from(err.offset()).ok(),
+            ),
+        };
+        TableIterator::once(row)
+    }
+
+    fn values_to_jenks<I>(values: I, breaks: i32, invert: bool) -> Option<Vec<f64>>
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        match jenks::breaks_from_values(values, breaks, invert) {
+            Ok(result) => result,
+            Err(err) => raise_err(err),
+        }
+    }
+
+    fn anynumeric_to_f64(value: AnyNumeric) -> f64 {
+        match f64::try_from(value) {
+            Ok(value) if value.is_finite() => value,
+            Ok(value) => raise_err(jenks::JenksError::NonFiniteValue(value)),
+            Err(err) => raise(
+                SqlState::InvalidParameterValue,
+                format!("numeric value cannot be converted to finite f64: {err}"),
+            ),
+        }
+    }
+
+    fn collect_array_values<T, F>(values: Vec<Option<T>>, convert: F) -> Vec<f64>
+    where
+        F: Fn(T) -> f64,
+    {
+        values
+            .into_iter()
+            .filter_map(|value| value.map(&convert))
+            .collect()
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_float8_array(values: Vec<Option<f64>>, breaks: i32) -> Option<Vec<f64>> {
+        st_jenksbins_float8_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_float8_array_invert(
+        values: Vec<Option<f64>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(collect_array_values(values, |value| value), breaks, invert)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_float4_array(values: Vec<Option<f32>>, breaks: i32) -> Option<Vec<f64>> {
+        st_jenksbins_float4_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_float4_array_invert(
+        values: Vec<Option<f32>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(
+            collect_array_values(values, |value| f64::from(value)),
+            breaks,
+            invert,
+        )
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int8_array(values: Vec<Option<i64>>, breaks: i32) -> Option<Vec<f64>> {
+        st_jenksbins_int8_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int8_array_invert(
+        values: Vec<Option<i64>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(
+            collect_array_values(values, |value| value as f64),
+            breaks,
+            invert,
+        )
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int4_array(values: Vec<Option<i32>>, breaks: i32) -> Option<Vec<f64>> {
+        st_jenksbins_int4_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int4_array_invert(
+        values: Vec<Option<i32>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(
+            collect_array_values(values, |value| f64::from(value)),
+            breaks,
+            invert,
+        )
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int2_array(values: Vec<Option<i16>>, breaks: i32) -> Option<Vec<f64>> {
+        st_jenksbins_int2_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_int2_array_invert(
+        values: Vec<Option<i16>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(
+            collect_array_values(values, |value| f64::from(value)),
+            breaks,
+            invert,
+        )
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_numeric_array(
+        values: Vec<Option<AnyNumeric>>,
+        breaks: i32,
+    ) -> Option<Vec<f64>> {
+        st_jenksbins_numeric_array_invert(values, breaks, false)
+    }
+
+    #[pg_extern(immutable, parallel_safe, name = "st_jenksbins")]
+    fn st_jenksbins_numeric_array_invert(
+        values: Vec<Option<AnyNumeric>>,
+        breaks: i32,
+        invert: bool,
+    ) -> Option<Vec<f64>> {
+        values_to_jenks(
+            collect_array_values(values, anynumeric_to_f64),
+            breaks,
+            invert,
+        )
+    }
+
+    #[derive(Clone, Default)]
+    struct JenksBinsState {
+        counts: JenksCounts,
+        breaks: i32,
+        invert: bool,
+        initialized: bool,
+    }
+
+    impl JenksBinsState {
+        fn add_value(&mut self, value: Option<f64>, breaks: i32, invert: bool) {
+            if breaks < 1 {
+                raise_err(jenks::JenksError::InvalidBreaks);
+            }
+            if self.initialized {
+                if self.breaks != breaks || self.invert != invert {
+                    raise(
+                        SqlState::InvalidParameterValue,
+                        "ST_JenksBins aggregate breaks and invert arguments must be constant",
+                    );
+                }
+            } else {
+                self.breaks = breaks;
+                self.invert = invert;
                 self.initialized = true;
             }
             if let Some(value) = value {
                 if let Err(err) = self.counts.push(value) {
-                    error!("{err}");
+                    raise_err(err);
                 }
             }
         }
@@ -404,7 +1457,7 @@ mod extension {
             }
             match jenks::breaks_from_counts(&self.counts, self.breaks, self.invert) {
                 Ok(result) => result,
-                Err(err) => error!("{err}"),
+                Err(err) => raise_err(err),
             }
         }
     }
@@ -527,28 +1580,10 @@ mod extension {
 
     extension_sql!(
         r#"
-        CREATE FUNCTION eviltransform_internal.__parse_custom_srid(spec text)
-        RETURNS integer
-        LANGUAGE SQL
-        IMMUTABLE STRICT PARALLEL SAFE
-        AS $$
-        SELECT CASE upper(trim(spec))
-          WHEN '990001' THEN 990001
-          WHEN 'EPSG:990001' THEN 990001
-          WHEN 'GCJ02' THEN 990001
-          WHEN 'GCJ-02' THEN 990001
-          WHEN '990002' THEN 990002
-          WHEN 'EPSG:990002' THEN 990002
-          WHEN 'BD09' THEN 990002
-          WHEN 'BD-09' THEN 990002
-          ELSE NULL
-        END;
-        $$;
-
         CREATE FUNCTION st_eviltransform(geom geometry, to_proj text)
         RETURNS geometry
         LANGUAGE SQL
-        IMMUTABLE STRICT PARALLEL SAFE
+        STABLE STRICT PARALLEL SAFE
         AS $$
         WITH params AS (
           SELECT
@@ -558,7 +1593,7 @@ mod extension {
         )
         SELECT CASE
           WHEN dst_custom IS NOT NULL THEN st_eviltransform(geom, dst_custom)
-          WHEN src_srid IN (990001, 990002)
+          WHEN eviltransform_internal.__is_custom_srid(src_srid)
             THEN ST_Transform(st_eviltransform(geom, 4326), to_proj)
           ELSE ST_Transform(geom, to_proj)
         END
@@ -568,7 +1603,7 @@ mod extension {
         CREATE FUNCTION st_eviltransform(geom geometry, from_proj text, to_srid integer)
         RETURNS geometry
         LANGUAGE SQL
-        IMMUTABLE STRICT PARALLEL SAFE
+        STABLE STRICT PARALLEL SAFE
         AS $$
         WITH params AS (
           SELECT
@@ -577,7 +1612,7 @@ mod extension {
             eviltransform_internal.__parse_custom_srid(from_proj) AS src_custom
         )
         SELECT CASE
-          WHEN src_custom IS NULL AND dst_srid NOT IN (990001, 990002)
+          WHEN src_custom IS NULL AND NOT eviltransform_internal.__is_custom_srid(dst_srid)
             THEN ST_Transform(geom, from_proj, dst_srid)
           WHEN src_custom IS NOT NULL
             THEN st_eviltransform(ST_SetSRID(geom, src_custom), dst_srid)
@@ -589,7 +1624,7 @@ mod extension {
         CREATE FUNCTION st_eviltransform(geom geometry, from_proj text, to_proj text)
         RETURNS geometry
         LANGUAGE SQL
-        IMMUTABLE STRICT PARALLEL SAFE
+        STABLE STRICT PARALLEL SAFE
         AS $$
         WITH params AS (
           SELECT
@@ -610,20 +1645,187 @@ mod extension {
         $$;
         "#,
         name = "st_eviltransform_sql",
-        requires = [st_eviltransform_integer, st_eviltransform_ewkb_internal]
+        requires = [
+            st_eviltransform_integer,
+            st_eviltransform_ewkb_internal,
+            parse_custom_srid,
+            is_custom_srid
+        ]
     );
 
-    #[cfg(any(test, feature = "pg_test"))]
-    #[pg_schema]
-    mod tests {
-        use pgrx::prelude::*;
-
+    extension_sql!(
+        r#"
+        CREATE FUNCTION st_eviltransform(geog geography, target text)
+        RETURNS geography
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_geography(geog, target);
+        $$;
+        "#,
+        name = "st_eviltransform_geography_sql",
+        requires = [st_eviltransform_geography_internal]
+    );
+
+    extension_sql!(
+        r#"
+        CREATE FUNCTION ST_IsInChinaOffsetZone(geom geometry, offset_zone text DEFAULT 'border')
+        RETURNS boolean
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_isinchinaoffsetzone(geom, offset_zone);
+        $$;
+        "#,
+        name = "st_isinchinaoffsetzone_sql",
+        requires = [st_isinchinaoffsetzone_internal]
+    );
+
+    extension_sql!(
+        r#"
+        CREATE FUNCTION ST_EvilTransformGeoJSON(geojson json, from_proj text, to_proj text)
+        RETURNS json
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_geojson(geojson::text, from_proj, to_proj)::json;
+        $$;
+
+        CREATE FUNCTION ST_EvilTransformGeoJSON(geojson jsonb, from_proj text, to_proj text)
+        RETURNS jsonb
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_geojson(geojson::text, from_proj, to_proj)::jsonb;
+        $$;
+        "#,
+        name = "st_eviltransform_geojson_sql",
+        requires = [st_eviltransform_geojson_internal]
+    );
+
+    // postgis_raster may be installed after this extension, so the raster
+    // overload is created by a function that can be called again later.
+    // Outside CREATE EXTENSION it adds the overload to the extension so that
+    // DROP EXTENSION removes it.
+    extension_sql!(
+        r#"
+        CREATE FUNCTION ST_EvilCreateRasterOverload()
+        RETURNS boolean
+        LANGUAGE plpgsql
+        VOLATILE
+        AS $fn$
+        DECLARE
+            ext_schema text;
+            overload regprocedure;
+        BEGIN
+            IF to_regtype('raster') IS NULL THEN
+                RETURN false;
+            END IF;
+            SELECT n.nspname INTO ext_schema
+            FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace
+            WHERE e.extname = 'pg_eviltransform';
+            EXECUTE format(
+                $sql$
+                CREATE OR REPLACE FUNCTION %1$I.st_eviltransform(rast %2$s, target_srid integer, resampling text DEFAULT 'nearest')
+                RETURNS %2$s
+                LANGUAGE SQL
+                STABLE STRICT PARALLEL SAFE
+                AS $$
+                SELECT eviltransform_internal.__st_eviltransform_raster(rast, target_srid, resampling);
+                $$
+                $sql$,
+                ext_schema, to_regtype('raster'));
+            overload := format('%I.st_eviltransform(%s, integer, text)', ext_schema, to_regtype('raster'))::regprocedure;
+            IF NOT EXISTS (
+                SELECT FROM pg_depend
+                WHERE classid = 'pg_proc'::regclass AND objid = overload AND deptype = 'e'
+            ) THEN
+                EXECUTE format('ALTER EXTENSION pg_eviltransform ADD FUNCTION %s', overload);
+            END IF;
+            RETURN true;
+        END
+        $fn$;
+
+        SELECT ST_EvilCreateRasterOverload();
+        "#,
+        name = "st_eviltransform_raster_sql",
+        requires = [st_eviltransform_raster_internal]
+    );
+
+    extension_sql!(
+        r#"
+        CREATE FUNCTION ST_EvilTransformSafe(geom geometry, dst_srid integer, on_error text DEFAULT 'null')
+        RETURNS geometry
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_safe(geom, dst_srid, on_error);
+        $$;
+
+        CREATE FUNCTION ST_EvilTransformSafe(ewkb bytea, dst_srid integer, on_error text DEFAULT 'null')
+        RETURNS bytea
+        LANGUAGE SQL
+        STABLE STRICT PARALLEL SAFE
+        AS $$
+        SELECT eviltransform_internal.__st_eviltransform_safe_ewkb(ewkb, dst_srid, on_error);
+        $$;
+        "#,
+        name = "st_eviltransform_safe_sql",
+        requires = [
+            st_eviltransform_safe_internal,
+            st_eviltransform_safe_ewkb_internal
+        ]
+    );
+
+    // spatial_ref_sys is a PostGIS configuration table, so pg_dump dumps the
+    // EVILTRANSFORM rows and a restore copies them back after CREATE EXTENSION.
+    // Installing therefore leaves the table alone; the rows are written only by
+    // an explicit ST_EvilRegisterSRIDs() call.
+    extension_sql!(
+        r#"
+        CREATE FUNCTION ST_EvilRegisterSRIDs()
+        RETURNS integer
+        LANGUAGE SQL
+        VOLATILE
+        AS $$
+        DELETE FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM';
+        WITH inserted AS (
+          INSERT INTO spatial_ref_sys (srid, auth_name, auth_srid, srtext, proj4text)
+          SELECT srid, 'EVILTRANSFORM', srid, srtext, proj4text
+          FROM eviltransform_internal.__custom_srid_definitions()
+          ON CONFLICT (srid) DO NOTHING
+          RETURNING 1
+        )
+        SELECT count(*)::integer FROM inserted;
+        $$;
+
+        CREATE FUNCTION ST_EvilUnregisterSRIDs()
+        RETURNS integer
+        LANGUAGE SQL
+        VOLATILE
+        AS $$
+        WITH deleted AS (
+          DELETE FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM'
+          RETURNING 1
+        )
+        SELECT count(*)::integer FROM deleted;
+        $$;
+        "#,
+        name = "spatial_ref_sys_sql",
+        requires = [custom_srid_definitions]
+    );
+
+    #[cfg(any(test, feature = "pg_test"))]
+    #[pg_schema]
+    mod tests {
+        use pgrx::prelude::*;
+
         #[pg_test]
         fn test_integer_overload_to_gcj02() {
             let got = Spi::get_one::<String>(&format!(
                 "SELECT ST_AsText(ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, {}), {}))",
                 super::SRID_WGS84,
-                super::SRID_GCJ02
+                990001
             ))
             .expect("SPI failed")
             .expect("no row returned");
@@ -652,7 +1854,7 @@ mod extension {
             .expect("SPI failed")
             .expect("no row returned");
 
-            assert_eq!(got, super::SRID_GCJ02);
+            assert_eq!(got, 990001);
         }
 
         #[pg_test]
@@ -663,7 +1865,153 @@ mod extension {
             .expect("SPI failed")
             .expect("no row returned");
 
-            assert_eq!(got, super::SRID_BD09);
+            assert_eq!(got, 990002);
+        }
+
+        #[pg_test]
+        fn test_bd09mc_reference_point() {
+            let got = Spi::get_one::<bool>(
+                "WITH t AS (
+                   SELECT ST_EvilTransform('SRID=990002;POINT(116.404 39.915)'::geometry, 990003) AS mc
+                 )
+                 SELECT ST_SRID(mc) = 990003
+                   AND abs(ST_X(mc) - 12958175.0) < 0.01
+                   AND abs(ST_Y(mc) - 4825923.77) < 0.01
+                   AND ST_SRID(ST_EvilTransform(mc, 'BD09')) = 990002
+                   AND ST_Distance(ST_EvilTransform(mc, 990002), 'SRID=990002;POINT(116.404 39.915)'::geometry) < 1e-6
+                   AND ST_Distance(
+                     ST_EvilTransform(ST_EvilTransform('SRID=4326;POINT(116.404 39.915)'::geometry, 'BD09MC'), 4326, true),
+                     'SRID=4326;POINT(116.404 39.915)'::geometry
+                   ) < 1e-6
+                   AND ST_SRID(ST_EvilTransform(mc, 3857)) = 3857
+                 FROM t",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_web_mercator_custom_srids() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT 'SRID=4326;POINT(120 30)'::geometry AS wgs,
+                          ST_Transform('SRID=4326;POINT(120 30)'::geometry, 3857) AS merc
+                 ), t AS (
+                   SELECT wgs, merc,
+                          ST_EvilTransform(wgs, 990004) AS gcj_merc,
+                          ST_EvilTransform(wgs, 990005) AS bd_merc
+                   FROM src
+                 )
+                 SELECT ST_SRID(gcj_merc) = 990004
+                   AND ST_Distance(
+                     ST_SetSRID(gcj_merc, 3857),
+                     ST_Transform(ST_SetSRID(ST_EvilTransform(wgs, 990001), 4326), 3857)
+                   ) < 1e-6
+                   AND ST_Distance(
+                     ST_SetSRID(bd_merc, 3857),
+                     ST_Transform(ST_SetSRID(ST_EvilTransform(wgs, 990002), 4326), 3857)
+                   ) < 1e-6
+                   AND ST_Distance(ST_EvilTransform(merc, 990004), gcj_merc) < 1e-6
+                   AND ST_Distance(ST_EvilTransform(gcj_merc, 990005), bd_merc) < 1e-3
+                   AND ST_Distance(ST_EvilTransform(gcj_merc, 3857, true), merc) < 1e-3
+                   AND ST_Distance(ST_EvilTransform(gcj_merc, 4326, true), wgs) < 1e-9
+                   AND ST_Distance(ST_EvilTransform(gcj_merc, 990001), ST_EvilTransform(wgs, 990001)) < 1e-9
+                   AND ST_SRID(ST_EvilTransform(wgs, 'GCJ02/3857')) = 990004
+                   AND ST_SRID(ST_EvilTransform(wgs, 'bd09_webmercator')) = 990005
+                 FROM t",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_fused_projected_targets_match_st_transform() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT ST_EvilTransform(ST_SetSRID('LINESTRING(116.404 39.915, 121.47 31.23)'::geometry, 4326), 990002) AS g
+                 )
+                 SELECT bool_and(ST_HausdorffDistance(
+                          ST_EvilTransform(g, dst),
+                          ST_Transform(ST_EvilTransform(g, 4326), dst)
+                        ) < tolerance
+                        AND ST_SRID(ST_EvilTransform(g, dst)) = dst)
+                 FROM src,
+                      (VALUES (3857, 1e-6), (4490, 1e-9), (32650, 1e-3), (32651, 1e-3)) AS t(dst, tolerance)",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[cfg(feature = "proj")]
+        #[pg_test]
+        fn test_proj_fused_path_matches_st_transform() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT ST_EvilTransform(ST_SetSRID('LINESTRING(113.9 22.5, 114.1 30.6)'::geometry, 4326), 990002) AS g
+                 )
+                 SELECT ST_HausdorffDistance(
+                          ST_EvilTransform(g, 4547),
+                          ST_Transform(ST_EvilTransform(g, 4326), 4547)
+                        ) < 1e-6
+                    AND ST_HausdorffDistance(
+                          ST_EvilTransform(ST_EvilTransform(g, 4547), 990002),
+                          ST_EvilTransform(ST_Transform(ST_EvilTransform(g, 4547), 4326), 990002)
+                        ) < 1e-9
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[cfg(feature = "proj")]
+        #[pg_test]
+        fn test_proj_fused_gcj02_matches_step_by_step() {
+            // 990001 -> 4547 takes the fused path; the chain below goes
+            // through WGS84 and ST_Transform instead.
+            let (distance, srid) = Spi::get_two::<f64, i32>(
+                "WITH src AS (
+                   SELECT ST_EvilTransform(ST_SetSRID('LINESTRING(113.9 22.5, 114.1 30.6)'::geometry, 4326), 990001) AS g
+                 )
+                 SELECT ST_HausdorffDistance(
+                          ST_EvilTransform(g, 4547),
+                          ST_Transform(ST_EvilTransform(g, 4326), 4547)
+                        ),
+                        ST_SRID(ST_EvilTransform(g, 4547))
+                 FROM src",
+            )
+            .expect("SPI failed");
+
+            assert!(distance.expect("no row returned") < 1e-6, "{distance:?}");
+            assert_eq!(srid, Some(4547));
+        }
+
+        #[pg_test]
+        fn test_cgcs2000_and_vendor_aliases() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (SELECT 'POINT(120 30)'::geometry AS g)
+                 SELECT ST_SRID(ST_EvilTransform(ST_SetSRID(g, 4326), 'CGCS2000')) = 4490
+                   AND ST_SRID(ST_EvilTransform(g, 'CGCS2000', 990001)) = 990001
+                   AND ST_Distance(
+                     ST_EvilTransform(g, 'CGCS2000', 'GCJ02'),
+                     ST_EvilTransform(ST_SetSRID(g, 4326), 990001)
+                   ) < 1e-6
+                   AND ST_SRID(ST_EvilTransform(ST_SetSRID(g, 4326), 'Tencent')) = 990001
+                   AND ST_SRID(ST_EvilTransform(ST_SetSRID(g, 4326), 'AMAP')) = 990001
+                   AND ST_SRID(ST_EvilTransform(ST_SetSRID(g, 4326), 'baidu')) = 990002
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
         }
 
         #[pg_test]
@@ -676,7 +2024,7 @@ mod extension {
 
             let _ = Spi::get_one::<String>(&format!(
                 "SELECT encode(ST_AsEWKB(ST_EvilTransform(g, {})), 'hex') FROM t",
-                super::SRID_BD09
+                990002
             ))
             .expect("transform query failed");
 
@@ -698,6 +2046,747 @@ mod extension {
             assert!(got);
         }
 
+        #[pg_test]
+        fn test_gserialized_and_ewkb_paths_agree() {
+            let got = Spi::get_one::<bool>(
+                "WITH grid AS (
+                   SELECT ST_MakePoint(70.0 + i * 4.9, -1.0 + j * 4.1) AS g
+                   FROM generate_series(0, 14) AS i, generate_series(0, 14) AS j
+                 ),
+                 shapes AS (
+                   SELECT g FROM grid
+                   UNION ALL
+                   SELECT ST_MakeLine(array_agg(g)) FROM grid
+                 ),
+                 modes(mode, src, dst, exact) AS (
+                   VALUES
+                     (1, 4326, 990001, false),
+                     (2, 990001, 4326, false),
+                     (3, 4326, 990002, false),
+                     (4, 990002, 4326, false),
+                     (5, 990001, 990002, false),
+                     (6, 990002, 990001, false),
+                     (7, 990001, 4326, true),
+                     (8, 990002, 4326, true),
+                     (9, 990002, 990001, true)
+                 )
+                 SELECT bool_and(
+                   ST_AsEWKB(ST_EvilTransform(ST_SetSRID(g, src), dst, exact))
+                   = ST_AsEWKB(ST_SetSRID(ST_GeomFromEWKB(
+                       eviltransform_internal.__st_eviltransform_ewkb(ST_AsEWKB(ST_SetSRID(g, src)), mode)
+                     ), dst))
+                 )
+                 FROM shapes, modes",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_cached_bbox_matches_recomputed_envelope() {
+            let got = Spi::get_one::<bool>(
+                "WITH shapes(g) AS (
+                   VALUES
+                     ('LINESTRING(116.1 39.7, 116.9 40.2, 117.4 39.1)'::geometry),
+                     ('POLYGON((116 39, 117 39, 117 40, 116 40, 116 39), (116.2 39.2, 116.4 39.2, 116.4 39.4, 116.2 39.2))'::geometry),
+                     ('MULTIPOLYGON(((116 39, 117 39, 117 40, 116 39)), ((120 30, 121 30, 121 31, 120 30)))'::geometry),
+                     ('MULTIPOINT(116 39, 121 31)'::geometry),
+                     ('LINESTRING Z (116 39 5, 117 40 -3, 118 39.5 9)'::geometry),
+                     ('LINESTRING M (116 39 0, 117 40 1, 118 39.5 2)'::geometry),
+                     ('CIRCULARSTRING(116 39, 116.5 39.5, 117 39)'::geometry),
+                     ('COMPOUNDCURVE(CIRCULARSTRING(116 39, 116.5 39.5, 117 39), (117 39, 118 38))'::geometry),
+                     ('CURVEPOLYGON(CIRCULARSTRING(116 39, 117 40, 116 39))'::geometry),
+                     ('MULTICURVE((116 39, 117 40), CIRCULARSTRING(118 39, 118.5 39.5, 119 39))'::geometry),
+                     ('MULTISURFACE(((116 39, 117 39, 117 40, 116 39)), CURVEPOLYGON(CIRCULARSTRING(120 30, 121 31, 120 30)))'::geometry),
+                     ('POLYHEDRALSURFACE Z (((116 39 0, 117 39 0, 117 40 0, 116 39 0)), ((116 39 0, 117 40 0, 116 40 1, 116 39 0)))'::geometry),
+                     ('TIN(((116 39, 117 39, 117 40, 116 39)), ((116 39, 117 40, 116 40, 116 39)))'::geometry),
+                     ('TRIANGLE((116 39, 117 39, 117 40, 116 39))'::geometry),
+                     ('GEOMETRYCOLLECTION(POINT(116 39), LINESTRING(120 30, 121 31))'::geometry)
+                 ),
+                 transformed AS (
+                   SELECT ST_EvilTransform(ST_SetSRID(g, src), dst) AS t
+                   FROM shapes,
+                        (VALUES (4326, 990001), (990001, 990002), (990002, 4326)) AS pairs(src, dst)
+                 ),
+                 boxes AS (
+                   SELECT postgis_hasbbox(t) AS has_cached,
+                          postgis_getbbox(t) AS cached,
+                          postgis_getbbox(ST_GeomFromEWKB(ST_AsEWKB(t))) AS recomputed
+                   FROM transformed
+                 )
+                 -- postgis_getbbox reads the box stored in the value rather than
+                 -- recomputing it; re-parsing the EWKB stores a fresh one.
+                 SELECT bool_and(has_cached)
+                   AND bool_and(
+                     ST_XMin(cached) = ST_XMin(recomputed)
+                     AND ST_YMin(cached) = ST_YMin(recomputed)
+                     AND ST_XMax(cached) = ST_XMax(recomputed)
+                     AND ST_YMax(cached) = ST_YMax(recomputed)
+                   )
+                 FROM boxes",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_bbox_operator_after_transform() {
+            let got = Spi::get_one::<bool>(
+                "WITH t AS (
+                   SELECT ST_EvilTransform(
+                     ST_SetSRID('LINESTRING(116 39, 117 40, 118 39)'::geometry, 4326),
+                     990002
+                   ) AS g
+                 )
+                 SELECT g && ST_SetSRID(ST_MakeEnvelope(118.005, 39.0, 118.02, 39.01), 990002)
+                   AND NOT (g && ST_SetSRID(ST_MakeEnvelope(117.0, 38.99, 117.1, 39.005), 990002))
+                 FROM t",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_exact_inverse_round_trip() {
+            let got = Spi::get_one::<f64>(
+                "WITH src AS (
+                   SELECT ST_SetSRID(ST_MakePoint(116.404, 39.915), 4326) AS g
+                 )
+                 SELECT ST_Distance(
+                   g,
+                   ST_EvilTransform(ST_EvilTransform(ST_EvilTransform(g, 990002), 990001, true), 4326, true)
+                 )
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got < 1e-9, "round-trip error {got}");
+        }
+
+        #[pg_test]
+        fn test_exact_flag_false_matches_default() {
+            let got = Spi::get_one::<bool>(
+                "SELECT ST_AsEWKB(ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 990001), 4326, false)) = ST_AsEWKB(ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 990001), 4326))",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_exact_tolerance_argument() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT ST_EvilTransform(ST_SetSRID(ST_MakePoint(116.404, 39.915), 4326), 990001) AS g
+                 )
+                 SELECT ST_AsEWKB(ST_EvilTransform(g, 4326, true, 1e-12)) = ST_AsEWKB(ST_EvilTransform(g, 4326, true))
+                   AND ST_AsEWKB(ST_EvilTransform(g, 4326, true, 1e-3)) = ST_AsEWKB(ST_EvilTransform(g, 4326))
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(error = "tolerance must be a positive number, got 0")]
+        fn test_exact_tolerance_rejects_zero() {
+            Spi::run(
+                "SELECT ST_EvilTransform('SRID=990001;POINT(120 30)'::geometry, 4326, true, 0::float8)",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_offset_zone_argument() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT ST_SetSRID('POINT(106.92 47.92)'::geometry, 4326) AS ub,
+                          ST_SetSRID('POINT(114.16 22.28)'::geometry, 4326) AS hk
+                 )
+                 SELECT NOT ST_Equals(ST_SetSRID(ST_EvilTransform(ub, 990001), 4326), ub)
+                   AND ST_Equals(ST_SetSRID(ST_EvilTransform(ub, 990001, 'border'), 4326), ub)
+                   AND NOT ST_Equals(ST_SetSRID(ST_EvilTransform(hk, 990001, 'border'), 4326), hk)
+                   AND ST_Equals(ST_SetSRID(ST_EvilTransform(hk, 990001, 'skip_hk_mo_tw'), 4326), hk)
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_offset_zone_guc() {
+            Spi::run("SET eviltransform.offset_zone = 'border'").expect("SPI failed");
+            let got = Spi::get_one::<bool>(
+                "SELECT ST_AsEWKB(ST_EvilTransform(ST_SetSRID('POINT(106.92 47.92)'::geometry, 4326), 990001))
+                      = ST_AsEWKB(ST_SetSRID('POINT(106.92 47.92)'::geometry, 990001))",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            Spi::run("RESET eviltransform.offset_zone").expect("SPI failed");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_spatial_ref_sys_rows() {
+            let registered = Spi::get_one::<i32>("SELECT ST_EvilRegisterSRIDs()")
+                .expect("SPI failed")
+                .expect("no row returned");
+            let described = Spi::get_one::<bool>(
+                "SELECT count(*) = 5 AND bool_and(auth_srid = srid AND srtext LIKE '%EVILTRANSFORM%')
+                 FROM spatial_ref_sys
+                 WHERE auth_name = 'EVILTRANSFORM' AND srid BETWEEN 990001 AND 990005",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            let rewritten = Spi::get_one::<i32>("SELECT ST_EvilRegisterSRIDs()")
+                .expect("SPI failed")
+                .expect("no row returned");
+            let removed = Spi::get_one::<i32>("SELECT ST_EvilUnregisterSRIDs()")
+                .expect("SPI failed")
+                .expect("no row returned");
+            let remaining = Spi::get_one::<i64>(
+                "SELECT count(*) FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(described);
+            assert_eq!((registered, rewritten, removed, remaining), (5, 5, 5, 0));
+        }
+
+        #[pg_test]
+        fn test_install_leaves_spatial_ref_sys_alone() {
+            // A restore copies dumped EVILTRANSFORM rows back after CREATE
+            // EXTENSION, which must not have written them already.
+            let rows = Spi::get_one::<i64>(
+                "SELECT count(*) FROM spatial_ref_sys WHERE auth_name = 'EVILTRANSFORM'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            let triggers = Spi::get_one::<i64>(
+                "SELECT count(*) FROM pg_trigger
+                 WHERE tgrelid = 'spatial_ref_sys'::regclass
+                   AND tgfoid::regproc::text LIKE '%eviltransform%'",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert_eq!((rows, triggers), (0, 0));
+        }
+
+        #[pg_test]
+        fn test_custom_srid_gucs() {
+            Spi::run("SET eviltransform.gcj02_srid = 991001").expect("SPI failed");
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (SELECT ST_SetSRID('POINT(120 30)'::geometry, 4326) AS g)
+                 SELECT ST_SRID(ST_EvilTransform(g, 991001)) = 991001
+                   AND ST_SRID(ST_EvilTransform(g, 'GCJ02')) = 991001
+                   AND eviltransform_internal.__is_custom_srid(991001)
+                   AND NOT eviltransform_internal.__is_custom_srid(990001)
+                   AND ST_Distance(
+                     ST_EvilTransform(ST_EvilTransform(g, 991001), 990002),
+                     ST_EvilTransform(g, 990002)
+                   ) < 1e-9
+                   AND eviltransform_internal.__parse_custom_srid('990001') IS NULL
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+            Spi::run("RESET eviltransform.gcj02_srid").expect("SPI failed");
+
+            assert!(got);
+        }
+
+        #[pg_test(error = "eviltransform.gcj02_srid and eviltransform.bd09_srid are both 990001")]
+        fn test_custom_srid_gucs_reject_collisions() {
+            Spi::run("SET eviltransform.bd09_srid = 990001").expect("SPI failed");
+            Spi::run(
+                "SELECT ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 4326), 990001)",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test(
+            error = "unknown offset zone \"nowhere\", expected one of rectangle, border, never_skip, skip_hk_mo_tw"
+        )]
+        fn test_offset_zone_rejects_unknown_policy() {
+            Spi::run(
+                "SELECT ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 4326), 990001, 'nowhere')",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_z_and_m_modes() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT 'SRID=4326;POINT Z (120 30 7)'::geometry AS pz,
+                          'SRID=4326;POINT M (120 30 42)'::geometry AS pm,
+                          'SRID=4326;POINT ZM (120 30 7 42)'::geometry AS pzm,
+                          'SRID=4326;LINESTRING(120 30, 121 30, 121 31)'::geometry AS line
+                 )
+                 SELECT ST_Z(ST_EvilTransform(pz, 990001)) = 7
+                   AND ST_Z(ST_EvilTransform(pz, 990001, 'offset:2.5', 'passthrough')) = 9.5
+                   AND ST_NDims(ST_EvilTransform(pz, 990001, 'drop', 'passthrough')) = 2
+                   AND ST_M(ST_EvilTransform(pm, 990001)) = 42
+                   AND NOT ST_HasM(ST_EvilTransform(pm, 990001, 'passthrough', 'drop'))
+                   AND ST_M(ST_EvilTransform(pm, 990001, 'passthrough', 'cumulative_length')) = 0
+                   AND ST_Zmflag(ST_EvilTransform(pzm, 990001, 'drop', 'drop')) = 0
+                   AND ST_Z(ST_EvilTransform(pzm, 990001, 'offset:-7', 'passthrough')) = 0
+                   AND abs(
+                     ST_M(ST_EndPoint(ST_EvilTransform(line, 990001, 'passthrough', 'cumulative_length')))
+                     - ST_Length(ST_EvilTransform(line, 990001))
+                   ) < 1e-9
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_curve_modes() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT 'SRID=4326;CIRCULARSTRING(120.5 30, 120.3 30.4, 119.5 30)'::geometry AS arc,
+                          ST_EvilTransform('SRID=4326;POINT(120 30.5)'::geometry, 990001) AS mid
+                 )
+                 SELECT ST_AsEWKB(ST_EvilTransform(arc, 990001, 'passthrough', 'passthrough', 'control_points'))
+                        = ST_AsEWKB(ST_EvilTransform(arc, 990001))
+                   AND ST_GeometryType(ST_EvilTransform(arc, 990001, 'passthrough', 'passthrough', 'refit')) = 'ST_CircularString'
+                   AND ST_Distance(
+                     ST_PointN(ST_EvilTransform(arc, 990001, 'passthrough', 'passthrough', 'refit'), 2),
+                     mid
+                   ) < 1e-9
+                   AND ST_GeometryType(ST_EvilTransform(arc, 990001, 'passthrough', 'passthrough', 'densify:1e-4')) = 'ST_LineString'
+                   AND ST_NPoints(ST_EvilTransform(arc, 990001, 'passthrough', 'passthrough', 'densify:1e-4')) > 20
+                   AND ST_NDims(ST_EvilTransform(ST_Force3D(arc), 990001, 'drop', 'passthrough', 'densify:1e-4')) = 2
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(
+            error = "unknown curve mode \"arcs\", expected control_points, refit or densify:<tolerance>"
+        )]
+        fn test_curve_mode_rejects_unknown() {
+            Spi::run(
+                "SELECT ST_EvilTransform('SRID=4326;CIRCULARSTRING(120.5 30, 120.3 30.4, 119.5 30)'::geometry, 990001, 'passthrough', 'passthrough', 'arcs')",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_max_segment_error() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT 'SRID=4326;LINESTRING(100 20, 130 45)'::geometry AS line,
+                          'SRID=4326;LINESTRING(-120 30, -100 45)'::geometry AS far,
+                          'SRID=4326;POLYGON((110 25, 120 25, 120 35, 110 35, 110 25))'::geometry AS square
+                 )
+                 SELECT ST_NPoints(ST_EvilTransform(line, 990001, 1e-6)) > 100
+                   AND ST_SRID(ST_EvilTransform(line, 990001, 1e-6)) = 990001
+                   AND ST_Equals(
+                     ST_EndPoint(ST_EvilTransform(line, 990001, 1e-6)),
+                     ST_EndPoint(ST_EvilTransform(line, 990001))
+                   )
+                   AND ST_NPoints(ST_EvilTransform(far, 990001, 1e-6)) = 2
+                   AND ST_IsValid(ST_EvilTransform(square, 990001, 1e-6))
+                   AND ST_NPoints(ST_EvilTransform(ST_SetSRID(square, 990001), 4326, 1e-6)) > 5
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(
+            error = "failed to transform GSERIALIZED geometry: segmentizing a point array would exceed 4194304 points"
+        )]
+        fn test_max_segment_error_point_limit() {
+            Spi::run(
+                "SELECT ST_EvilTransform('SRID=4326;LINESTRING(100 20, 130 45)'::geometry, 990001, 1e-300::float8)",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test(error = "max_segment_error must be a positive number, got 0")]
+        fn test_max_segment_error_rejects_zero() {
+            Spi::run(
+                "SELECT ST_EvilTransform('SRID=4326;LINESTRING(100 20, 130 45)'::geometry, 990001, 0::float8)",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_geography_overload() {
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT 'SRID=4326;POINT(120 30)'::geography AS wgs,
+                          ST_EvilTransform('SRID=4326;POINT(120 30)'::geography, 'gcj02') AS gcj
+                 )
+                 SELECT pg_typeof(gcj) = 'geography'::regtype
+                   AND ST_SRID(gcj) = 990001
+                   AND ST_AsText(gcj) LIKE 'POINT(120.00466044559%'
+                   AND ST_SRID(ST_EvilTransform(gcj, 'BD09')) = 990002
+                   AND ST_SRID(ST_EvilTransform(gcj, 'EPSG:4326')) = 4326
+                   AND ST_Distance(ST_EvilTransform(gcj, 'wgs84'), wgs) < 5
+                   AND ST_AsText(ST_EvilTransform(wgs, 'WGS84')) = 'POINT(120 30)'
+                 FROM src",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(
+            error = "geography target must be a geodetic system (WGS84, GCJ02 or BD09), got \"EPSG:3857\""
+        )]
+        fn test_geography_rejects_projected_target() {
+            Spi::run("SELECT ST_EvilTransform('SRID=4326;POINT(120 30)'::geography, 'EPSG:3857')")
+                .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_geojson_overloads() {
+            let check = |condition: &str| {
+                Spi::get_one::<bool>(&format!(
+                    r#"WITH src AS (
+                         SELECT '{{"type": "Feature", "properties": {{"name": "a"}},
+                                  "geometry": {{"type": "Point", "coordinates": [120.000000000000, 30.000000000000]}}}}'::json AS doc
+                       ), out AS (
+                         SELECT ST_EvilTransformGeoJSON(doc, 'WGS84', 'GCJ02') AS gcj,
+                                ST_EvilTransformGeoJSON(doc::jsonb, 'EPSG:4326', 'BD09') AS bd
+                         FROM src
+                       )
+                       SELECT {condition} FROM out"#
+                ))
+                .expect("SPI failed")
+                .expect("no row returned")
+            };
+
+            assert!(check(r#"gcj::text LIKE '%"properties": {"name": "a"}%'"#));
+            assert!(check(
+                "ST_Distance(
+                   ST_SetSRID(ST_GeomFromGeoJSON(gcj -> 'geometry'), 990001),
+                   ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 4326), 990001)
+                 ) < 1e-9"
+            ));
+            assert!(check(
+                "ST_Distance(
+                   ST_SetSRID(ST_GeomFromGeoJSON(bd -> 'geometry'), 990002),
+                   ST_EvilTransform(ST_SetSRID('POINT(120 30)'::geometry, 4326), 990002)
+                 ) < 1e-9"
+            ));
+            assert!(check(r#"bd -> 'properties' = '{"name": "a"}'::jsonb"#));
+            assert!(check(
+                "ST_EvilTransformGeoJSON(ST_EvilTransformGeoJSON(gcj, 'GCJ02', 'WGS84'), 'WGS84', 'WGS84')::text
+                   = ST_EvilTransformGeoJSON(gcj, 'GCJ02', 'WGS84')::text"
+            ));
+        }
+
+        #[pg_test(error = "GeoJSON position at offset 34 has fewer than 2 ordinates")]
+        fn test_geojson_rejects_short_position() {
+            Spi::run(
+                r#"SELECT ST_EvilTransformGeoJSON('{"type": "Point", "coordinates": [120]}'::json, 'WGS84', 'GCJ02')"#,
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test(error = "GeoJSON position at offset 34 has fewer than 2 ordinates")]
+        fn test_geojson_same_system_is_still_validated() {
+            Spi::run(
+                r#"SELECT ST_EvilTransformGeoJSON('{"type": "Point", "coordinates": [120]}'::json, 'GCJ02', '990001')"#,
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_raster_warp() {
+            Spi::run("CREATE EXTENSION IF NOT EXISTS postgis_raster").expect("SPI failed");
+            // The extension was created before postgis_raster here.
+            let created =
+                Spi::get_one::<bool>("SELECT ST_EvilCreateRasterOverload()").expect("SPI failed");
+            assert_eq!(created, Some(true));
+            let member = Spi::get_one::<bool>(
+                "SELECT EXISTS (
+                   SELECT FROM pg_depend d JOIN pg_extension e ON e.oid = d.refobjid
+                   WHERE e.extname = 'pg_eviltransform' AND d.deptype = 'e'
+                     AND d.objid = 'st_eviltransform(raster, integer, text)'::regprocedure
+                 )",
+            )
+            .expect("SPI failed");
+            assert_eq!(member, Some(true));
+            let got = Spi::get_one::<bool>(
+                "WITH src AS (
+                   SELECT ST_AddBand(
+                            ST_AddBand(ST_MakeEmptyRaster(8, 8, 120.0, 30.008, 0.001, -0.001, 0, 0, 4326),
+                                       '8BUI'::text, 7, 255),
+                            '32BF'::text, 1.5, NULL) AS rast
+                 ), warped AS (
+                   SELECT rast,
+                          ST_EvilTransform(rast, 990001, 'nearest') AS gcj
+                   FROM src
+                 )
+                 SELECT ST_SRID(gcj) = 990001
+                   AND ST_Width(gcj) = 8 AND ST_Height(gcj) = 8 AND ST_NumBands(gcj) = 2
+                   AND abs(ST_UpperLeftX(gcj) - 120.00466) < 1e-4
+                   AND ST_BandPixelType(gcj, 1) = '8BUI'
+                   AND ST_BandNoDataValue(gcj, 1) = 255
+                   AND ST_Value(gcj, 1, 4, 4) = 7
+                   AND ST_Value(gcj, 2, 4, 4) = 1.5
+                   AND ST_Value(ST_EvilTransform(rast, 990001), 1, 4, 4) = 7
+                   AND ST_SRID(ST_EvilTransform(gcj, 990002, 'bilinear')) = 990002
+                   AND ST_Value(ST_EvilTransform(gcj, 4326, 'bilinear'), 1, 4, 4) = 7
+                 FROM warped",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(error = "unknown resampling \"cubic\", expected nearest or bilinear")]
+        fn test_raster_rejects_unknown_resampling() {
+            Spi::run("CREATE EXTENSION IF NOT EXISTS postgis_raster").expect("SPI failed");
+            Spi::run(
+                "SELECT eviltransform_internal.__st_eviltransform_raster(
+                   ST_AddBand(ST_MakeEmptyRaster(2, 2, 120.0, 30.0, 0.001, -0.001, 0, 0, 4326), '8BUI'::text),
+                   990001, 'cubic')",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_safe_transform_of_corrupted_ewkb() {
+            // A point whose second ordinate is cut short.
+            let truncated = "'\\x0101000020e6100000000000000000005e400000'::bytea";
+            let null_row = Spi::get_one::<bool>(&format!(
+                "SELECT ST_EvilTransformSafe({truncated}, 990001) IS NULL"
+            ))
+            .expect("SPI failed");
+            assert_eq!(null_row, Some(true));
+
+            let passthrough = Spi::get_one::<bool>(&format!(
+                "SELECT ST_EvilTransformSafe({truncated}, 990001, 'passthrough') = {truncated}"
+            ))
+            .expect("SPI failed");
+            assert_eq!(passthrough, Some(true));
+
+            let transformed = Spi::get_one::<bool>(
+                "SELECT ST_EvilTransformSafe(ST_AsEWKB('SRID=4326;POINT(120 30)'::geometry), 990001)
+                      = ST_AsEWKB(ST_EvilTransform('SRID=4326;POINT(120 30)'::geometry, 990001))",
+            )
+            .expect("SPI failed");
+            assert_eq!(transformed, Some(true));
+
+            // One bad row among good ones leaves the rest of the statement intact.
+            let counts = Spi::get_two::<i64, i64>(&format!(
+                "SELECT count(*), count(out) FROM (
+                   SELECT ST_EvilTransformSafe(e, 990001) AS out
+                   FROM (VALUES (ST_AsEWKB('SRID=4326;POINT(120 30)'::geometry)),
+                                ({truncated}),
+                                (ST_AsEWKB('SRID=4326;POINT(121 31)'::geometry))) AS t(e)
+                 ) AS s"
+            ))
+            .expect("SPI failed");
+            assert_eq!(counts, (Some(3), Some(2)));
+        }
+
+        #[pg_test(error = "failed to transform EWKB geometry: unexpected end of EWKB at byte 17")]
+        fn test_safe_transform_error_mode() {
+            Spi::run(
+                "SELECT ST_EvilTransformSafe('\\x0101000020e6100000000000000000005e400000'::bytea, 990001, 'error')",
+            )
+            .expect("SPI failed");
+        }
+
+        #[pg_test]
+        fn test_safe_transform_geometry_without_plan() {
+            let result = Spi::get_two::<bool, bool>(
+                "SELECT ST_EvilTransformSafe('POINT(120 30)'::geometry, 990001) IS NULL,
+                        ST_EvilTransformSafe('POINT(120 30)'::geometry, 990001, 'passthrough')
+                          = 'POINT(120 30)'::geometry",
+            )
+            .expect("SPI failed");
+            assert_eq!(result, (Some(true), Some(true)));
+        }
+
+        #[pg_test(error = "unknown on_error mode \"skip\", expected null, passthrough or error")]
+        fn test_safe_transform_rejects_unknown_mode() {
+            Spi::run(
+                "SELECT ST_EvilTransformSafe('SRID=4326;POINT(120 30)'::geometry, 990001, 'skip')",
+            )
+            .expect("SPI failed");
+        }
+
+        // Runs `query`, which must fail, and returns the SQLSTATE, DETAIL and
+        // HINT it failed with.
+        fn error_fields(query: &str) -> (String, String, String) {
+            Spi::run(
+                "CREATE OR REPLACE FUNCTION pg_temp.error_fields(query text)
+                 RETURNS TABLE (state text, detail text, hint text)
+                 LANGUAGE plpgsql AS $$
+                 BEGIN
+                   EXECUTE query;
+                 EXCEPTION WHEN OTHERS THEN
+                   GET STACKED DIAGNOSTICS state = RETURNED_SQLSTATE,
+                                           detail = PG_EXCEPTION_DETAIL,
+                                           hint = PG_EXCEPTION_HINT;
+                   RETURN NEXT;
+                 END
+                 $$",
+            )
+            .expect("SPI failed");
+            let (state, detail, hint) = Spi::get_three_with_args::<String, String, String>(
+                "SELECT * FROM pg_temp.error_fields($1)",
+                &[query.into()],
+            )
+            .unwrap_or_else(|err| panic!("{query} did not fail: {err}"));
+            (
+                state.unwrap_or_default(),
+                detail.unwrap_or_default(),
+                hint.unwrap_or_default(),
+            )
+        }
+
+        #[pg_test]
+        fn test_error_sqlstates() {
+            assert_eq!(
+                error_fields(
+                    "SELECT ST_EvilTransformSafe('\\x0101000020e6100000000000000000005e400000'::bytea, 990001, 'error')"
+                ),
+                (
+                    "22P03".into(),
+                    "source SRID 4326, target SRID 990001, geometry type Point".into(),
+                    "Check the input with ST_EvilValidateEWKB.".into()
+                )
+            );
+            // A TIN and a PolyhedralSurface cut off after their element
+            // count: the DETAIL names them by their WKB type codes.
+            for (ewkb, name) in [
+                ("\\x0110000020e610000001000000", "Tin"),
+                ("\\x010f000020e610000001000000", "PolyhedralSurface"),
+            ] {
+                assert_eq!(
+                    error_fields(&format!(
+                        "SELECT ST_EvilTransformSafe('{ewkb}'::bytea, 990001, 'error')"
+                    ))
+                    .1,
+                    format!("source SRID 4326, target SRID 990001, geometry type {name}")
+                );
+            }
+            assert_eq!(
+                error_fields(
+                    "SELECT ST_EvilTransform('LINESTRING(120 30, 121 31)'::geometry, 990001)"
+                ),
+                (
+                    "22023".into(),
+                    "source SRID 0, target SRID 990001, geometry type LineString".into(),
+                    "Set the input SRID with ST_SetSRID.".into()
+                )
+            );
+            assert_eq!(
+                error_fields(
+                    "SELECT ST_EvilTransformSafe(ST_AsEWKB('SRID=4326;POINT(120 30)'::geometry), 4214, 'error')"
+                )
+                .0,
+                "0A000"
+            );
+            for query in [
+                "SELECT ST_EvilTransform('SRID=4326;POINT(120 30)'::geometry, 990001, 'everywhere')",
+                "SELECT ST_EvilTransform('SRID=4326;POINT(120 30)'::geometry, 990001, 'up', 'drop')",
+                "SELECT eviltransform_internal.__st_eviltransform_ewkb(ST_AsEWKB('POINT(120 30)'::geometry), 99)",
+                "SELECT ST_JenksBins(ARRAY[1.0, 2.0]::float8[], 0)",
+            ] {
+                assert_eq!(error_fields(query).0, "22023", "{query}");
+            }
+        }
+
+        #[pg_test]
+        fn test_validate_ewkb() {
+            let (valid, reason, offset) = Spi::get_three::<bool, String, i32>(
+                "SELECT * FROM ST_EvilValidateEWKB(ST_AsEWKB('SRID=4326;POLYGON((0 0, 1 0, 1 1, 0 0))'::geometry))",
+            )
+            .expect("SPI failed");
+            assert_eq!((valid, reason, offset), (Some(true), None, None));
+
+            let (valid, reason, offset) = Spi::get_three::<bool, String, i32>(
+                "SELECT * FROM ST_EvilValidateEWKB(
+                   '\\x0104000000010000000101000080000000000000f03f00000000000000400000000000000840'::bytea
+                 )",
+            )
+            .expect("SPI failed");
+            assert_eq!(valid, Some(false));
+            assert_eq!(
+                reason.as_deref(),
+                Some("EWKB sub-geometry at byte 9 is XYZ, expected XY")
+            );
+            assert_eq!(offset, Some(9));
+        }
+
+        #[pg_test]
+        fn test_is_in_china_offset_zone() {
+            let got = Spi::get_one::<bool>(
+                "SELECT ST_IsInChinaOffsetZone('SRID=4326;POINT(116.404 39.915)'::geometry)
+                   AND ST_IsInChinaOffsetZone('SRID=4326;LINESTRING(121.47 31.23, 114.16 22.28)'::geometry)
+                   AND NOT ST_IsInChinaOffsetZone('SRID=4326;POINT(106.92 47.92)'::geometry)
+                   AND NOT ST_IsInChinaOffsetZone('SRID=4326;LINESTRING(116.404 39.915, 125.75 39.03)'::geometry)
+                   AND NOT ST_IsInChinaOffsetZone('SRID=4326;POINT EMPTY'::geometry)",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test]
+        fn test_is_in_china_offset_zone_srids_and_zones() {
+            let got = Spi::get_one::<bool>(
+                "WITH beijing AS (
+                   SELECT ST_SetSRID(ST_MakePoint(116.404, 39.915), 4326) AS g
+                 )
+                 SELECT ST_IsInChinaOffsetZone(ST_Transform(g, 3857))
+                   AND ST_IsInChinaOffsetZone(ST_Transform(g, 32650))
+                   AND ST_IsInChinaOffsetZone(ST_EvilTransform(g, 990003))
+                   AND ST_IsInChinaOffsetZone(ST_EvilTransform(g, 990005))
+                   AND NOT ST_IsInChinaOffsetZone(ST_Transform(ST_SetSRID('POINT(106.92 47.92)'::geometry, 4326), 3857))
+                   AND ST_IsInChinaOffsetZone('SRID=4326;POINT(106.92 47.92)'::geometry, 'rectangle')
+                   AND NOT ST_IsInChinaOffsetZone('SRID=4326;POINT(114.16 22.28)'::geometry, 'skip_hk_mo_tw')
+                 FROM beijing",
+            )
+            .expect("SPI failed")
+            .expect("no row returned");
+
+            assert!(got);
+        }
+
+        #[pg_test(error = "expected a geometry value")]
+        fn test_is_in_china_offset_zone_rejects_geography() {
+            Spi::run(
+                "SELECT eviltransform_internal.__st_isinchinaoffsetzone('SRID=4326;POINT(116 39)'::geography, 'border')",
+            )
+            .expect("SPI failed");
+        }
+
         #[pg_test]
         fn test_jenksbins_array_overloads() {
             for sql in [
@@ -0,0 +1,451 @@
+use crate::coord::Transform;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ZMode {
+    #[default]
+    Passthrough,
+    Drop,
+    Offset(f64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MMode {
+    #[default]
+    Passthrough,
+    Drop,
+    CumulativeLength,
+}
+
+// How CIRCULARSTRING arcs are carried through a transform. Control points
+// are transformed as they are by default; `Refit` first moves each middle
+// control point onto its arc's midpoint, and `Densify` replaces arcs by
+// chords within the given tolerance (in input units) before transforming,
+// turning curve types into their linear counterparts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CurveMode {
+    #[default]
+    ControlPoints,
+    Refit,
+    Densify(f64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeometryOptions {
+    pub z: ZMode,
+    pub m: MMode,
+    pub curve: CurveMode,
+    // When set, straight segments are split until each transformed midpoint
+    // lies within this distance (output units) of the transformed chord.
+    pub max_segment_error: Option<f64>,
+    // Convergence tolerance (degrees) for the exact inverses, in place of
+    // `coord::DEFAULT_EXACT_TOLERANCE`.
+    pub exact_tolerance: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidOption(pub String);
+
+impl std::fmt::Display for InvalidOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidOption {}
+
+impl std::str::FromStr for ZMode {
+    type Err = InvalidOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec = s.trim().to_ascii_lowercase();
+        match spec.as_str() {
+            "passthrough" => Ok(Self::Passthrough),
+            "drop" => Ok(Self::Drop),
+            _ => spec
+                .strip_prefix("offset:")
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite())
+                .map(Self::Offset)
+                .ok_or_else(|| {
+                    InvalidOption(format!(
+                        "unknown Z mode {s:?}, expected passthrough, drop or offset:<value>"
+                    ))
+                }),
+        }
+    }
+}
+
+impl std::str::FromStr for MMode {
+    type Err = InvalidOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "passthrough" => Ok(Self::Passthrough),
+            "drop" => Ok(Self::Drop),
+            "cumulative_length" => Ok(Self::CumulativeLength),
+            _ => Err(InvalidOption(format!(
+                "unknown M mode {s:?}, expected passthrough, drop or cumulative_length"
+            ))),
+        }
+    }
+}
+
+impl std::str::FromStr for CurveMode {
+    type Err = InvalidOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec = s.trim().to_ascii_lowercase();
+        match spec.as_str() {
+            "control_points" => Ok(Self::ControlPoints),
+            "refit" => Ok(Self::Refit),
+            _ => spec
+                .strip_prefix("densify:")
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .map(Self::Densify)
+                .ok_or_else(|| {
+                    InvalidOption(format!(
+                        "unknown curve mode {s:?}, expected control_points, refit or densify:<tolerance>"
+                    ))
+                }),
+        }
+    }
+}
+
+// What ST_EvilTransformSafe returns for a row that fails to transform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnError {
+    #[default]
+    Null,
+    Passthrough,
+    Error,
+}
+
+impl std::str::FromStr for OnError {
+    type Err = InvalidOption;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "null" => Ok(Self::Null),
+            "passthrough" => Ok(Self::Passthrough),
+            "error" => Ok(Self::Error),
+            _ => Err(InvalidOption(format!(
+                "unknown on_error mode {s:?}, expected null, passthrough or error"
+            ))),
+        }
+    }
+}
+
+// A vertex as (x, y, z, m); absent ordinates are 0.
+pub(crate) type Vertex = [f64; 4];
+
+// Transforms XY (when a transform is given) and then applies the Z/M rules.
+pub(crate) fn transform_vertices(
+    points: &mut [Vertex],
+    transform: Option<Transform>,
+    options: &GeometryOptions,
+) {
+    if let Some(transform) = transform {
+        let mut xs: Vec<f64> = points.iter().map(|p| p[0]).collect();
+        let mut ys: Vec<f64> = points.iter().map(|p| p[1]).collect();
+        transform.apply_batch(&mut xs, &mut ys);
+        for (p, (x, y)) in points.iter_mut().zip(xs.into_iter().zip(ys)) {
+            p[0] = x;
+            p[1] = y;
+        }
+    }
+    options.apply(points);
+}
+
+// As transform_vertices, but segmentizes linear arrays (not arc control
+// points) when `max_segment_error` is set.
+pub(crate) fn transform_vertices_segmentized(
+    points: &mut Vec<Vertex>,
+    transform: Option<Transform>,
+    options: &GeometryOptions,
+    linear: bool,
+) -> Result<(), TooManyPoints> {
+    match (transform, options.max_segment_error) {
+        (Some(transform), Some(max_error)) if linear => {
+            *points = transform_segmentized(points, transform, max_error)?;
+            options.apply(points);
+        }
+        _ => transform_vertices(points, transform, options),
+    }
+    Ok(())
+}
+
+// Splitting stops at this depth even if the error bound is not met, so a
+// segment crossing the edge of the offset zone (where the warp jumps) adds at
+// most two vertices per level.
+const MAX_SPLIT_DEPTH: u32 = 24;
+
+// The most vertices segmentizing may produce for one point array. A tiny
+// `max_error` on long segments would otherwise grow without bound.
+pub const MAX_SEGMENTIZED_POINTS: usize = 1 << 22;
+
+// Segmentizing a point array would exceed `MAX_SEGMENTIZED_POINTS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyPoints;
+
+impl std::fmt::Display for TooManyPoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "segmentizing a point array would exceed {MAX_SEGMENTIZED_POINTS} points"
+        )
+    }
+}
+
+impl std::error::Error for TooManyPoints {}
+
+// Transforms the XY of a linear point array, inserting vertices along each
+// segment until the transformed midpoint lies within `max_error` of the
+// transformed chord, both in output units. Z and M of inserted vertices are
+// interpolated.
+pub(crate) fn transform_segmentized(
+    points: &[Vertex],
+    transform: Transform,
+    max_error: f64,
+) -> Result<Vec<Vertex>, TooManyPoints> {
+    let apply = |p: Vertex| {
+        let (lat, lng) = transform.apply(p[1], p[0]);
+        [lng, lat, p[2], p[3]]
+    };
+    if points.len() > MAX_SEGMENTIZED_POINTS {
+        return Err(TooManyPoints);
+    }
+    let mut out = Vec::with_capacity(points.len());
+    let Some((&first, rest)) = points.split_first() else {
+        return Ok(out);
+    };
+    let mut prev = (first, apply(first));
+    out.push(prev.1);
+    for (i, &p) in rest.iter().enumerate() {
+        let next = (p, apply(p));
+        // Leave room for the original vertices still to come.
+        let limit = MAX_SEGMENTIZED_POINTS - (rest.len() - i);
+        split_segment(
+            prev,
+            next,
+            &apply,
+            max_error,
+            MAX_SPLIT_DEPTH,
+            limit,
+            &mut out,
+        )?;
+        out.push(next.1);
+        prev = next;
+    }
+    Ok(out)
+}
+
+#[inline]
+fn midpoint(a: Vertex, b: Vertex) -> Vertex {
+    [
+        (a[0] + b[0]) / 2.0,
+        (a[1] + b[1]) / 2.0,
+        (a[2] + b[2]) / 2.0,
+        (a[3] + b[3]) / 2.0,
+    ]
+}
+
+// `a` and `b` are (source, transformed) pairs; pushes the vertices strictly
+// between them, keeping `out` within `limit` vertices.
+fn split_segment(
+    a: (Vertex, Vertex),
+    b: (Vertex, Vertex),
+    apply: &impl Fn(Vertex) -> Vertex,
+    max_error: f64,
+    depth: u32,
+    limit: usize,
+    out: &mut Vec<Vertex>,
+) -> Result<(), TooManyPoints> {
+    if depth == 0 {
+        return Ok(());
+    }
+    let source = midpoint(a.0, b.0);
+    let target = apply(source);
+    let chord = midpoint(a.1, b.1);
+    if (target[0] - chord[0]).hypot(target[1] - chord[1]) <= max_error {
+        return Ok(());
+    }
+    if out.len() >= limit {
+        return Err(TooManyPoints);
+    }
+    let mid = (source, target);
+    split_segment(a, mid, apply, max_error, depth - 1, limit, out)?;
+    out.push(target);
+    split_segment(mid, b, apply, max_error, depth - 1, limit, out)
+}
+
+impl GeometryOptions {
+    // Whether the layout is left alone; the exact tolerance only changes
+    // the coordinates.
+    pub fn is_passthrough(&self) -> bool {
+        Self {
+            exact_tolerance: None,
+            ..*self
+        } == Self::default()
+    }
+
+    pub fn output_dims(&self, has_z: bool, has_m: bool) -> (bool, bool) {
+        let has_z = has_z && self.z != ZMode::Drop;
+        let has_m = match self.m {
+            MMode::Passthrough => has_m,
+            MMode::Drop => false,
+            MMode::CumulativeLength => true,
+        };
+        (has_z, has_m)
+    }
+
+    // Applies the Z/M rules to one point array whose XY is already final.
+    // Cumulative length restarts at 0 for every point array (each linestring,
+    // each ring) and is measured in the output coordinate units.
+    pub(crate) fn apply(&self, points: &mut [Vertex]) {
+        if let ZMode::Offset(dz) = self.z {
+            for p in points.iter_mut() {
+                p[2] += dz;
+            }
+        }
+        if self.m == MMode::CumulativeLength {
+            let mut length = 0.0;
+            let mut prev: Option<Vertex> = None;
+            for p in points.iter_mut() {
+                if let Some(q) = prev {
+                    length += (p[0] - q[0]).hypot(p[1] - q[1]);
+                }
+                prev = Some(*p);
+                p[3] = length;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::TransformKind;
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!("drop".parse(), Ok(ZMode::Drop));
+        assert_eq!(" Offset:-12.5 ".parse(), Ok(ZMode::Offset(-12.5)));
+        assert!("offset:nan".parse::<ZMode>().is_err());
+        assert_eq!("cumulative_length".parse(), Ok(MMode::CumulativeLength));
+        assert!("sideways".parse::<MMode>().is_err());
+        assert_eq!(" PassThrough".parse(), Ok(OnError::Passthrough));
+        assert!("ignore".parse::<OnError>().is_err());
+        assert_eq!("Refit".parse(), Ok(CurveMode::Refit));
+        assert_eq!("densify: 1e-4".parse(), Ok(CurveMode::Densify(1e-4)));
+        assert!("densify:0".parse::<CurveMode>().is_err());
+    }
+
+    #[test]
+    fn test_cumulative_length_restarts_per_array() {
+        let options = GeometryOptions {
+            z: ZMode::Offset(1.0),
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        let mut points = [
+            [0.0, 0.0, 5.0, 9.0],
+            [3.0, 4.0, 5.0, 9.0],
+            [3.0, 10.0, 5.0, 9.0],
+        ];
+        options.apply(&mut points);
+        assert_eq!(
+            points.map(|p| (p[2], p[3])),
+            [(6.0, 0.0), (6.0, 5.0), (6.0, 11.0)]
+        );
+    }
+
+    #[test]
+    fn test_output_dims() {
+        let options = GeometryOptions {
+            z: ZMode::Drop,
+            m: MMode::CumulativeLength,
+            ..Default::default()
+        };
+        assert_eq!(options.output_dims(true, false), (false, true));
+        assert_eq!(
+            GeometryOptions::default().output_dims(true, true),
+            (true, true)
+        );
+    }
+
+    fn gcj(p: Vertex) -> Vertex {
+        let (lat, lng) = crate::coord::apply(TransformKind::Wgs2Gcj, p[1], p[0]);
+        [lng, lat, p[2], p[3]]
+    }
+
+    #[test]
+    fn test_segmentize_bounds_midpoint_error() {
+        let line = [[100.0, 20.0, 0.0, 0.0], [130.0, 45.0, 10.0, 0.0]];
+        let out = transform_segmentized(&line, TransformKind::Wgs2Gcj.into(), 1e-6).unwrap();
+        assert!(out.len() > 100);
+        assert_eq!(out[0], gcj(line[0]));
+        assert_eq!(out[out.len() - 1], gcj(line[1]));
+
+        let source: Vec<Vertex> = out
+            .iter()
+            .map(|p| {
+                let (lat, lng) = crate::coord::apply(TransformKind::Gcj2WgsExact, p[1], p[0]);
+                [lng, lat, p[2], p[3]]
+            })
+            .collect();
+        for (pair, src) in out.windows(2).zip(source.windows(2)) {
+            let expected = gcj(midpoint(src[0], src[1]));
+            let chord = midpoint(pair[0], pair[1]);
+            assert!((expected[0] - chord[0]).hypot(expected[1] - chord[1]) < 1.01e-6);
+            assert!(pair[1][2] > pair[0][2]);
+        }
+    }
+
+    #[test]
+    fn test_segmentize_leaves_unwarped_segments() {
+        let line = [[-120.0, 30.0, 0.0, 0.0], [-100.0, 45.0, 0.0, 0.0]];
+        let out = transform_segmentized(&line, TransformKind::Wgs2Gcj.into(), 1e-9).unwrap();
+        assert_eq!(out, line.to_vec());
+    }
+
+    #[test]
+    fn test_segmentize_terminates_at_zone_edge() {
+        // The warp jumps where the segment enters the zone; only the halves
+        // containing the jump keep splitting, down to the error bound.
+        let crossing = [[70.0, 30.0, 0.0, 0.0], [75.0, 30.0, 0.0, 0.0]];
+        let inside = [[72.0041, 30.0, 0.0, 0.0], [75.0, 30.0, 0.0, 0.0]];
+        let transform = TransformKind::Wgs2Gcj.into();
+        let crossing = transform_segmentized(&crossing, transform, 1e-6).unwrap();
+        let inside = transform_segmentized(&inside, transform, 1e-6).unwrap();
+        assert!(
+            crossing.len() <= inside.len() + 2 * MAX_SPLIT_DEPTH as usize,
+            "{} {}",
+            crossing.len(),
+            inside.len()
+        );
+    }
+
+    #[test]
+    fn test_segmentize_measures_error_in_output_units() {
+        // BD09MC output is in metres: a 1 m bound needs far fewer vertices
+        // than a 1e-6 bound, and the result stays within it.
+        let line = [[116.0, 39.0, 0.0, 0.0], [117.0, 40.0, 0.0, 0.0]];
+        let transform: Transform = TransformKind::Wgs2Bdmc.into();
+        let metres = transform_segmentized(&line, transform, 1.0).unwrap();
+        let fine = transform_segmentized(&line, transform, 1e-3).unwrap();
+        assert!(metres.len() < fine.len(), "{} {}", metres.len(), fine.len());
+        assert!(metres.len() > 2);
+    }
+
+    #[test]
+    fn test_segmentize_point_limit() {
+        // The GCJ-02 warp is never exactly linear, so an error bound of zero
+        // only stops at MAX_SPLIT_DEPTH, far past the point limit.
+        let line = [[100.0, 20.0, 0.0, 0.0], [130.0, 45.0, 0.0, 0.0]];
+        assert_eq!(
+            transform_segmentized(&line, TransformKind::Wgs2Gcj.into(), 0.0),
+            Err(TooManyPoints)
+        );
+    }
+}
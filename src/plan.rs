@@ -0,0 +1,228 @@
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::coord::{Projection, TransformKind};
+use crate::srid::{self, CustomSrids, SRID_WEB_MERCATOR, SRID_WGS84};
+
+// One hop of a plan. `srid` is the SRID the geometry carries afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Custom {
+        kind: TransformKind,
+        from: Projection,
+        to: Projection,
+        srid: i32,
+    },
+    StTransform {
+        srid: i32,
+    },
+}
+
+pub type Plan = Rc<[Step]>;
+
+// Finds the shortest chain of steps between two SRIDs. Custom and standard
+// SRIDs form an implicit graph: any two standard SRIDs are one ST_Transform
+// apart, and SRIDs with a frame (4326, 3857 and the custom ones) are one
+// custom pass apart when their datums share an edge. Plans are cached per
+// SRID pair, so a planner must be rebuilt when the custom SRIDs change.
+pub struct Planner {
+    srids: CustomSrids,
+    cache: HashMap<(i32, i32, bool), Option<Plan>>,
+}
+
+impl Planner {
+    pub fn new(srids: CustomSrids) -> Self {
+        Self {
+            srids,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn srids(&self) -> &CustomSrids {
+        &self.srids
+    }
+
+    pub fn plan(&mut self, src_srid: i32, dst_srid: i32, exact: bool) -> Option<Plan> {
+        let key = (src_srid, dst_srid, exact);
+        if let Some(plan) = self.cache.get(&key) {
+            return plan.clone();
+        }
+        let plan = self.search(src_srid, dst_srid, exact).map(Rc::from);
+        self.cache.insert(key, plan.clone());
+        plan
+    }
+
+    // A single custom pass between two SRIDs with a frame, including the
+    // standard ones that a plan would hand to ST_Transform.
+    pub fn direct(&self, src_srid: i32, dst_srid: i32, exact: bool) -> Option<Step> {
+        let (src_datum, from) = self.srids.frame(src_srid)?;
+        let (dst_datum, to) = self.srids.frame(dst_srid)?;
+        let kind = srid::datum_kind(src_datum, dst_datum, exact)?;
+        Some(Step::Custom {
+            kind,
+            from,
+            to,
+            srid: dst_srid,
+        })
+    }
+
+    fn edge(&self, src_srid: i32, dst_srid: i32, exact: bool) -> Option<Step> {
+        if !self.srids.is_custom(src_srid) && !self.srids.is_custom(dst_srid) {
+            Some(Step::StTransform { srid: dst_srid })
+        } else {
+            self.direct(src_srid, dst_srid, exact)
+        }
+    }
+
+    // Breadth-first search over the SRIDs that can appear in a plan. WGS84 comes
+    // before Web Mercator so that ties go through the cheaper unprojected frame.
+    fn search(&self, src_srid: i32, dst_srid: i32, exact: bool) -> Option<Vec<Step>> {
+        let mut nodes = vec![src_srid, dst_srid, SRID_WGS84, SRID_WEB_MERCATOR];
+        for srid in self.srids.all() {
+            if !nodes.contains(&srid) {
+                nodes.push(srid);
+            }
+        }
+
+        let mut prev: HashMap<i32, (i32, Step)> = HashMap::new();
+        let mut queue = VecDeque::from([src_srid]);
+        while let Some(node) = queue.pop_front() {
+            if node == dst_srid {
+                let mut steps = Vec::new();
+                let mut at = dst_srid;
+                while let Some(&(from, step)) = prev.get(&at) {
+                    steps.push(step);
+                    at = from;
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+            for &next in &nodes {
+                if next == src_srid || prev.contains_key(&next) {
+                    continue;
+                }
+                if let Some(step) = self.edge(node, next, exact) {
+                    prev.insert(next, (node, step));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(kind: TransformKind, from: Projection, to: Projection, srid: i32) -> Step {
+        Step::Custom {
+            kind,
+            from,
+            to,
+            srid,
+        }
+    }
+
+    #[test]
+    fn test_standard_pairs_use_st_transform() {
+        let mut planner = Planner::new(CustomSrids::default());
+        assert_eq!(
+            *planner.plan(4326, 3857, false).unwrap(),
+            [Step::StTransform { srid: 3857 }]
+        );
+        assert_eq!(*planner.plan(2000, 2000, false).unwrap(), []);
+    }
+
+    #[test]
+    fn test_custom_pairs_use_one_pass() {
+        let mut planner = Planner::new(CustomSrids::default());
+        assert_eq!(
+            *planner.plan(990003, 990004, true).unwrap(),
+            [custom(
+                TransformKind::Bdmc2GcjExact,
+                Projection::LngLat,
+                Projection::WebMercator,
+                990004
+            )]
+        );
+        assert_eq!(
+            *planner.plan(3857, 990002, false).unwrap(),
+            [custom(
+                TransformKind::Wgs2Bd,
+                Projection::WebMercator,
+                Projection::LngLat,
+                990002
+            )]
+        );
+    }
+
+    #[test]
+    fn test_projected_standard_targets_are_fused() {
+        let mut planner = Planner::new(CustomSrids::default());
+        assert_eq!(
+            *planner.plan(990002, 32650, false).unwrap(),
+            [custom(
+                TransformKind::Bd2Wgs,
+                Projection::LngLat,
+                Projection::Utm {
+                    zone: 50,
+                    south: false
+                },
+                32650
+            )]
+        );
+        assert_eq!(
+            *planner.plan(4490, 990001, false).unwrap(),
+            [custom(
+                TransformKind::Wgs2Gcj,
+                Projection::LngLat,
+                Projection::LngLat,
+                990001
+            )]
+        );
+    }
+
+    #[test]
+    fn test_standard_and_custom_meet_at_wgs84() {
+        let mut planner = Planner::new(CustomSrids::default());
+        assert_eq!(
+            *planner.plan(990001, 4547, true).unwrap(),
+            [
+                custom(
+                    TransformKind::Gcj2WgsExact,
+                    Projection::LngLat,
+                    Projection::LngLat,
+                    4326
+                ),
+                Step::StTransform { srid: 4547 },
+            ]
+        );
+        assert_eq!(
+            *planner.plan(4214, 990005, false).unwrap(),
+            [
+                Step::StTransform { srid: 4326 },
+                custom(
+                    TransformKind::Wgs2Bd,
+                    Projection::LngLat,
+                    Projection::WebMercator,
+                    990005
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plans_are_cached_per_pair() {
+        let mut planner = Planner::new(CustomSrids::default());
+        let first = planner.plan(990001, 4547, false).unwrap();
+        assert!(Rc::ptr_eq(
+            &first,
+            &planner.plan(990001, 4547, false).unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &first,
+            &planner.plan(990001, 4547, true).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::ptr;
+
+// The subset of the PROJ (>= 6) C API needed to run a CRS-to-CRS pipeline
+// over coordinate slices.
+#[repr(C)]
+struct PjContext {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct Pj {
+    _private: [u8; 0],
+}
+
+const PJ_FWD: c_int = 1;
+
+#[link(name = "proj")]
+unsafe extern "C" {
+    fn proj_context_create() -> *mut PjContext;
+    fn proj_context_destroy(ctx: *mut PjContext) -> *mut PjContext;
+    fn proj_context_errno(ctx: *mut PjContext) -> c_int;
+    // Deprecated in PROJ 8 in favour of proj_context_errno_string, which
+    // PROJ 6 and 7 lack.
+    fn proj_errno_string(err: c_int) -> *const c_char;
+    fn proj_create(ctx: *mut PjContext, definition: *const c_char) -> *mut Pj;
+    fn proj_create_crs_to_crs_from_pj(
+        ctx: *mut PjContext,
+        source_crs: *const Pj,
+        target_crs: *const Pj,
+        area: *mut c_void,
+        options: *const *const c_char,
+    ) -> *mut Pj;
+    fn proj_normalize_for_visualization(ctx: *mut PjContext, obj: *const Pj) -> *mut Pj;
+    fn proj_destroy(pj: *mut Pj) -> *mut Pj;
+    fn proj_errno(pj: *const Pj) -> c_int;
+    fn proj_errno_reset(pj: *const Pj) -> c_int;
+    fn proj_trans_generic(
+        pj: *mut Pj,
+        direction: c_int,
+        x: *mut f64,
+        sx: usize,
+        nx: usize,
+        y: *mut f64,
+        sy: usize,
+        ny: usize,
+        z: *mut f64,
+        sz: usize,
+        nz: usize,
+        t: *mut f64,
+        st: usize,
+        nt: usize,
+    ) -> usize;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjError(pub String);
+
+impl std::fmt::Display for ProjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ProjError {}
+
+// A PROJ pipeline between two CRSs, normalized so that geographic CRSs take
+// and return lng/lat degrees like PostGIS geometries do. Each transformer owns
+// its PROJ context, so it must stay on the thread that created it.
+pub struct ProjTransform {
+    ctx: *mut PjContext,
+    pj: *mut Pj,
+}
+
+impl ProjTransform {
+    // Each side is a list of candidate definitions (e.g. "EPSG:4326", WKT,
+    // a proj4 string), tried in order until PROJ accepts one.
+    pub fn new(src: &[&str], dst: &[&str]) -> Result<Self, ProjError> {
+        let ctx = unsafe { proj_context_create() };
+        if ctx.is_null() {
+            return Err(ProjError("failed to create PROJ context".into()));
+        }
+        // Owning the context first means every early return cleans it up.
+        let mut transform = Self {
+            ctx,
+            pj: ptr::null_mut(),
+        };
+        let src_crs = transform.create_crs(src)?;
+        let dst_crs = match transform.create_crs(dst) {
+            Ok(crs) => crs,
+            Err(err) => {
+                unsafe { proj_destroy(src_crs) };
+                return Err(err);
+            }
+        };
+        let pj = unsafe {
+            proj_create_crs_to_crs_from_pj(ctx, src_crs, dst_crs, ptr::null_mut(), ptr::null())
+        };
+        unsafe {
+            proj_destroy(src_crs);
+            proj_destroy(dst_crs);
+        }
+        if pj.is_null() {
+            return Err(transform.context_error("failed to create PROJ pipeline"));
+        }
+        let normalized = unsafe { proj_normalize_for_visualization(ctx, pj) };
+        unsafe { proj_destroy(pj) };
+        if normalized.is_null() {
+            return Err(transform.context_error("failed to normalize PROJ pipeline"));
+        }
+        transform.pj = normalized;
+        Ok(transform)
+    }
+
+    fn create_crs(&self, candidates: &[&str]) -> Result<*mut Pj, ProjError> {
+        for definition in candidates {
+            let Ok(definition) = CString::new(*definition) else {
+                continue;
+            };
+            let crs = unsafe { proj_create(self.ctx, definition.as_ptr()) };
+            if !crs.is_null() {
+                return Ok(crs);
+            }
+        }
+        Err(self.context_error(&format!("no usable CRS definition in {candidates:?}")))
+    }
+
+    fn context_error(&self, what: &str) -> ProjError {
+        let message = unsafe {
+            let err = proj_context_errno(self.ctx);
+            let text = proj_errno_string(err);
+            if text.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(text).to_string_lossy().into_owned()
+            }
+        };
+        ProjError(format!("{what}: {message}"))
+    }
+
+    // Transforms x/y (and z, when given) in place; the slices must be the
+    // same length. Without z PROJ treats every height as 0 and the stored Z
+    // is left as it was, which is wrong for 3D datum shifts and vertical CRSs.
+    pub fn apply_batch(
+        &self,
+        xs: &mut [f64],
+        ys: &mut [f64],
+        zs: Option<&mut [f64]>,
+    ) -> Result<(), ProjError> {
+        assert_eq!(xs.len(), ys.len());
+        let n = xs.len();
+        let stride = std::mem::size_of::<f64>();
+        let (z, nz) = match zs {
+            Some(zs) => {
+                assert_eq!(zs.len(), n);
+                (zs.as_mut_ptr(), n)
+            }
+            None => (ptr::null_mut(), 0),
+        };
+        unsafe {
+            proj_errno_reset(self.pj);
+            proj_trans_generic(
+                self.pj,
+                PJ_FWD,
+                xs.as_mut_ptr(),
+                stride,
+                n,
+                ys.as_mut_ptr(),
+                stride,
+                n,
+                z,
+                stride,
+                nz,
+                ptr::null_mut(),
+                0,
+                0,
+            );
+        }
+        match unsafe { proj_errno(self.pj) } {
+            0 => Ok(()),
+            err => {
+                let text = unsafe { proj_errno_string(err) };
+                let message = if text.is_null() {
+                    format!("PROJ error {err}")
+                } else {
+                    unsafe { CStr::from_ptr(text) }
+                        .to_string_lossy()
+                        .into_owned()
+                };
+                Err(ProjError(message))
+            }
+        }
+    }
+}
+
+impl Drop for ProjTransform {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.pj.is_null() {
+                proj_destroy(self.pj);
+            }
+            proj_context_destroy(self.ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::Projection;
+
+    #[test]
+    fn test_utm_matches_rust_projection() {
+        let proj = ProjTransform::new(&["EPSG:4326"], &["EPSG:32650"]).unwrap();
+        let (mut xs, mut ys) = (vec![120.0, 116.404], vec![30.0, 39.915]);
+        proj.apply_batch(&mut xs, &mut ys, None).unwrap();
+        let utm = Projection::Utm {
+            zone: 50,
+            south: false,
+        };
+        for (i, (lat, lng)) in [(30.0, 120.0), (39.915, 116.404)].into_iter().enumerate() {
+            let (y, x) = utm.project(lat, lng);
+            assert!((xs[i] - x).abs() < 1e-3, "{} {x}", xs[i]);
+            assert!((ys[i] - y).abs() < 1e-3, "{} {y}", ys[i]);
+        }
+    }
+
+    #[test]
+    fn test_passes_z_through() {
+        let proj = ProjTransform::new(&["EPSG:4979"], &["EPSG:4978"]).unwrap();
+        let (mut xs, mut ys, mut zs) = (vec![120.0, 120.0], vec![30.0, 30.0], vec![0.0, 1000.0]);
+        proj.apply_batch(&mut xs, &mut ys, Some(&mut zs)).unwrap();
+        // The two points lie 1000 m apart along the ellipsoid normal.
+        let (dx, dy, dz) = (xs[1] - xs[0], ys[1] - ys[0], zs[1] - zs[0]);
+        assert!(((dx * dx + dy * dy + dz * dz).sqrt() - 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_falls_back_to_later_candidates() {
+        let proj = ProjTransform::new(
+            &["NOPE:1", "+proj=longlat +datum=WGS84 +no_defs"],
+            &["EPSG:3857"],
+        )
+        .unwrap();
+        let (mut xs, mut ys) = (vec![120.0], vec![30.0]);
+        proj.apply_batch(&mut xs, &mut ys, None).unwrap();
+        assert!((xs[0] - 13_358_338.895_192_828).abs() < 1e-6);
+        assert!(ProjTransform::new(&["NOPE:1"], &["EPSG:3857"]).is_err());
+    }
+}
@@ -0,0 +1,672 @@
+use crate::coord::Transform;
+
+// Serialized PostGIS raster (rt_raster_serialized_t), native byte order:
+// varlena size, version, band count, six georeference doubles, SRID, width
+// and height, followed by 8-byte aligned bands.
+const HEADER_SIZE: usize = 64;
+
+const BANDTYPE_PIXTYPE_MASK: u8 = 0x0F;
+const BANDTYPE_FLAG_OFFDB: u8 = 0x80;
+const BANDTYPE_FLAG_HASNODATA: u8 = 0x40;
+const BANDTYPE_FLAG_ISNODATA: u8 = 0x20;
+
+// Samples per raster edge when estimating the transformed extent.
+const EDGE_SAMPLES: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RasterError {
+    UnexpectedEof,
+    UnsupportedVersion(u16),
+    UnsupportedPixelType(u8),
+    OfflineBand(usize),
+    DegenerateGeoreference,
+    TrailingData(usize),
+}
+
+impl std::fmt::Display for RasterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RasterError::UnexpectedEof => write!(f, "unexpected end of raster"),
+            RasterError::UnsupportedVersion(v) => write!(f, "unsupported raster version: {v}"),
+            RasterError::UnsupportedPixelType(v) => write!(f, "unsupported raster pixel type: {v}"),
+            RasterError::OfflineBand(n) => {
+                write!(
+                    f,
+                    "raster band {n} is out-db, only in-db bands can be warped"
+                )
+            }
+            RasterError::DegenerateGeoreference => {
+                write!(f, "raster georeference is not invertible")
+            }
+            RasterError::TrailingData(n) => write!(f, "raster has {n} trailing bytes"),
+        }
+    }
+}
+
+impl std::error::Error for RasterError {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resampling {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownResampling(pub String);
+
+impl std::fmt::Display for UnknownResampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown resampling {:?}, expected nearest or bilinear",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownResampling {}
+
+impl std::str::FromStr for Resampling {
+    type Err = UnknownResampling;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "nearest" | "nearestneighbor" | "nearestneighbour" => Ok(Self::Nearest),
+            "bilinear" => Ok(Self::Bilinear),
+            _ => Err(UnknownResampling(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelType {
+    Bool1,
+    UInt2,
+    UInt4,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PixelType {
+    fn from_code(code: u8) -> Result<Self, RasterError> {
+        match code {
+            0 => Ok(Self::Bool1),
+            1 => Ok(Self::UInt2),
+            2 => Ok(Self::UInt4),
+            3 => Ok(Self::Int8),
+            4 => Ok(Self::UInt8),
+            5 => Ok(Self::Int16),
+            6 => Ok(Self::UInt16),
+            7 => Ok(Self::Int32),
+            8 => Ok(Self::UInt32),
+            10 => Ok(Self::Float32),
+            11 => Ok(Self::Float64),
+            other => Err(RasterError::UnsupportedPixelType(other)),
+        }
+    }
+
+    // Sub-byte types are stored one pixel per byte in the serialized form.
+    #[inline]
+    pub fn size(self) -> usize {
+        match self {
+            Self::Bool1 | Self::UInt2 | Self::UInt4 | Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    #[inline]
+    fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            Self::Bool1 | Self::UInt2 | Self::UInt4 | Self::UInt8 => f64::from(bytes[0]),
+            Self::Int8 => f64::from(bytes[0] as i8),
+            Self::Int16 => f64::from(i16::from_ne_bytes([bytes[0], bytes[1]])),
+            Self::UInt16 => f64::from(u16::from_ne_bytes([bytes[0], bytes[1]])),
+            Self::Int32 => f64::from(i32::from_ne_bytes(bytes[..4].try_into().unwrap())),
+            Self::UInt32 => f64::from(u32::from_ne_bytes(bytes[..4].try_into().unwrap())),
+            Self::Float32 => f64::from(f32::from_ne_bytes(bytes[..4].try_into().unwrap())),
+            Self::Float64 => f64::from_ne_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+
+    // Integer types round to nearest and clamp to their range, as
+    // rt_band_set_pixel does.
+    #[inline]
+    fn write(self, value: f64, out: &mut [u8]) {
+        let int = |max: f64, min: f64| value.round().clamp(min, max);
+        match self {
+            Self::Bool1 => out[0] = int(1.0, 0.0) as u8,
+            Self::UInt2 => out[0] = int(3.0, 0.0) as u8,
+            Self::UInt4 => out[0] = int(15.0, 0.0) as u8,
+            Self::UInt8 => out[0] = int(255.0, 0.0) as u8,
+            Self::Int8 => out[0] = (int(127.0, -128.0) as i8) as u8,
+            Self::Int16 => out[..2].copy_from_slice(&(int(32767.0, -32768.0) as i16).to_ne_bytes()),
+            Self::UInt16 => out[..2].copy_from_slice(&(int(65535.0, 0.0) as u16).to_ne_bytes()),
+            Self::Int32 => out[..4].copy_from_slice(
+                &(int(f64::from(i32::MAX), f64::from(i32::MIN)) as i32).to_ne_bytes(),
+            ),
+            Self::UInt32 => {
+                out[..4].copy_from_slice(&(int(f64::from(u32::MAX), 0.0) as u32).to_ne_bytes())
+            }
+            Self::Float32 => out[..4].copy_from_slice(&(value as f32).to_ne_bytes()),
+            Self::Float64 => out[..8].copy_from_slice(&value.to_ne_bytes()),
+        }
+    }
+}
+
+// The affine transform from (column, row) to world coordinates, measured
+// from the upper-left corner of the upper-left pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Georeference {
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub ip_x: f64,
+    pub ip_y: f64,
+    pub skew_x: f64,
+    pub skew_y: f64,
+}
+
+impl Georeference {
+    #[inline]
+    pub fn cell_to_world(&self, col: f64, row: f64) -> (f64, f64) {
+        (
+            self.ip_x + self.scale_x * col + self.skew_x * row,
+            self.ip_y + self.skew_y * col + self.scale_y * row,
+        )
+    }
+
+    #[inline]
+    pub fn world_to_cell(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let det = self.scale_x * self.scale_y - self.skew_x * self.skew_y;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (dx, dy) = (x - self.ip_x, y - self.ip_y);
+        Some((
+            (self.scale_y * dx - self.skew_x * dy) / det,
+            (self.scale_x * dy - self.skew_y * dx) / det,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterHeader {
+    pub version: u16,
+    pub num_bands: u16,
+    pub georef: Georeference,
+    pub srid: i32,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[inline]
+fn ensure_remaining(buf: &[u8], offset: usize, need: usize) -> Result<(), RasterError> {
+    if buf.len().saturating_sub(offset) < need {
+        return Err(RasterError::UnexpectedEof);
+    }
+    Ok(())
+}
+
+#[inline]
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([buf[offset], buf[offset + 1]])
+}
+
+#[inline]
+fn read_f64(buf: &[u8], offset: usize) -> f64 {
+    f64::from_ne_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+pub fn read_header(buf: &[u8]) -> Result<RasterHeader, RasterError> {
+    ensure_remaining(buf, 0, HEADER_SIZE)?;
+    let version = read_u16(buf, 4);
+    if version != 0 {
+        return Err(RasterError::UnsupportedVersion(version));
+    }
+    Ok(RasterHeader {
+        version,
+        num_bands: read_u16(buf, 6),
+        georef: Georeference {
+            scale_x: read_f64(buf, 8),
+            scale_y: read_f64(buf, 16),
+            ip_x: read_f64(buf, 24),
+            ip_y: read_f64(buf, 32),
+            skew_x: read_f64(buf, 40),
+            skew_y: read_f64(buf, 48),
+        },
+        srid: i32::from_ne_bytes(buf[56..60].try_into().unwrap()),
+        width: read_u16(buf, 60),
+        height: read_u16(buf, 62),
+    })
+}
+
+fn write_header(buf: &mut [u8], georef: &Georeference, srid: i32) {
+    let fields = [
+        georef.scale_x,
+        georef.scale_y,
+        georef.ip_x,
+        georef.ip_y,
+        georef.skew_x,
+        georef.skew_y,
+    ];
+    for (i, value) in fields.iter().enumerate() {
+        buf[8 + i * 8..16 + i * 8].copy_from_slice(&value.to_ne_bytes());
+    }
+    buf[56..60].copy_from_slice(&srid.to_ne_bytes());
+}
+
+struct Band {
+    flags: u8,
+    pixtype: PixelType,
+    nodata: f64,
+    data_offset: usize,
+}
+
+impl Band {
+    #[inline]
+    fn has_nodata(&self) -> bool {
+        self.flags & BANDTYPE_FLAG_HASNODATA != 0
+    }
+
+    #[inline]
+    fn is_nodata(&self) -> bool {
+        self.flags & BANDTYPE_FLAG_ISNODATA != 0
+    }
+
+    // Value written where the warped raster has no source pixel.
+    #[inline]
+    fn fill(&self) -> f64 {
+        if self.has_nodata() { self.nodata } else { 0.0 }
+    }
+}
+
+fn read_bands(buf: &[u8], header: &RasterHeader) -> Result<Vec<Band>, RasterError> {
+    let npixels = usize::from(header.width) * usize::from(header.height);
+    let mut bands = Vec::with_capacity(usize::from(header.num_bands));
+    let mut offset = HEADER_SIZE;
+    for n in 0..usize::from(header.num_bands) {
+        ensure_remaining(buf, offset, 1)?;
+        let flags = buf[offset];
+        if flags & BANDTYPE_FLAG_OFFDB != 0 {
+            return Err(RasterError::OfflineBand(n + 1));
+        }
+        let pixtype = PixelType::from_code(flags & BANDTYPE_PIXTYPE_MASK)?;
+        let size = pixtype.size();
+        // The type byte is padded so the nodata value is pixel-aligned.
+        offset += size;
+        ensure_remaining(buf, offset, size + npixels * size)?;
+        let nodata = pixtype.read(&buf[offset..offset + size]);
+        offset += size;
+        bands.push(Band {
+            flags,
+            pixtype,
+            nodata,
+            data_offset: offset,
+        });
+        offset += npixels * size;
+        offset = offset.next_multiple_of(8);
+    }
+    if offset < buf.len() {
+        return Err(RasterError::TrailingData(buf.len() - offset));
+    }
+    Ok(bands)
+}
+
+// A north-up grid of the same size covering the transformed extent, sampled
+// along the raster's edges. The signs of the input scales are kept.
+fn warped_georeference(
+    header: &RasterHeader,
+    forward: Transform,
+) -> Result<Georeference, RasterError> {
+    let (width, height) = (f64::from(header.width), f64::from(header.height));
+    let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for i in 0..=EDGE_SAMPLES {
+        let t = i as f64 / EDGE_SAMPLES as f64;
+        for (col, row) in [
+            (t * width, 0.0),
+            (t * width, height),
+            (0.0, t * height),
+            (width, t * height),
+        ] {
+            let (x, y) = header.georef.cell_to_world(col, row);
+            let (lat, lng) = forward.apply(y, x);
+            min_x = min_x.min(lng);
+            max_x = max_x.max(lng);
+            min_y = min_y.min(lat);
+            max_y = max_y.max(lat);
+        }
+    }
+
+    let scale_x = (max_x - min_x) / width;
+    let scale_y = (max_y - min_y) / height;
+    if !(scale_x > 0.0 && scale_y > 0.0) {
+        return Err(RasterError::DegenerateGeoreference);
+    }
+    let (scale_x, ip_x) = if header.georef.scale_x < 0.0 {
+        (-scale_x, max_x)
+    } else {
+        (scale_x, min_x)
+    };
+    let (scale_y, ip_y) = if header.georef.scale_y < 0.0 {
+        (-scale_y, max_y)
+    } else {
+        (scale_y, min_y)
+    };
+    Ok(Georeference {
+        scale_x,
+        scale_y,
+        ip_x,
+        ip_y,
+        skew_x: 0.0,
+        skew_y: 0.0,
+    })
+}
+
+struct Sampler<'a> {
+    buf: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Sampler<'_> {
+    #[inline]
+    fn pixel(&self, band: &Band, col: i64, row: i64) -> Option<f64> {
+        if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+            return None;
+        }
+        let size = band.pixtype.size();
+        let offset = band.data_offset + (row as usize * self.width + col as usize) * size;
+        let value = band.pixtype.read(&self.buf[offset..offset + size]);
+        if band.has_nodata() && value == band.nodata {
+            return None;
+        }
+        Some(value)
+    }
+
+    // `col`/`row` are fractional cell coordinates; pixel centres sit at
+    // integer + 0.5.
+    fn sample(&self, band: &Band, col: f64, row: f64, resampling: Resampling) -> Option<f64> {
+        match resampling {
+            Resampling::Nearest => self.pixel(band, col.floor() as i64, row.floor() as i64),
+            Resampling::Bilinear => {
+                let (fx, fy) = (col - 0.5, row - 0.5);
+                let (x0, y0) = (fx.floor(), fy.floor());
+                let (tx, ty) = (fx - x0, fy - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let mut sum = 0.0;
+                let mut weight = 0.0;
+                for (dx, dy, w) in [
+                    (0, 0, (1.0 - tx) * (1.0 - ty)),
+                    (1, 0, tx * (1.0 - ty)),
+                    (0, 1, (1.0 - tx) * ty),
+                    (1, 1, tx * ty),
+                ] {
+                    if w == 0.0 {
+                        continue;
+                    }
+                    if let Some(value) = self.pixel(band, x0 + dx, y0 + dy) {
+                        sum += value * w;
+                        weight += w;
+                    }
+                }
+                (weight > 0.0).then(|| sum / weight)
+            }
+        }
+    }
+}
+
+// Warps every in-db band onto a north-up grid of the same size covering the
+// transformed extent. Output pixel centres are mapped back with `inverse`
+// and sampled from the input; pixels that fall outside the input, or only on
+// nodata, get the band's nodata value (0 without one).
+pub fn warp_raster(
+    input: &[u8],
+    forward: Transform,
+    inverse: Transform,
+    dst_srid: i32,
+    resampling: Resampling,
+) -> Result<Vec<u8>, RasterError> {
+    let header = read_header(input)?;
+    let bands = read_bands(input, &header)?;
+    let mut out = input.to_vec();
+    if header.width == 0 || header.height == 0 {
+        write_header(&mut out, &header.georef, dst_srid);
+        return Ok(out);
+    }
+    if header.georef.world_to_cell(0.0, 0.0).is_none() {
+        return Err(RasterError::DegenerateGeoreference);
+    }
+
+    let georef = warped_georeference(&header, forward)?;
+    write_header(&mut out, &georef, dst_srid);
+
+    let (width, height) = (usize::from(header.width), usize::from(header.height));
+    let mut cells = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for col in 0..width {
+            let (x, y) = georef.cell_to_world(col as f64 + 0.5, row as f64 + 0.5);
+            let (lat, lng) = inverse.apply(y, x);
+            cells.push(header.georef.world_to_cell(lng, lat));
+        }
+    }
+
+    let sampler = Sampler {
+        buf: input,
+        width,
+        height,
+    };
+    for band in bands.iter().filter(|band| !band.is_nodata()) {
+        let size = band.pixtype.size();
+        for (i, cell) in cells.iter().enumerate() {
+            let value = cell
+                .and_then(|(col, row)| sampler.sample(band, col, row, resampling))
+                .unwrap_or_else(|| band.fill());
+            let offset = band.data_offset + i * size;
+            band.pixtype.write(value, &mut out[offset..offset + size]);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::TransformKind;
+
+    struct Builder(Vec<u8>);
+
+    impl Builder {
+        fn new(width: u16, height: u16, georef: [f64; 6], srid: i32) -> Self {
+            let mut buf = vec![0u8; HEADER_SIZE];
+            for (i, value) in georef.iter().enumerate() {
+                buf[8 + i * 8..16 + i * 8].copy_from_slice(&value.to_ne_bytes());
+            }
+            buf[56..60].copy_from_slice(&srid.to_ne_bytes());
+            buf[60..62].copy_from_slice(&width.to_ne_bytes());
+            buf[62..64].copy_from_slice(&height.to_ne_bytes());
+            Self(buf)
+        }
+
+        fn band(mut self, code: u8, nodata: Option<f64>, pixels: &[f64]) -> Self {
+            let pixtype = PixelType::from_code(code).unwrap();
+            let size = pixtype.size();
+            let mut flags = code;
+            if nodata.is_some() {
+                flags |= BANDTYPE_FLAG_HASNODATA;
+            }
+            self.0.push(flags);
+            self.0.resize(self.0.len() + size - 1, 0);
+            for value in std::iter::once(nodata.unwrap_or(0.0)).chain(pixels.iter().copied()) {
+                let start = self.0.len();
+                self.0.resize(start + size, 0);
+                pixtype.write(value, &mut self.0[start..]);
+            }
+            self.0.resize(self.0.len().next_multiple_of(8), 0);
+            let count = self.0[6] as u16 + 1;
+            self.0[6..8].copy_from_slice(&count.to_ne_bytes());
+            self
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            let len = (self.0.len() as u32) << 2;
+            self.0[..4].copy_from_slice(&len.to_ne_bytes());
+            self.0
+        }
+    }
+
+    fn pixels(buf: &[u8], band: usize) -> Vec<f64> {
+        let header = read_header(buf).unwrap();
+        let bands = read_bands(buf, &header).unwrap();
+        let band = &bands[band];
+        let size = band.pixtype.size();
+        let n = usize::from(header.width) * usize::from(header.height);
+        (0..n)
+            .map(|i| {
+                let offset = band.data_offset + i * size;
+                band.pixtype.read(&buf[offset..offset + size])
+            })
+            .collect()
+    }
+
+    fn wgs2gcj() -> (Transform, Transform) {
+        let kind = TransformKind::Wgs2Gcj;
+        (kind.into(), kind.inverse().into())
+    }
+
+    // 8x8 pixels of 0.001 degrees near (120, 30), north-up.
+    const GEOREF: [f64; 6] = [0.001, -0.001, 120.0, 30.008, 0.0, 0.0];
+
+    fn gradient() -> Vec<f64> {
+        (0..64).map(|i| f64::from(i % 8 * 10 + i / 8)).collect()
+    }
+
+    #[test]
+    fn test_parse_resampling() {
+        assert_eq!("Bilinear".parse(), Ok(Resampling::Bilinear));
+        assert_eq!(" nearest ".parse(), Ok(Resampling::Nearest));
+        assert!("cubic".parse::<Resampling>().is_err());
+    }
+
+    #[test]
+    fn test_warp_moves_georeference_and_keeps_layout() {
+        let input = Builder::new(8, 8, GEOREF, 4326)
+            .band(4, Some(255.0), &gradient())
+            .band(11, None, &gradient())
+            .finish();
+        let (forward, inverse) = wgs2gcj();
+        let out = warp_raster(&input, forward, inverse, 990001, Resampling::Nearest).unwrap();
+        assert_eq!(out.len(), input.len());
+
+        let header = read_header(&out).unwrap();
+        assert_eq!((header.srid, header.num_bands), (990001, 2));
+        assert_eq!((header.width, header.height), (8, 8));
+        let (lat, lng) = forward.apply(30.008, 120.0);
+        assert!((header.georef.ip_x - lng).abs() < 1e-4);
+        assert!((header.georef.ip_y - lat).abs() < 1e-4);
+        assert!((header.georef.scale_x - 0.001).abs() < 1e-5);
+        assert!((header.georef.scale_y + 0.001).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_warp_round_trip_restores_pixels() {
+        let input = Builder::new(8, 8, GEOREF, 4326)
+            .band(4, Some(255.0), &gradient())
+            .finish();
+        let (forward, inverse) = wgs2gcj();
+        let gcj = warp_raster(&input, forward, inverse, 990001, Resampling::Nearest).unwrap();
+        let back = warp_raster(&gcj, inverse, forward, 4326, Resampling::Nearest).unwrap();
+        let restored = pixels(&back, 0);
+        let matching = restored
+            .iter()
+            .zip(gradient())
+            .filter(|(a, b)| **a == *b)
+            .count();
+        assert!(matching >= 56, "{matching} of 64 pixels restored");
+    }
+
+    #[test]
+    fn test_bilinear_interpolates_between_pixels() {
+        let input = Builder::new(8, 8, GEOREF, 4326)
+            .band(11, None, &gradient())
+            .finish();
+        let (forward, inverse) = wgs2gcj();
+        let nearest = warp_raster(&input, forward, inverse, 990001, Resampling::Nearest).unwrap();
+        let bilinear = warp_raster(&input, forward, inverse, 990001, Resampling::Bilinear).unwrap();
+        let nearest = pixels(&nearest, 0);
+        let bilinear = pixels(&bilinear, 0);
+        assert!(nearest.iter().all(|v| v.fract() == 0.0));
+        assert!(bilinear.iter().any(|v| v.fract() != 0.0));
+        for (n, b) in nearest.iter().zip(&bilinear) {
+            assert!((n - b).abs() <= 11.0);
+        }
+    }
+
+    #[test]
+    fn test_uncovered_pixels_get_nodata() {
+        let input = Builder::new(16, 16, [0.001, -0.001, 120.0, 30.016, 0.0, 0.0], 4326)
+            .band(6, Some(9999.0), &[1.0; 256])
+            .band(6, None, &[1.0; 256])
+            .finish();
+        // Mapping back with the forward transform samples twice the offset
+        // away from the source grid, leaving part of the output uncovered.
+        let forward: Transform = TransformKind::Wgs2Gcj.into();
+        let out = warp_raster(&input, forward, forward, 990001, Resampling::Nearest).unwrap();
+        let with_nodata = pixels(&out, 0);
+        let without = pixels(&out, 1);
+        assert!(with_nodata.contains(&9999.0));
+        assert!(with_nodata.contains(&1.0));
+        assert!(without.contains(&0.0));
+        assert!(!without.contains(&9999.0));
+    }
+
+    #[test]
+    fn test_nodata_pixels_are_not_interpolated() {
+        let mut values = [10.0; 64];
+        values[..32].fill(255.0);
+        let input = Builder::new(8, 8, GEOREF, 4326)
+            .band(4, Some(255.0), &values)
+            .finish();
+        let (forward, inverse) = wgs2gcj();
+        let out = warp_raster(&input, forward, inverse, 990001, Resampling::Bilinear).unwrap();
+        assert!(pixels(&out, 0).iter().all(|v| *v == 255.0 || *v == 10.0));
+    }
+
+    #[test]
+    fn test_integer_pixels_round_and_clamp() {
+        let mut out = [0u8; 2];
+        PixelType::UInt8.write(300.0, &mut out);
+        assert_eq!(out[0], 255);
+        PixelType::Int8.write(-3.6, &mut out);
+        assert_eq!(out[0] as i8, -4);
+        PixelType::UInt4.write(7.4, &mut out);
+        assert_eq!(out[0], 7);
+        PixelType::Int16.write(-40000.0, &mut out);
+        assert_eq!(i16::from_ne_bytes(out), -32768);
+    }
+
+    #[test]
+    fn test_rejects_offline_bands_and_truncation() {
+        let mut input = Builder::new(2, 2, GEOREF, 4326)
+            .band(4, None, &[1.0; 4])
+            .finish();
+        let (forward, inverse) = wgs2gcj();
+        assert_eq!(
+            warp_raster(&input[..68], forward, inverse, 990001, Resampling::Nearest),
+            Err(RasterError::UnexpectedEof)
+        );
+        input[HEADER_SIZE] |= BANDTYPE_FLAG_OFFDB;
+        assert_eq!(
+            warp_raster(&input, forward, inverse, 990001, Resampling::Nearest),
+            Err(RasterError::OfflineBand(1))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::ewkb::EwkbError;
+use crate::geojson::GeoJsonError;
+use crate::gserialized::GserializedError;
+use crate::jenks::JenksError;
+use crate::options::InvalidOption;
+#[cfg(feature = "proj")]
+use crate::proj::ProjError;
+use crate::raster::{RasterError, UnknownResampling};
+use crate::srid::InvalidSrids;
+use crate::wkt::WktError;
+use crate::zone::UnknownOffsetZone;
+
+// The SQLSTATEs the extension raises errors with. The mapping from the
+// library's error types lives here, away from pgrx, so it can be tested
+// without a server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlState {
+    InvalidParameterValue,
+    InvalidBinaryRepresentation,
+    InvalidTextRepresentation,
+    DatatypeMismatch,
+    FeatureNotSupported,
+    ProgramLimitExceeded,
+    UndefinedFunction,
+    UndefinedObject,
+    DataCorrupted,
+    Internal,
+}
+
+impl SqlState {
+    pub fn code(self) -> &'static str {
+        match self {
+            SqlState::InvalidParameterValue => "22023",
+            SqlState::InvalidBinaryRepresentation => "22P03",
+            SqlState::InvalidTextRepresentation => "22P02",
+            SqlState::DatatypeMismatch => "42804",
+            SqlState::FeatureNotSupported => "0A000",
+            SqlState::ProgramLimitExceeded => "54000",
+            SqlState::UndefinedFunction => "42883",
+            SqlState::UndefinedObject => "42704",
+            SqlState::DataCorrupted => "XX001",
+            SqlState::Internal => "XX000",
+        }
+    }
+}
+
+pub trait HasSqlState {
+    fn sqlstate(&self) -> SqlState;
+}
+
+// Raw EWKB comes from the caller, so any problem reading it is bad input.
+impl HasSqlState for EwkbError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            EwkbError::TooManyPoints { .. } => SqlState::ProgramLimitExceeded,
+            _ => SqlState::InvalidBinaryRepresentation,
+        }
+    }
+}
+
+impl HasSqlState for WktError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            WktError::UnsupportedType { .. } => SqlState::FeatureNotSupported,
+            _ => SqlState::InvalidTextRepresentation,
+        }
+    }
+}
+
+impl HasSqlState for GeoJsonError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            GeoJsonError::UnsupportedType { .. } => SqlState::FeatureNotSupported,
+            _ => SqlState::InvalidTextRepresentation,
+        }
+    }
+}
+
+// GSERIALIZED values were built by PostGIS, so a short or oversized buffer
+// means the stored value is damaged rather than the query being wrong.
+impl HasSqlState for GserializedError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            GserializedError::InvalidSrid(_) => SqlState::InvalidParameterValue,
+            GserializedError::UnsupportedType(_) => SqlState::FeatureNotSupported,
+            GserializedError::UnexpectedEof | GserializedError::TrailingData(_) => {
+                SqlState::DataCorrupted
+            }
+            GserializedError::TooManyPoints => SqlState::ProgramLimitExceeded,
+        }
+    }
+}
+
+impl HasSqlState for RasterError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            RasterError::UnsupportedVersion(_)
+            | RasterError::UnsupportedPixelType(_)
+            | RasterError::OfflineBand(_) => SqlState::FeatureNotSupported,
+            RasterError::DegenerateGeoreference => SqlState::InvalidParameterValue,
+            RasterError::UnexpectedEof | RasterError::TrailingData(_) => SqlState::DataCorrupted,
+        }
+    }
+}
+
+impl HasSqlState for JenksError {
+    fn sqlstate(&self) -> SqlState {
+        match self {
+            JenksError::InvalidBreaks | JenksError::NonFiniteValue(_) => {
+                SqlState::InvalidParameterValue
+            }
+            JenksError::CountOverflow => SqlState::ProgramLimitExceeded,
+            JenksError::Internal(_) => SqlState::Internal,
+        }
+    }
+}
+
+#[cfg(feature = "proj")]
+impl HasSqlState for ProjError {
+    fn sqlstate(&self) -> SqlState {
+        SqlState::Internal
+    }
+}
+
+macro_rules! invalid_parameter {
+    ($($ty:ty),*) => {
+        $(
+            impl HasSqlState for $ty {
+                fn sqlstate(&self) -> SqlState {
+                    SqlState::InvalidParameterValue
+                }
+            }
+        )*
+    };
+}
+
+invalid_parameter!(
+    InvalidOption,
+    InvalidSrids,
+    UnknownOffsetZone,
+    UnknownResampling
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_by_error_source() {
+        assert_eq!(
+            EwkbError::UnexpectedEof { offset: 0 }.sqlstate().code(),
+            "22P03"
+        );
+        assert_eq!(
+            EwkbError::RingNotClosed { offset: 9 }.sqlstate(),
+            SqlState::InvalidBinaryRepresentation
+        );
+        assert_eq!(
+            GeoJsonError::TooFewOrdinates { offset: 4 }
+                .sqlstate()
+                .code(),
+            "22P02"
+        );
+        assert_eq!(
+            WktError::UnsupportedType {
+                offset: 0,
+                name: "CIRCLE".into()
+            }
+            .sqlstate(),
+            SqlState::FeatureNotSupported
+        );
+        assert_eq!(GserializedError::TrailingData(3).sqlstate().code(), "XX001");
+        assert_eq!(GserializedError::InvalidSrid(-1).sqlstate().code(), "22023");
+        assert_eq!(
+            EwkbError::TooManyPoints { offset: 0 }.sqlstate().code(),
+            "54000"
+        );
+        assert_eq!(GserializedError::TooManyPoints.sqlstate().code(), "54000");
+        assert_eq!(RasterError::OfflineBand(1).sqlstate().code(), "0A000");
+        assert_eq!(JenksError::InvalidBreaks.sqlstate().code(), "22023");
+        assert_eq!(JenksError::CountOverflow.sqlstate().code(), "54000");
+        assert_eq!(JenksError::Internal("bug").sqlstate().code(), "XX000");
+        assert_eq!(
+            "sideways"
+                .parse::<crate::options::MMode>()
+                .unwrap_err()
+                .sqlstate(),
+            SqlState::InvalidParameterValue
+        );
+    }
+}
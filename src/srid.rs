@@ -0,0 +1,480 @@
+use std::ffi::CStr;
+
+use crate::coord::{Projection, TransformKind};
+
+pub const SRID_WGS84: i32 = 4326;
+pub const SRID_WEB_MERCATOR: i32 = 3857;
+pub const SRID_CGCS2000: i32 = 4490;
+// WGS 84 / UTM zones 1N..60N and 1S..60S.
+const SRID_UTM_NORTH: i32 = 32601;
+const SRID_UTM_NORTH_LAST: i32 = 32660;
+const SRID_UTM_SOUTH: i32 = 32701;
+const SRID_UTM_SOUTH_LAST: i32 = 32760;
+
+// auth_name of the spatial_ref_sys rows describing the custom SRIDs.
+pub const AUTH_NAME: &str = "EVILTRANSFORM";
+
+const PROJ4_LNGLAT: &str = "+proj=longlat +datum=WGS84 +no_defs";
+const PROJ4_WEB_MERCATOR: &str = "+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs";
+// The ellipsoid Baidu's polynomials are usually fitted to.
+const PROJ4_BAIDU_MERCATOR: &str = "+proj=merc +a=6378206 +b=6356584.314245179 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs";
+
+const WGS84_BODY: &str = r#"DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]]"#;
+const BAIDU_BODY: &str = r#"DATUM["Baidu",SPHEROID["Baidu",6378206,294.9911525089081]],PRIMEM["Greenwich",0],UNIT["degree",0.0174532925199433]"#;
+
+// The datum a custom SRID's offsets are computed in. BD09MC is its own datum
+// because Baidu's polynomials are part of the TransformKind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Datum {
+    Wgs84,
+    Gcj02,
+    Bd09,
+    Bd09Mc,
+}
+
+/// Error for a `CustomSrids` setting that is unknown, repeated or reserved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSrids(pub(crate) String);
+
+impl std::fmt::Display for InvalidSrids {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSrids {}
+
+// A spatial_ref_sys row for a custom SRID. The offsets cannot be expressed
+// in WKT or PROJ, so each row describes the undistorted system it is closest
+// to; tools use it to label and roughly place the data, not to convert it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialRef {
+    pub srid: i32,
+    pub srtext: String,
+    pub proj4text: String,
+}
+
+fn geographic(srid: i32, name: &str) -> SpatialRef {
+    SpatialRef {
+        srid,
+        srtext: format!(
+            r#"GEOGCS["{name} (approximated by WGS 84)",{WGS84_BODY},AUTHORITY["{AUTH_NAME}","{srid}"]]"#
+        ),
+        proj4text: PROJ4_LNGLAT.to_string(),
+    }
+}
+
+fn mercator(srid: i32, name: &str, geogcs: (&str, &str), proj4text: &str) -> SpatialRef {
+    let (geog_name, geog_body) = geogcs;
+    SpatialRef {
+        srid,
+        srtext: format!(
+            r#"PROJCS["{name}",GEOGCS["{geog_name}",{geog_body}],PROJECTION["Mercator_1SP"],PARAMETER["central_meridian",0],PARAMETER["scale_factor",1],PARAMETER["false_easting",0],PARAMETER["false_northing",0],UNIT["metre",1,AUTHORITY["EPSG","9001"]],AXIS["Easting",EAST],AXIS["Northing",NORTH],EXTENSION["PROJ4","{proj4text}"],AUTHORITY["{AUTH_NAME}","{srid}"]]"#
+        ),
+        proj4text: proj4text.to_string(),
+    }
+}
+
+// A custom coordinate system: a datum with offsets in a projection, under an
+// SRID the `guc_name` setting can move. Adding a system is one entry here;
+// only a new datum also needs a `Datum` variant, its `TransformKind`s and the
+// `DATUM_EDGES` between them.
+// `description` and `spatial_ref` are read by the extension only.
+#[cfg_attr(not(feature = "extension"), allow(dead_code))]
+pub struct CustomSystem {
+    pub guc_name: &'static CStr,
+    pub description: &'static CStr,
+    pub default_srid: i32,
+    pub datum: Datum,
+    pub projection: Projection,
+    // Names the text overloads accept, in upper case.
+    pub names: &'static [&'static str],
+    pub spatial_ref: fn(i32) -> SpatialRef,
+}
+
+pub const CUSTOM_SYSTEMS: &[CustomSystem] = &[
+    CustomSystem {
+        guc_name: c"eviltransform.gcj02_srid",
+        description: c"SRID standing for GCJ02 longitude/latitude.",
+        default_srid: 990001,
+        datum: Datum::Gcj02,
+        projection: Projection::LngLat,
+        names: &[
+            "GCJ02", "GCJ-02", "TENCENT", "QQ", "AMAP", "GAODE", "AUTONAVI",
+        ],
+        spatial_ref: |srid| geographic(srid, "GCJ-02"),
+    },
+    CustomSystem {
+        guc_name: c"eviltransform.bd09_srid",
+        description: c"SRID standing for BD09 longitude/latitude.",
+        default_srid: 990002,
+        datum: Datum::Bd09,
+        projection: Projection::LngLat,
+        names: &["BD09", "BD-09", "BAIDU"],
+        spatial_ref: |srid| geographic(srid, "BD-09"),
+    },
+    CustomSystem {
+        guc_name: c"eviltransform.bd09mc_srid",
+        description: c"SRID standing for Baidu Mercator (BD09MC) metres.",
+        default_srid: 990003,
+        datum: Datum::Bd09Mc,
+        projection: Projection::LngLat,
+        names: &["BD09MC", "BD-09MC", "BD09_MC", "BAIDU_MERCATOR"],
+        spatial_ref: |srid| {
+            mercator(
+                srid,
+                "BD-09 / Baidu Mercator (approximation)",
+                ("Baidu", BAIDU_BODY),
+                PROJ4_BAIDU_MERCATOR,
+            )
+        },
+    },
+    CustomSystem {
+        guc_name: c"eviltransform.gcj02_mercator_srid",
+        description: c"SRID standing for GCJ02 in Web Mercator.",
+        default_srid: 990004,
+        datum: Datum::Gcj02,
+        projection: Projection::WebMercator,
+        names: &["GCJ02/3857", "GCJ02_3857", "GCJ02_WEBMERCATOR"],
+        spatial_ref: |srid| {
+            mercator(
+                srid,
+                "GCJ-02 / Pseudo-Mercator (approximated by WGS 84)",
+                ("WGS 84", WGS84_BODY),
+                PROJ4_WEB_MERCATOR,
+            )
+        },
+    },
+    CustomSystem {
+        guc_name: c"eviltransform.bd09_mercator_srid",
+        description: c"SRID standing for BD09 in Web Mercator.",
+        default_srid: 990005,
+        datum: Datum::Bd09,
+        projection: Projection::WebMercator,
+        names: &["BD09/3857", "BD09_3857", "BD09_WEBMERCATOR"],
+        spatial_ref: |srid| {
+            mercator(
+                srid,
+                "BD-09 / Pseudo-Mercator (approximated by WGS 84)",
+                ("WGS 84", WGS84_BODY),
+                PROJ4_WEB_MERCATOR,
+            )
+        },
+    },
+];
+
+/// The SRID numbers standing for the custom coordinate systems.
+///
+/// They default to `990001..=990005` and are moved by the name of the
+/// matching `eviltransform.*_srid` setting.
+///
+/// ```
+/// use pg_eviltransform::CustomSrids;
+///
+/// let mut srids = CustomSrids::default();
+/// srids.set("eviltransform.gcj02_srid", 910001)?;
+/// assert_eq!(srids.get("eviltransform.gcj02_srid"), Some(910001));
+/// assert_eq!(srids.parse("GCJ02"), Some(910001));
+/// assert!(srids.set("eviltransform.bd09_srid", 910001).is_ok());
+/// assert!(srids.validate().is_err());
+/// # Ok::<(), pg_eviltransform::InvalidSrids>(())
+/// ```
+// One SRID per entry of CUSTOM_SYSTEMS, in the same order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CustomSrids([i32; CUSTOM_SYSTEMS.len()]);
+
+impl CustomSrids {
+    /// `990001..=990005`.
+    pub const DEFAULT: Self = {
+        let mut srids = [0; CUSTOM_SYSTEMS.len()];
+        let mut i = 0;
+        while i < srids.len() {
+            srids[i] = CUSTOM_SYSTEMS[i].default_srid;
+            i += 1;
+        }
+        Self(srids)
+    };
+
+    #[cfg_attr(not(feature = "extension"), allow(dead_code))]
+    pub(crate) fn new(srids: [i32; CUSTOM_SYSTEMS.len()]) -> Self {
+        Self(srids)
+    }
+
+    // Each system with its configured SRID.
+    pub(crate) fn systems(&self) -> impl Iterator<Item = (&'static CustomSystem, i32)> + '_ {
+        CUSTOM_SYSTEMS.iter().zip(self.0.iter().copied())
+    }
+
+    fn position(guc_name: &str) -> Option<usize> {
+        CUSTOM_SYSTEMS
+            .iter()
+            .position(|system| system.guc_name.to_bytes() == guc_name.as_bytes())
+    }
+
+    /// The SRID of the system set by `guc_name`, e.g.
+    /// `"eviltransform.gcj02_srid"`.
+    pub fn get(&self, guc_name: &str) -> Option<i32> {
+        Self::position(guc_name).map(|i| self.0[i])
+    }
+
+    /// Moves the system set by `guc_name` to `srid`. Call `validate` once all
+    /// systems are set.
+    pub fn set(&mut self, guc_name: &str, srid: i32) -> Result<(), InvalidSrids> {
+        let i = Self::position(guc_name)
+            .ok_or_else(|| InvalidSrids(format!("unknown setting {guc_name:?}")))?;
+        self.0[i] = srid;
+        Ok(())
+    }
+
+    /// Checks that the SRIDs are positive, distinct and none of `4326`,
+    /// `3857`, `4490` or a WGS 84 UTM SRID, which the routing treats
+    /// specially.
+    pub fn validate(&self) -> Result<(), InvalidSrids> {
+        let systems: Vec<_> = self.systems().collect();
+        for (i, &(system, srid)) in systems.iter().enumerate() {
+            let name = system.guc_name.to_string_lossy();
+            if standard_frame(srid).is_some() || srid <= 0 {
+                return Err(InvalidSrids(format!("{name} cannot be {srid}")));
+            }
+            if let Some(&(other, _)) = systems[..i].iter().find(|(_, s)| *s == srid) {
+                return Err(InvalidSrids(format!(
+                    "{} and {name} are both {srid}",
+                    other.guc_name.to_string_lossy()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn frame(&self, srid: i32) -> Option<(Datum, Projection)> {
+        match self.systems().find(|&(_, s)| s == srid) {
+            Some((system, _)) => Some((system.datum, system.projection)),
+            None => standard_frame(srid),
+        }
+    }
+
+    #[cfg_attr(not(feature = "extension"), allow(dead_code))]
+    pub(crate) fn spatial_refs(&self) -> Vec<SpatialRef> {
+        self.systems()
+            .map(|(system, srid)| (system.spatial_ref)(srid))
+            .collect()
+    }
+
+    pub(crate) fn all(&self) -> [i32; CUSTOM_SYSTEMS.len()] {
+        self.0
+    }
+
+    /// Whether `srid` is one of the custom SRIDs.
+    pub fn is_custom(&self, srid: i32) -> bool {
+        self.0.contains(&srid)
+    }
+
+    /// Resolves a custom coordinate system name such as `"GCJ02"` or
+    /// `"Baidu"`, or the number / `EPSG:` form of a custom SRID, as the text
+    /// overloads of `ST_EvilTransform` do. `"CGCS2000"` resolves to `4490`.
+    pub fn parse(&self, spec: &str) -> Option<i32> {
+        let spec = spec.trim().to_ascii_uppercase();
+        if spec == "CGCS2000" {
+            return Some(SRID_CGCS2000);
+        }
+        let named = self
+            .systems()
+            .find(|(system, _)| system.names.contains(&spec.as_str()))
+            .map(|(_, srid)| srid);
+        named.or_else(|| {
+            spec.strip_prefix("EPSG:")
+                .unwrap_or(&spec)
+                .parse::<i32>()
+                .ok()
+                .filter(|&srid| self.is_custom(srid))
+        })
+    }
+}
+
+impl Default for CustomSrids {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// Standard SRIDs whose projection math is done in Rust when the other side of
+// a transform is a custom SRID. CGCS2000 is treated as WGS84; the two differ
+// by centimetres, far below the error of the GCJ02/BD09 offsets themselves.
+fn standard_frame(srid: i32) -> Option<(Datum, Projection)> {
+    let projection = match srid {
+        SRID_WGS84 | SRID_CGCS2000 => Projection::LngLat,
+        SRID_WEB_MERCATOR => Projection::WebMercator,
+        SRID_UTM_NORTH..=SRID_UTM_NORTH_LAST => Projection::Utm {
+            zone: (srid - SRID_UTM_NORTH + 1) as u8,
+            south: false,
+        },
+        SRID_UTM_SOUTH..=SRID_UTM_SOUTH_LAST => Projection::Utm {
+            zone: (srid - SRID_UTM_SOUTH + 1) as u8,
+            south: true,
+        },
+        _ => return None,
+    };
+    Some((Datum::Wgs84, projection))
+}
+
+// A direct conversion between two datums. Every datum declares edges to and
+// from WGS84; the other edges are fused shortcuts the planner prefers over
+// going through WGS84. `exact_kind` is used for the directions that have an
+// iterative inverse and equals `kind` otherwise.
+pub struct DatumEdge {
+    pub from: Datum,
+    pub to: Datum,
+    pub kind: TransformKind,
+    pub exact_kind: TransformKind,
+}
+
+const fn edge(from: Datum, to: Datum, kind: TransformKind, exact_kind: TransformKind) -> DatumEdge {
+    DatumEdge {
+        from,
+        to,
+        kind,
+        exact_kind,
+    }
+}
+
+pub const DATUM_EDGES: &[DatumEdge] = {
+    use Datum::*;
+    use TransformKind as K;
+    &[
+        edge(Wgs84, Gcj02, K::Wgs2Gcj, K::Wgs2Gcj),
+        edge(Gcj02, Wgs84, K::Gcj2Wgs, K::Gcj2WgsExact),
+        edge(Wgs84, Bd09, K::Wgs2Bd, K::Wgs2Bd),
+        edge(Bd09, Wgs84, K::Bd2Wgs, K::Bd2WgsExact),
+        edge(Wgs84, Bd09Mc, K::Wgs2Bdmc, K::Wgs2Bdmc),
+        edge(Bd09Mc, Wgs84, K::Bdmc2Wgs, K::Bdmc2WgsExact),
+        edge(Gcj02, Bd09, K::Gcj2Bd, K::Gcj2Bd),
+        edge(Bd09, Gcj02, K::Bd2Gcj, K::Bd2GcjExact),
+        edge(Gcj02, Bd09Mc, K::Gcj2Bdmc, K::Gcj2Bdmc),
+        edge(Bd09Mc, Gcj02, K::Bdmc2Gcj, K::Bdmc2GcjExact),
+        edge(Bd09, Bd09Mc, K::Bd2Bdmc, K::Bd2Bdmc),
+        edge(Bd09Mc, Bd09, K::Bdmc2Bd, K::Bdmc2Bd),
+    ]
+};
+
+// The single-pass transform between two datums, if they share an edge.
+pub fn datum_kind(src: Datum, dst: Datum, exact: bool) -> Option<TransformKind> {
+    if src == dst {
+        return Some(TransformKind::Identity);
+    }
+    DATUM_EDGES
+        .iter()
+        .find(|e| e.from == src && e.to == dst)
+        .map(|e| if exact { e.exact_kind } else { e.kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved() -> CustomSrids {
+        let mut srids = CustomSrids::default();
+        srids.set("eviltransform.gcj02_srid", 991001).unwrap();
+        srids
+    }
+
+    #[test]
+    fn test_parse_names_follow_configuration() {
+        let srids = moved();
+        assert_eq!(srids.parse(" gcj-02 "), Some(991001));
+        assert_eq!(srids.parse("Tencent"), Some(991001));
+        assert_eq!(srids.parse("BD09"), Some(990002));
+        assert_eq!(srids.parse("gcj02/3857"), Some(990004));
+        assert_eq!(srids.parse("CGCS2000"), Some(SRID_CGCS2000));
+        assert_eq!(srids.parse("EPSG:991001"), Some(991001));
+        assert_eq!(srids.parse("990001"), None);
+        assert_eq!(srids.parse("EPSG:3857"), None);
+    }
+
+    #[test]
+    fn test_frame_follows_configuration() {
+        let srids = moved();
+        assert_eq!(
+            srids.frame(991001),
+            Some((Datum::Gcj02, Projection::LngLat))
+        );
+        assert_eq!(srids.frame(990001), None);
+        assert_eq!(
+            srids.frame(SRID_WEB_MERCATOR),
+            Some((Datum::Wgs84, Projection::WebMercator))
+        );
+        assert_eq!(
+            srids.frame(32750),
+            Some((
+                Datum::Wgs84,
+                Projection::Utm {
+                    zone: 50,
+                    south: true
+                }
+            ))
+        );
+        assert_eq!(srids.frame(32661), None);
+        assert!(!srids.is_custom(SRID_WGS84));
+        assert!(srids.is_custom(990005));
+    }
+
+    #[test]
+    fn test_spatial_refs_follow_configuration() {
+        let refs = moved().spatial_refs();
+        assert_eq!(
+            refs.iter().map(|r| r.srid).collect::<Vec<_>>(),
+            [991001, 990002, 990003, 990004, 990005]
+        );
+        assert_eq!(
+            refs[0].srtext,
+            r#"GEOGCS["GCJ-02 (approximated by WGS 84)",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EVILTRANSFORM","991001"]]"#
+        );
+        assert!(
+            refs[4]
+                .srtext
+                .starts_with(r#"PROJCS["BD-09 / Pseudo-Mercator"#)
+        );
+        assert!(
+            refs[4]
+                .srtext
+                .ends_with(r#"AUTHORITY["EVILTRANSFORM","990005"]]"#)
+        );
+        assert!(refs[2].proj4text.contains("+a=6378206"));
+    }
+
+    #[test]
+    fn test_custom_systems_are_distinct() {
+        assert_eq!(CustomSrids::DEFAULT.validate(), Ok(()));
+        for (i, system) in CUSTOM_SYSTEMS.iter().enumerate() {
+            for other in &CUSTOM_SYSTEMS[..i] {
+                assert_ne!(other.guc_name, system.guc_name);
+                assert_ne!(
+                    (other.datum, other.projection),
+                    (system.datum, system.projection)
+                );
+                assert!(other.names.iter().all(|name| !system.names.contains(name)));
+            }
+            let srid = system.default_srid;
+            assert_eq!((system.spatial_ref)(srid).srid, srid);
+            assert_eq!(
+                CustomSrids::DEFAULT.frame(srid),
+                Some((system.datum, system.projection))
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_collisions() {
+        assert_eq!(CustomSrids::default().validate(), Ok(()));
+        let mut clash = CustomSrids::default();
+        clash.set("eviltransform.bd09_srid", 990001).unwrap();
+        assert_eq!(
+            clash.validate().unwrap_err().to_string(),
+            "eviltransform.gcj02_srid and eviltransform.bd09_srid are both 990001"
+        );
+        let mut standard = CustomSrids::default();
+        standard.set("eviltransform.bd09mc_srid", 3857).unwrap();
+        assert!(standard.validate().is_err());
+        assert!(standard.set("eviltransform.wgs84_srid", 1).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::coord::Transform;
+use crate::gserialized;
+
+// Transformed ordinates keep the input's decimal places but never drop
+// below this many, so integer test coordinates don't lose the offset.
+const MIN_DECIMALS: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WktError {
+    UnexpectedEnd,
+    Unexpected { offset: usize, found: char },
+    UnsupportedType { offset: usize, name: String },
+    InvalidNumber { offset: usize },
+    TooFewOrdinates { offset: usize },
+    TrailingData { offset: usize },
+}
+
+impl std::fmt::Display for WktError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WktError::UnexpectedEnd => write!(f, "unexpected end of WKT"),
+            WktError::Unexpected { offset, found } => {
+                write!(f, "unexpected {found:?} at WKT offset {offset}")
+            }
+            WktError::UnsupportedType { offset, name } => {
+                write!(
+                    f,
+                    "unsupported WKT geometry type {name:?} at offset {offset}"
+                )
+            }
+            WktError::InvalidNumber { offset } => {
+                write!(f, "invalid number at WKT offset {offset}")
+            }
+            WktError::TooFewOrdinates { offset } => {
+                write!(
+                    f,
+                    "WKT coordinate at offset {offset} has fewer than 2 ordinates"
+                )
+            }
+            WktError::TrailingData { offset } => {
+                write!(f, "WKT has trailing data at offset {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WktError {}
+
+// Writes a transformed ordinate in the style of the token it replaces.
+// Untouched ordinates, such as points outside the offset zone, keep their
+// original text.
+pub(crate) fn format_like(token: &str, old: f64, new: f64) -> String {
+    if new == old {
+        return token.to_string();
+    }
+    if token.contains(['e', 'E']) {
+        return new.to_string();
+    }
+    let decimals = token.split_once('.').map_or(0, |(_, frac)| frac.len());
+    format!("{new:.*}", decimals.max(MIN_DECIMALS))
+}
+
+// The length of the number at the start of `s`, in JSON/WKT syntax with an
+// optional leading `+` and bare leading or trailing dots.
+pub(crate) fn number_len(s: &[u8]) -> usize {
+    let mut i = 0;
+    if matches!(s.first(), Some(b'+' | b'-')) {
+        i += 1;
+    }
+    while s.get(i).is_some_and(|b| b.is_ascii_digit() || *b == b'.') {
+        i += 1;
+    }
+    if matches!(s.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(s.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if s.get(j).is_some_and(u8::is_ascii_digit) {
+            while s.get(j).is_some_and(u8::is_ascii_digit) {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    i
+}
+
+struct Rewriter<'a> {
+    src: &'a str,
+    pos: usize,
+    copied: usize,
+    out: String,
+    transform: Transform,
+}
+
+impl<'a> Rewriter<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&self) -> WktError {
+        match self.src[self.pos..].chars().next() {
+            Some(found) => WktError::Unexpected {
+                offset: self.pos,
+                found,
+            },
+            None => WktError::UnexpectedEnd,
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), WktError> {
+        if self.peek() != Some(byte) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn word(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn number(&mut self) -> Result<(&'a str, f64), WktError> {
+        let start = self.pos;
+        let len = number_len(&self.src.as_bytes()[start..]);
+        let token = &self.src[start..start + len];
+        let value = token
+            .parse::<f64>()
+            .map_err(|_| WktError::InvalidNumber { offset: start })?;
+        self.pos += len;
+        Ok((token, value))
+    }
+
+    fn replace(&mut self, start: usize, token: &str, old: f64, new: f64) {
+        self.out.push_str(&self.src[self.copied..start]);
+        self.out.push_str(&format_like(token, old, new));
+        self.copied = start + token.len();
+    }
+
+    fn geometry(&mut self) -> Result<(), WktError> {
+        self.skip_ws();
+        let start = self.pos;
+        let name = self.word();
+        let upper = name.to_ascii_uppercase();
+        if !["ZM", "Z", "M", ""]
+            .iter()
+            .any(|suffix| upper.strip_suffix(suffix).is_some_and(is_supported))
+        {
+            return Err(match name {
+                "" => self.unexpected(),
+                _ => WktError::UnsupportedType {
+                    offset: start,
+                    name: name.to_string(),
+                },
+            });
+        }
+        self.skip_ws();
+        let mark = self.pos;
+        let word = self.word();
+        if !["Z", "M", "ZM"]
+            .iter()
+            .any(|d| word.eq_ignore_ascii_case(d))
+        {
+            self.pos = mark;
+        }
+        self.skip_ws();
+        let mark = self.pos;
+        if self.word().eq_ignore_ascii_case("EMPTY") {
+            return Ok(());
+        }
+        self.pos = mark;
+        self.list()
+    }
+
+    fn list(&mut self) -> Result<(), WktError> {
+        self.expect(b'(')?;
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(b'(') => self.list()?,
+                // `MULTIPOINT(EMPTY, ...)` style empty members.
+                Some(b) if b.is_ascii_alphabetic() => {
+                    let mark = self.pos;
+                    if !self.word().eq_ignore_ascii_case("EMPTY") {
+                        self.pos = mark;
+                        self.geometry()?;
+                    }
+                }
+                Some(_) => self.coordinate()?,
+                None => return Err(WktError::UnexpectedEnd),
+            }
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b')') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn coordinate(&mut self) -> Result<(), WktError> {
+        let start = self.pos;
+        let (x_token, x) = self.number()?;
+        if !self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            return Err(WktError::TooFewOrdinates { offset: start });
+        }
+        self.skip_ws();
+        let y_start = self.pos;
+        let (y_token, y) = self.number()?;
+        // Z and M are kept as written.
+        loop {
+            let mark = self.pos;
+            self.skip_ws();
+            if self.pos == mark || matches!(self.peek(), Some(b',' | b')') | None) {
+                self.pos = mark;
+                break;
+            }
+            self.number()?;
+        }
+        let (lat, lng) = self.transform.apply(y, x);
+        self.replace(start, x_token, x, lng);
+        self.replace(y_start, y_token, y, lat);
+        Ok(())
+    }
+}
+
+fn is_supported(name: &str) -> bool {
+    (1..=17)
+        .filter_map(gserialized::type_name)
+        .any(|known| known.eq_ignore_ascii_case(name))
+}
+
+/// Transforms the coordinates of a WKT or EWKT geometry, keeping everything
+/// else as written.
+///
+/// Transformed x and y values keep the input's decimal places, with at
+/// least six; Z and M values, whitespace, keywords and any `SRID=...;`
+/// prefix are copied unchanged. Curved and surface types are accepted, as in
+/// EWKB.
+///
+/// ```
+/// use pg_eviltransform::TransformKind;
+/// use pg_eviltransform::transform_wkt;
+///
+/// let wkt = transform_wkt("SRID=4326;POINT Z (120.000000 30.000000 12.5)", TransformKind::Wgs2Gcj)?;
+/// assert_eq!(wkt, "SRID=4326;POINT Z (120.004661 29.997534 12.5)");
+/// # Ok::<(), pg_eviltransform::wkt::WktError>(())
+/// ```
+pub fn transform_wkt(wkt: &str, transform: impl Into<Transform>) -> Result<String, WktError> {
+    let mut rewriter = Rewriter {
+        src: wkt,
+        pos: 0,
+        copied: 0,
+        out: String::with_capacity(wkt.len() + 16),
+        transform: transform.into(),
+    };
+    rewriter.skip_ws();
+    let mark = rewriter.pos;
+    if rewriter.word().eq_ignore_ascii_case("SRID") {
+        rewriter.expect(b'=')?;
+        rewriter.number()?;
+        rewriter.expect(b';')?;
+    } else {
+        rewriter.pos = mark;
+    }
+    rewriter.geometry()?;
+    rewriter.skip_ws();
+    if rewriter.pos != wkt.len() {
+        return Err(WktError::TrailingData {
+            offset: rewriter.pos,
+        });
+    }
+    rewriter.out.push_str(&wkt[rewriter.copied..]);
+    Ok(rewriter.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::{TransformKind, apply};
+
+    fn expected(lng: f64, lat: f64, decimals: usize) -> String {
+        let (lat, lng) = apply(TransformKind::Wgs2Bd, lat, lng);
+        format!("{lng:.decimals$} {lat:.decimals$}")
+    }
+
+    #[test]
+    fn test_keeps_formatting() {
+        let wkt = "multipolygon zm (((120.1234567 30 1 2,121 30 3 4, 121 31 5 6,120.1234567 30 1 2)),EMPTY)";
+        let out = transform_wkt(wkt, TransformKind::Wgs2Bd).unwrap();
+        let (lat, lng) = apply(TransformKind::Wgs2Bd, 30.0, 120.1234567);
+        let first = format!("{lng:.7} {lat:.6}");
+        assert_eq!(
+            out,
+            format!(
+                "multipolygon zm ((({first} 1 2,{} 3 4, {} 5 6,{first} 1 2)),EMPTY)",
+                expected(121.0, 30.0, 6),
+                expected(121.0, 31.0, 6),
+            )
+        );
+    }
+
+    #[test]
+    fn test_covers_ewkb_types() {
+        let wkt = "GEOMETRYCOLLECTION(POINT EMPTY,MULTIPOINT(120 30,(121 31)),\
+            COMPOUNDCURVE(CIRCULARSTRING(120 30,121 31,122 30),(122 30,123 30)),\
+            CURVEPOLYGON(CIRCULARSTRING(120 30,121 31,120 30)),TIN(((120 30,121 30,121 31,120 30))),\
+            POLYHEDRALSURFACE Z (((120 30 0,121 30 0,121 31 0,120 30 0))),TRIANGLEM((120 30 1,121 30 1,121 31 1,120 30 1)))";
+        let out = transform_wkt(wkt, TransformKind::Wgs2Bd).unwrap();
+        let p = expected(120.0, 30.0, 6);
+        assert!(out.starts_with(&format!(
+            "GEOMETRYCOLLECTION(POINT EMPTY,MULTIPOINT({p},({})),",
+            expected(121.0, 31.0, 6)
+        )));
+        assert_eq!(out.matches(&p).count(), 10, "{out}");
+        assert!(out.ends_with(&format!(",{p} 1)))")));
+    }
+
+    #[test]
+    fn test_passthrough_and_errors() {
+        let wkt = "LINESTRING(-120.5 30, 1e2 -5)";
+        assert_eq!(transform_wkt(wkt, TransformKind::Wgs2Gcj).unwrap(), wkt);
+        assert_eq!(
+            transform_wkt("POINT(120)", TransformKind::Wgs2Gcj),
+            Err(WktError::TooFewOrdinates { offset: 6 })
+        );
+        assert_eq!(
+            transform_wkt("POINT(120 30", TransformKind::Wgs2Gcj),
+            Err(WktError::UnexpectedEnd)
+        );
+        assert_eq!(
+            transform_wkt("POINT(120 30) x", TransformKind::Wgs2Gcj),
+            Err(WktError::TrailingData { offset: 14 })
+        );
+        assert_eq!(
+            transform_wkt("POINT(120 3x)", TransformKind::Wgs2Gcj),
+            Err(WktError::Unexpected {
+                offset: 11,
+                found: 'x'
+            })
+        );
+    }
+}
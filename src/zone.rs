@@ -0,0 +1,371 @@
+use std::sync::OnceLock;
+
+use crate::boundary::{HAINAN, HONG_KONG, MACAU, MAINLAND, TAIWAN};
+
+/// Where the GCJ02/BD09 offsets apply; points outside are passed through.
+///
+/// New zones may be added in minor releases.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OffsetZone {
+    #[default]
+    Rectangle,
+    Border,
+    NeverSkip,
+    SkipHkMoTw,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownOffsetZone(pub String);
+
+impl std::fmt::Display for UnknownOffsetZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown offset zone {:?}, expected one of rectangle, border, never_skip, skip_hk_mo_tw",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownOffsetZone {}
+
+impl std::str::FromStr for OffsetZone {
+    type Err = UnknownOffsetZone;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rectangle" => Ok(Self::Rectangle),
+            "border" => Ok(Self::Border),
+            "never_skip" => Ok(Self::NeverSkip),
+            "skip_hk_mo_tw" => Ok(Self::SkipHkMoTw),
+            _ => Err(UnknownOffsetZone(s.to_owned())),
+        }
+    }
+}
+
+impl OffsetZone {
+    #[inline(always)]
+    pub fn contains(self, lat: f64, lng: f64) -> bool {
+        match self {
+            Self::Rectangle => in_rectangle(lat, lng),
+            Self::Border => in_border(lat, lng),
+            Self::NeverSkip => true,
+            Self::SkipHkMoTw => in_border_excluding_hk_mo_tw(lat, lng),
+        }
+    }
+}
+
+#[inline(always)]
+fn in_rectangle(lat: f64, lng: f64) -> bool {
+    (72.004..=137.8347).contains(&lng) && (0.8293..=55.8271).contains(&lat)
+}
+
+const GRID_CELLS: f64 = 128.0;
+
+fn ring_contains(ring: &[(f64, f64)], lat: f64, lng: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        if crosses_ray(ring[i], ring[j], lat, lng) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[inline(always)]
+fn crosses_ray((xi, yi): (f64, f64), (xj, yj): (f64, f64), lat: f64, lng: f64) -> bool {
+    (yi > lat) != (yj > lat) && lng < (xj - xi) * (lat - yi) / (yj - yi) + xi
+}
+
+#[derive(Clone, Copy)]
+enum Cell {
+    Inside,
+    Outside,
+    Boundary,
+}
+
+// A uniform grid over the ring's bbox. Cells no edge touches are answered
+// from a precomputed centre test; the rest fall back to ray casting over the
+// edges that overlap the cell's row.
+struct RingIndex {
+    ring: &'static [(f64, f64)],
+    min_lng: f64,
+    min_lat: f64,
+    max_lng: f64,
+    max_lat: f64,
+    cell_size: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    row_edges: Vec<Vec<u32>>,
+}
+
+impl RingIndex {
+    fn new(ring: &'static [(f64, f64)]) -> Self {
+        let (mut min_lng, mut min_lat) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_lng, mut max_lat) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &(lng, lat) in ring {
+            min_lng = min_lng.min(lng);
+            min_lat = min_lat.min(lat);
+            max_lng = max_lng.max(lng);
+            max_lat = max_lat.max(lat);
+        }
+        let cell_size = (max_lng - min_lng).max(max_lat - min_lat) / GRID_CELLS;
+        let cols = (((max_lng - min_lng) / cell_size).ceil() as usize).max(1);
+        let rows = (((max_lat - min_lat) / cell_size).ceil() as usize).max(1);
+
+        let mut index = Self {
+            ring,
+            min_lng,
+            min_lat,
+            max_lng,
+            max_lat,
+            cell_size,
+            cols,
+            rows,
+            cells: vec![Cell::Outside; cols * rows],
+            row_edges: vec![Vec::new(); rows],
+        };
+
+        let mut j = ring.len() - 1;
+        for i in 0..ring.len() {
+            let (a, b) = (ring[i], ring[j]);
+            let (col0, row0) = index.cell_of(a.1.min(b.1), a.0.min(b.0));
+            let (col1, row1) = index.cell_of(a.1.max(b.1), a.0.max(b.0));
+            for row in row0..=row1 {
+                index.row_edges[row].push(i as u32);
+                for col in col0..=col1 {
+                    index.cells[row * cols + col] = Cell::Boundary;
+                }
+            }
+            j = i;
+        }
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let cell = &mut index.cells[row * cols + col];
+                if matches!(cell, Cell::Boundary) {
+                    continue;
+                }
+                let lat = min_lat + (row as f64 + 0.5) * cell_size;
+                let lng = min_lng + (col as f64 + 0.5) * cell_size;
+                if ring_contains(ring, lat, lng) {
+                    *cell = Cell::Inside;
+                }
+            }
+        }
+        index
+    }
+
+    #[inline(always)]
+    fn cell_of(&self, lat: f64, lng: f64) -> (usize, usize) {
+        let col = ((lng - self.min_lng) / self.cell_size) as usize;
+        let row = ((lat - self.min_lat) / self.cell_size) as usize;
+        (col.min(self.cols - 1), row.min(self.rows - 1))
+    }
+
+    #[inline]
+    fn contains(&self, lat: f64, lng: f64) -> bool {
+        if !(self.min_lng..=self.max_lng).contains(&lng)
+            || !(self.min_lat..=self.max_lat).contains(&lat)
+        {
+            return false;
+        }
+        let (col, row) = self.cell_of(lat, lng);
+        match self.cells[row * self.cols + col] {
+            Cell::Inside => true,
+            Cell::Outside => false,
+            Cell::Boundary => {
+                let n = self.ring.len();
+                let mut inside = false;
+                for &i in &self.row_edges[row] {
+                    let i = i as usize;
+                    let j = if i == 0 { n - 1 } else { i - 1 };
+                    if crosses_ray(self.ring[i], self.ring[j], lat, lng) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+}
+
+struct BoundaryIndex {
+    mainland: RingIndex,
+    hainan: RingIndex,
+    taiwan: RingIndex,
+    hong_kong: RingIndex,
+    macau: RingIndex,
+}
+
+fn boundary() -> &'static BoundaryIndex {
+    static INDEX: OnceLock<BoundaryIndex> = OnceLock::new();
+    INDEX.get_or_init(|| BoundaryIndex {
+        mainland: RingIndex::new(MAINLAND),
+        hainan: RingIndex::new(HAINAN),
+        taiwan: RingIndex::new(TAIWAN),
+        hong_kong: RingIndex::new(HONG_KONG),
+        macau: RingIndex::new(MACAU),
+    })
+}
+
+fn in_border(lat: f64, lng: f64) -> bool {
+    let index = boundary();
+    index.mainland.contains(lat, lng)
+        || index.hainan.contains(lat, lng)
+        || index.taiwan.contains(lat, lng)
+}
+
+fn in_border_excluding_hk_mo_tw(lat: f64, lng: f64) -> bool {
+    let index = boundary();
+    (index.mainland.contains(lat, lng) || index.hainan.contains(lat, lng))
+        && !index.hong_kong.contains(lat, lng)
+        && !index.macau.contains(lat, lng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEIJING: (f64, f64) = (39.915, 116.404);
+    const SHANGHAI: (f64, f64) = (31.23, 121.47);
+    const URUMQI: (f64, f64) = (43.83, 87.62);
+    const LHASA: (f64, f64) = (29.65, 91.1);
+    const HAIKOU: (f64, f64) = (20.03, 110.33);
+    const SHENZHEN: (f64, f64) = (22.54, 114.06);
+    const HONG_KONG_CENTRAL: (f64, f64) = (22.28, 114.16);
+    const MACAU_PENINSULA: (f64, f64) = (22.19, 113.55);
+    const TAIPEI: (f64, f64) = (25.03, 121.56);
+    const ULAANBAATAR: (f64, f64) = (47.92, 106.92);
+    const HANOI: (f64, f64) = (21.03, 105.85);
+    const PYONGYANG: (f64, f64) = (39.03, 125.75);
+    const SEOUL: (f64, f64) = (37.57, 126.98);
+
+    fn check(zone: OffsetZone, inside: &[(f64, f64)], outside: &[(f64, f64)]) {
+        for &(lat, lng) in inside {
+            assert!(
+                zone.contains(lat, lng),
+                "{zone:?} should contain ({lat}, {lng})"
+            );
+        }
+        for &(lat, lng) in outside {
+            assert!(
+                !zone.contains(lat, lng),
+                "{zone:?} should skip ({lat}, {lng})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rectangle_keeps_neighbours() {
+        check(
+            OffsetZone::Rectangle,
+            &[
+                BEIJING,
+                HONG_KONG_CENTRAL,
+                TAIPEI,
+                ULAANBAATAR,
+                HANOI,
+                SEOUL,
+            ],
+            &[(30.0, -120.0), (35.68, 139.76)],
+        );
+    }
+
+    #[test]
+    fn test_border_skips_neighbours() {
+        check(
+            OffsetZone::Border,
+            &[
+                BEIJING,
+                SHANGHAI,
+                URUMQI,
+                LHASA,
+                HAIKOU,
+                SHENZHEN,
+                HONG_KONG_CENTRAL,
+                MACAU_PENINSULA,
+                TAIPEI,
+            ],
+            &[ULAANBAATAR, HANOI, PYONGYANG, SEOUL, (30.0, -120.0)],
+        );
+    }
+
+    #[test]
+    fn test_skip_hk_mo_tw() {
+        check(
+            OffsetZone::SkipHkMoTw,
+            &[BEIJING, SHANGHAI, HAIKOU, SHENZHEN],
+            &[
+                HONG_KONG_CENTRAL,
+                MACAU_PENINSULA,
+                TAIPEI,
+                ULAANBAATAR,
+                HANOI,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_never_skip() {
+        check(OffsetZone::NeverSkip, &[(30.0, -120.0), ULAANBAATAR], &[]);
+    }
+
+    #[test]
+    fn test_parse_offset_zone() {
+        assert_eq!(" Border ".parse(), Ok(OffsetZone::Border));
+        assert_eq!("skip_hk_mo_tw".parse(), Ok(OffsetZone::SkipHkMoTw));
+        assert!("nowhere".parse::<OffsetZone>().is_err());
+    }
+
+    #[test]
+    fn test_border_near_neighbours() {
+        check(
+            OffsetZone::Border,
+            &[
+                (39.47, 75.99),
+                (52.97, 122.54),
+                (41.8, 123.43),
+                (22.0, 100.8),
+                (25.04, 102.71),
+                (45.75, 126.65),
+            ],
+            &[
+                (17.97, 102.6),
+                (27.7, 85.3),
+                (27.47, 89.64),
+                (43.12, 131.9),
+                (48.48, 135.08),
+                (43.24, 76.9),
+                (42.9, 74.6),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_grid_index_matches_ray_casting() {
+        let rings = [MAINLAND, HAINAN, TAIWAN, HONG_KONG, MACAU];
+        for ring in rings {
+            let index = RingIndex::new(ring);
+            let span = (index.max_lng - index.min_lng).max(index.max_lat - index.min_lat);
+            let step = span / 301.0;
+            let mut lat = index.min_lat - step;
+            while lat <= index.max_lat + step {
+                let mut lng = index.min_lng - step;
+                while lng <= index.max_lng + step {
+                    assert_eq!(
+                        index.contains(lat, lng),
+                        ring_contains(ring, lat, lng),
+                        "({lat}, {lng})"
+                    );
+                    lng += step;
+                }
+                lat += step;
+            }
+        }
+    }
+}